@@ -0,0 +1,19 @@
+#version 450
+
+layout (location = 0) in vec2 o_uv;
+
+// Material parameters in the order the material declares them, one vec4 each.
+layout (set = 1, binding = 0) uniform Material {
+    vec4 base_color;
+} material;
+
+// Sampler of every texture, followed by an array with the texture slots in the order the material declares them.
+// Empty slots are white. Only the first slot is sampled, so it is declared on its own.
+layout (set = 1, binding = 1) uniform sampler texture_sampler;
+layout (set = 1, binding = 2) uniform texture2D base_color_texture;
+
+layout (location = 0) out vec4 uFragColor;
+
+void main() {
+    uFragColor = vec4(o_uv, 0.5, 1.0) * texture(sampler2D(base_color_texture, texture_sampler), o_uv) * material.base_color;
+}
@@ -0,0 +1,20 @@
+#version 450
+
+layout (location = 0) in vec4 pos;
+layout (location = 1) in vec2 uv;
+
+layout (set = 0, binding = 0) uniform Camera {
+    mat4 view;
+    mat4 projection;
+} camera;
+
+layout (push_constant) uniform Object {
+    mat4 model;
+} object;
+
+layout (location = 0) out vec2 o_uv;
+
+void main() {
+    o_uv = uv;
+    gl_Position = camera.projection * camera.view * object.model * pos;
+}
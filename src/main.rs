@@ -8,11 +8,24 @@ use crate::system::layer::System;
 
 
 fn main() {
+    let model_path = std::env::args().skip_while(|arg| arg != "--model").nth(1);
+    let material_path = std::env::args().skip_while(|arg| arg != "--material").nth(1);
+    let convert_paths: Vec<String> = std::env::args().skip_while(|arg| arg != "--convert").skip(1).take(2).collect();
+    if let [input_path, output_path] = convert_paths.as_slice() {
+        System::convert_model("", input_path, output_path);
+        return;
+    }
+    if std::env::args().any(|arg| arg == "--headless") {
+        let use_software_renderer = std::env::args().any(|arg| arg == "--software");
+        System::render_headless("", "headless_frame.ppm", use_software_renderer, model_path.as_deref(), material_path.as_deref());
+        return;
+    }
+
     let user_dirs = UserDirs::new();
     let desktop = user_dirs.unwrap().desktop_dir().unwrap().to_str().unwrap().to_owned();
 
-    let mut system = System::init(&desktop);
+    let system = System::init(&desktop);
     // system.init_logging(&desktop);
 
-    system.window.run_window_loop();
+    system.run(model_path.as_deref(), material_path.as_deref());
 }
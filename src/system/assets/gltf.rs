@@ -0,0 +1,591 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use gltf::Document;
+use gltf::material::AlphaMode;
+use gltf::mesh::Mode;
+use crate::system::log::Log;
+use crate::system::math::matrix::{Mat3, Mat4};
+use crate::system::math::quaternion::Quat;
+use crate::system::math::transform::Transform;
+use crate::system::math::vector::Vec3;
+use crate::system::rendering::material::{BASE_COLOR_PARAMETER, Material, MaterialInstance, MaterialParameter, MaterialTexture, MaterialValue};
+use crate::system::rendering::mesh::mesh::Mesh;
+use crate::system::rendering::mesh::processing::{compute_flat_normals, triangle_fan_to_list, triangle_strip_to_list};
+use crate::system::rendering::mesh::vertex::Vertex;
+
+/// Extensions the importer understands. Files requiring any other extension are rejected, files only using one get a warning.
+const SUPPORTED_EXTENSIONS: &[&str] = &["KHR_materials_unlit"];
+
+/// Everything that can go wrong while importing a gltf or glb file.
+#[derive(Debug)]
+pub enum GltfError {
+    Io { path: PathBuf, error: io::Error },
+    Parse { path: PathBuf, message: String },
+    UnsupportedExtensions { path: PathBuf, extensions: Vec<String> },
+    MissingData { path: PathBuf, message: String },
+}
+
+impl fmt::Display for GltfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GltfError::Io { path, error } => write!(f, "{}: {}", path.display(), error),
+            GltfError::Parse { path, message } => write!(f, "{}: {}", path.display(), message),
+            GltfError::UnsupportedExtensions { path, extensions } => write!(f, "{}: unsupported required extensions {}", path.display(), extensions.join(", ")),
+            GltfError::MissingData { path, message } => write!(f, "{}: {}", path.display(), message),
+        }
+    }
+}
+
+/// Texture used by a material slot, as index into `GltfModel::images` and the uv set it is sampled with.
+#[derive(Clone, Copy)]
+pub struct GltfTextureSlot {
+    pub image: usize,
+    pub tex_coord: u32,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum GltfAlphaMode {
+    Opaque,
+    Mask,
+    Blend,
+}
+
+/// Metallic roughness material parameters of the gltf core specification.
+pub struct GltfMaterial {
+    pub name: String,
+    pub base_color_factor: [f32; 4],
+    pub base_color_texture: Option<GltfTextureSlot>,
+    pub metallic_factor: f32,
+    pub roughness_factor: f32,
+    pub metallic_roughness_texture: Option<GltfTextureSlot>,
+    pub normal_texture: Option<GltfTextureSlot>,
+    pub normal_scale: f32,
+    pub occlusion_texture: Option<GltfTextureSlot>,
+    pub occlusion_strength: f32,
+    pub emissive_factor: [f32; 3],
+    pub emissive_texture: Option<GltfTextureSlot>,
+    pub alpha_mode: GltfAlphaMode,
+    pub alpha_cutoff: f32,
+    pub double_sided: bool,
+    pub unlit: bool,
+}
+
+impl GltfMaterial {
+    /// Material for the default shaders. Blended materials use the transparent pipeline.
+    /// Only textures in external files are referenced, embedded images have no path to point at.
+    pub fn to_material(&self, images: &[GltfImage]) -> Material {
+        let texture = |name: &str, slot: &Option<GltfTextureSlot>| MaterialTexture {
+            name: name.to_string(),
+            path: slot.and_then(|slot| match images.get(slot.image).map(|image| &image.source) {
+                Some(GltfImageSource::File(path)) => Some(path.clone()),
+                _ => None,
+            }),
+        };
+        let parameter = |name: &str, value: MaterialValue| MaterialParameter { name: name.to_string(), value };
+        Material {
+            name: self.name.clone(),
+            pipeline: if self.alpha_mode == GltfAlphaMode::Blend { "transparent" } else { "opaque" }.to_string(),
+            textures: vec![
+                texture(BASE_COLOR_PARAMETER, &self.base_color_texture),
+                texture("MetallicRoughness", &self.metallic_roughness_texture),
+                texture("Normal", &self.normal_texture),
+                texture("Occlusion", &self.occlusion_texture),
+                texture("Emissive", &self.emissive_texture),
+            ],
+            parameters: vec![
+                parameter(BASE_COLOR_PARAMETER, MaterialValue::Vec4(self.base_color_factor)),
+                parameter("Metallic", MaterialValue::Float(self.metallic_factor)),
+                parameter("Roughness", MaterialValue::Float(self.roughness_factor)),
+                parameter("Emissive", MaterialValue::Vec3(self.emissive_factor)),
+                parameter("NormalScale", MaterialValue::Float(self.normal_scale)),
+                parameter("OcclusionStrength", MaterialValue::Float(self.occlusion_strength)),
+                parameter("AlphaCutoff", MaterialValue::Float(if self.alpha_mode == GltfAlphaMode::Mask { self.alpha_cutoff } else { 0.0 })),
+            ],
+        }
+    }
+}
+
+/// Encoded image data. Nothing is decoded here, external files are only referenced.
+pub enum GltfImageSource {
+    File(PathBuf),
+    Embedded { mime_type: String, data: Vec<u8> },
+}
+
+pub struct GltfImage {
+    pub name: String,
+    pub source: GltfImageSource,
+}
+
+/// One draw call worth of geometry. The material indexes into `GltfModel::materials`, `None` is the gltf default material.
+pub struct GltfPrimitive {
+    pub mesh: Mesh,
+    pub material: Option<usize>,
+}
+
+pub struct GltfMesh {
+    pub name: String,
+    pub primitives: Vec<GltfPrimitive>,
+}
+
+/// Node of the scene tree. The transform is local to the parent, the rotation is a quaternion in x, y, z, w order.
+pub struct GltfNode {
+    pub name: String,
+    pub translation: [f32; 3],
+    pub rotation: [f32; 4],
+    pub scale: [f32; 3],
+    pub mesh: Option<usize>,
+    pub children: Vec<usize>,
+}
+
+pub struct GltfModel {
+    pub meshes: Vec<GltfMesh>,
+    pub nodes: Vec<GltfNode>,
+    /// Nodes of the default scene, or of the first scene if none is marked as default.
+    pub root_nodes: Vec<usize>,
+    pub materials: Vec<GltfMaterial>,
+    pub images: Vec<GltfImage>,
+}
+
+impl GltfModel {
+    /// Imports a .gltf or .glb file with all buffers it references. Images are collected as references and not decoded.
+    /// Point and line primitives are skipped with a warning, strips and fans are converted to triangle lists.
+    /// Primitives without normals get flat normals. Meshes reference an instance of their material, if they have one.
+    pub fn load(path: &Path) -> Result<GltfModel, GltfError> {
+        let bytes = fs::read(path).map_err(|error| GltfError::Io { path: path.to_path_buf(), error })?;
+        let gltf = gltf::Gltf::from_slice_without_validation(&bytes).map_err(|e| parse_error(path, e))?;
+
+        // Checked before the validation, which would only report a generic error for them.
+        let unsupported: Vec<String> = gltf.document.extensions_required()
+            .filter(|extension| !SUPPORTED_EXTENSIONS.contains(extension))
+            .map(|extension| extension.to_string())
+            .collect();
+        if !unsupported.is_empty() {
+            return Err(GltfError::UnsupportedExtensions { path: path.to_path_buf(), extensions: unsupported });
+        }
+        let ignored: Vec<&str> = gltf.document.extensions_used()
+            .filter(|extension| !SUPPORTED_EXTENSIONS.contains(extension))
+            .collect();
+        if !ignored.is_empty() {
+            Log::get().write_warning(format!("{}: ignoring unsupported extensions {}", path.display(), ignored.join(", ")).as_str());
+        }
+
+        let blob = gltf.blob;
+        let document = Document::from_json(gltf.document.into_json()).map_err(|e| parse_error(path, e))?;
+        let directory = path.parent().unwrap_or_else(|| Path::new(""));
+        let buffers = load_buffers(path, directory, &document, blob)?;
+
+        let mut meshes: Vec<GltfMesh> = Vec::new();
+        for mesh in document.meshes() {
+            meshes.push(load_mesh(path, &mesh, &buffers)?);
+        }
+
+        let nodes = document.nodes()
+            .map(|node| {
+                let (translation, rotation, scale) = node.transform().decomposed();
+                GltfNode {
+                    name: node.name().unwrap_or_default().to_string(),
+                    translation,
+                    rotation,
+                    scale,
+                    mesh: node.mesh().map(|mesh| mesh.index()),
+                    children: node.children().map(|child| child.index()).collect(),
+                }
+            })
+            .collect();
+
+        let root_nodes = document.default_scene()
+            .or_else(|| document.scenes().next())
+            .map(|scene| scene.nodes().map(|node| node.index()).collect())
+            .unwrap_or_default();
+
+        let materials: Vec<GltfMaterial> = document.materials().map(|material| load_material(&material)).collect();
+
+        let mut images = Vec::new();
+        for image in document.images() {
+            images.push(load_image(path, directory, &image, &buffers)?);
+        }
+
+        let instances: Vec<Arc<MaterialInstance>> = materials.iter()
+            .map(|material: &GltfMaterial| Arc::new(MaterialInstance::new(&material.name, Arc::new(material.to_material(&images)))))
+            .collect();
+        for primitive in meshes.iter_mut().flat_map(|mesh| mesh.primitives.iter_mut()) {
+            primitive.mesh.material = primitive.material.and_then(|index| instances.get(index)).cloned();
+        }
+
+        Ok(GltfModel {
+            meshes,
+            nodes,
+            root_nodes,
+            materials,
+            images,
+        })
+    }
+
+    /// Every primitive reachable from the root nodes, with the node transforms applied to positions and normals.
+    pub fn get_world_meshes(&self) -> Vec<Mesh> {
+        let mut meshes = Vec::new();
+        for root in self.root_nodes.iter() {
+            self.collect_world_meshes(*root, Mat4::IDENTITY, &mut meshes);
+        }
+        meshes
+    }
+
+    fn collect_world_meshes(&self, node_index: usize, parent: Mat4, meshes: &mut Vec<Mesh>) {
+        let node = &self.nodes[node_index];
+        let [x, y, z, w] = node.rotation;
+        let local = Transform { translation: Vec3::from(node.translation), rotation: Quat { x, y, z, w }, scale: Vec3::from(node.scale) };
+        let world = parent * local.to_matrix();
+
+        if let Some(mesh_index) = node.mesh {
+            let normal_matrix = Mat3::from_mat4(&world).cofactor();
+            for primitive in self.meshes[mesh_index].primitives.iter() {
+                let vertices: Vec<Vertex> = primitive.mesh.vertices.iter()
+                    .map(|vertex| Vertex {
+                        position: world.transform_point(Vec3::from(vertex.position)).into(),
+                        normal: (normal_matrix * Vec3::from(vertex.normal)).normalize_or_zero().into(),
+                        uv: vertex.uv,
+                    })
+                    .collect();
+                meshes.push(Mesh {
+                    vertices: vertices.into_boxed_slice(),
+                    indices: primitive.mesh.indices.clone(),
+                    faces: primitive.mesh.faces.clone(),
+                    material: primitive.mesh.material.clone(),
+                    lods: None,
+                });
+            }
+        }
+
+        for child in node.children.iter() {
+            self.collect_world_meshes(*child, world, meshes);
+        }
+    }
+}
+
+fn parse_error(path: &Path, error: gltf::Error) -> GltfError {
+    match error {
+        gltf::Error::Io(error) => GltfError::Io { path: path.to_path_buf(), error },
+        error => GltfError::Parse { path: path.to_path_buf(), message: error.to_string() },
+    }
+}
+
+fn missing_data(path: &Path, message: String) -> GltfError {
+    GltfError::MissingData { path: path.to_path_buf(), message }
+}
+
+/// Reads the binary chunk, external files and base64 data uris into one byte vector per buffer.
+fn load_buffers(path: &Path, directory: &Path, document: &Document, mut blob: Option<Vec<u8>>) -> Result<Vec<Vec<u8>>, GltfError> {
+    let mut buffers = Vec::new();
+    for buffer in document.buffers() {
+        let data = match buffer.source() {
+            gltf::buffer::Source::Bin => match blob.take() {
+                Some(data) => data,
+                None => return Err(missing_data(path, format!("buffer {} refers to a missing binary chunk", buffer.index()))),
+            },
+            gltf::buffer::Source::Uri(uri) => read_uri(path, directory, uri)?,
+        };
+        if data.len() < buffer.length() {
+            return Err(missing_data(path, format!("buffer {} has {} bytes, {} are expected", buffer.index(), data.len(), buffer.length())));
+        }
+        buffers.push(data);
+    }
+    Ok(buffers)
+}
+
+fn read_uri(path: &Path, directory: &Path, uri: &str) -> Result<Vec<u8>, GltfError> {
+    if let Some(data_uri) = uri.strip_prefix("data:") {
+        return match data_uri.split_once(";base64,") {
+            Some((_, data)) => base64::decode(data).map_err(|e| missing_data(path, format!("invalid base64 data uri: {}", e))),
+            None => Err(missing_data(path, "data uris must be base64 encoded".to_string())),
+        };
+    }
+
+    let file_path = directory.join(uri.replace("%20", " "));
+    fs::read(&file_path).map_err(|error| GltfError::Io { path: file_path, error })
+}
+
+fn load_mesh(path: &Path, mesh: &gltf::Mesh, buffers: &[Vec<u8>]) -> Result<GltfMesh, GltfError> {
+    let name = mesh.name().unwrap_or_default().to_string();
+    let mut primitives = Vec::new();
+
+    for primitive in mesh.primitives() {
+        let mode = primitive.mode();
+        if !matches!(mode, Mode::Triangles | Mode::TriangleStrip | Mode::TriangleFan) {
+            Log::get().write_warning(format!("{}: skipping {:?} primitive of mesh {}", path.display(), mode, name).as_str());
+            continue;
+        }
+
+        // The reader resolves strides, normalized and sparse accessors, so interleaved and separate attributes look the same.
+        let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|data| data.as_slice()));
+        let positions: Vec<[f32; 3]> = match reader.read_positions() {
+            Some(positions) => positions.collect(),
+            None => return Err(missing_data(path, format!("a primitive of mesh {} has no positions", name))),
+        };
+        let normals: Vec<[f32; 3]> = reader.read_normals().map(|normals| normals.collect()).unwrap_or_default();
+        let uvs: Vec<[f32; 2]> = reader.read_tex_coords(0).map(|uvs| uvs.into_f32().collect()).unwrap_or_default();
+
+        let vertices: Vec<Vertex> = positions.iter()
+            .enumerate()
+            .map(|(i, position)| Vertex {
+                position: *position,
+                normal: normals.get(i).copied().unwrap_or([0.0, 0.0, 0.0]),
+                uv: uvs.get(i).copied().unwrap_or([0.0, 0.0]),
+            })
+            .collect();
+
+        // u8, u16 and u32 indices are all widened to u32.
+        let indices: Vec<u32> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().collect(),
+            None if mode == Mode::Triangles => Vec::new(),
+            None => (0..vertices.len() as u32).collect(),
+        };
+        if let Some(index) = indices.iter().find(|&&index| index as usize >= vertices.len()) {
+            return Err(missing_data(path, format!("index {} of mesh {} is out of range, {} vertices are defined", index, name, vertices.len())));
+        }
+
+        let indices = match mode {
+            Mode::TriangleStrip => triangle_strip_to_list(&indices),
+            Mode::TriangleFan => triangle_fan_to_list(&indices),
+            _ => indices,
+        };
+
+        let mut mesh = Mesh {
+            vertices: vertices.into_boxed_slice(),
+            indices: indices.into_boxed_slice(),
+            faces: Box::new([]),
+            material: None,
+            lods: None,
+        };
+        // The specification asks for flat normals if a primitive has none.
+        if normals.is_empty() {
+            mesh = compute_flat_normals(&mesh);
+        }
+
+        primitives.push(GltfPrimitive {
+            mesh,
+            material: primitive.material().index(),
+        });
+    }
+
+    Ok(GltfMesh { name, primitives })
+}
+
+fn load_material(material: &gltf::Material) -> GltfMaterial {
+    let pbr = material.pbr_metallic_roughness();
+    let slot = |info: Option<gltf::texture::Info>| info.map(|info| GltfTextureSlot { image: info.texture().source().index(), tex_coord: info.tex_coord() });
+
+    GltfMaterial {
+        name: material.name().unwrap_or_default().to_string(),
+        base_color_factor: pbr.base_color_factor(),
+        base_color_texture: slot(pbr.base_color_texture()),
+        metallic_factor: pbr.metallic_factor(),
+        roughness_factor: pbr.roughness_factor(),
+        metallic_roughness_texture: slot(pbr.metallic_roughness_texture()),
+        normal_texture: material.normal_texture().map(|normal| GltfTextureSlot { image: normal.texture().source().index(), tex_coord: normal.tex_coord() }),
+        normal_scale: material.normal_texture().map(|normal| normal.scale()).unwrap_or(1.0),
+        occlusion_texture: material.occlusion_texture().map(|occlusion| GltfTextureSlot { image: occlusion.texture().source().index(), tex_coord: occlusion.tex_coord() }),
+        occlusion_strength: material.occlusion_texture().map(|occlusion| occlusion.strength()).unwrap_or(1.0),
+        emissive_factor: material.emissive_factor(),
+        emissive_texture: slot(material.emissive_texture()),
+        alpha_mode: match material.alpha_mode() {
+            AlphaMode::Opaque => GltfAlphaMode::Opaque,
+            AlphaMode::Mask => GltfAlphaMode::Mask,
+            AlphaMode::Blend => GltfAlphaMode::Blend,
+        },
+        alpha_cutoff: material.alpha_cutoff().unwrap_or(0.5),
+        double_sided: material.double_sided(),
+        unlit: material.unlit(),
+    }
+}
+
+fn load_image(path: &Path, directory: &Path, image: &gltf::Image, buffers: &[Vec<u8>]) -> Result<GltfImage, GltfError> {
+    let source = match image.source() {
+        gltf::image::Source::View { view, mime_type } => {
+            let buffer = &buffers[view.buffer().index()];
+            let end = view.offset() + view.length();
+            if end > buffer.len() {
+                return Err(missing_data(path, format!("image {} lies outside of its buffer", image.index())));
+            }
+            GltfImageSource::Embedded { mime_type: mime_type.to_string(), data: buffer[view.offset()..end].to_vec() }
+        }
+        gltf::image::Source::Uri { uri, mime_type } => {
+            if uri.starts_with("data:") {
+                let mime_type = mime_type
+                    .or_else(|| uri["data:".len()..].split(';').next())
+                    .unwrap_or_default()
+                    .to_string();
+                GltfImageSource::Embedded { mime_type, data: read_uri(path, directory, uri)? }
+            } else {
+                GltfImageSource::File(directory.join(uri.replace("%20", " ")))
+            }
+        }
+    };
+
+    Ok(GltfImage {
+        name: image.name().unwrap_or_default().to_string(),
+        source,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Three meshes of one triangle each: packed positions with u8 indices, interleaved positions and uvs with u16 indices
+    /// and packed positions with u32 indices. Node 0 has a translation, rotation and scale, its child node 1 a matrix.
+    /// Image 0 is an external file, image 1 a data uri and image 2 lies in the buffer.
+    const DOCUMENT: &str = r#"{
+        "asset": { "version": "2.0" },
+        "scene": 0,
+        "scenes": [{ "nodes": [0, 2] }],
+        "nodes": [
+            { "name": "trs", "mesh": 0, "translation": [1, 2, 3], "rotation": [0, 0.70710677, 0, 0.70710677], "scale": [2, 2, 2], "children": [1] },
+            { "name": "matrix", "mesh": 1, "matrix": [1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1, 0, 0, 0, 5, 1] },
+            { "name": "plain", "mesh": 2 }
+        ],
+        "meshes": [
+            { "name": "u8", "primitives": [{ "attributes": { "POSITION": 0, "NORMAL": 6 }, "indices": 3 }] },
+            { "name": "u16", "primitives": [{ "attributes": { "POSITION": 1, "TEXCOORD_0": 2, "NORMAL": 6 }, "indices": 4 }] },
+            { "name": "u32", "primitives": [{ "attributes": { "POSITION": 0, "NORMAL": 6 }, "indices": 5 }] }
+        ],
+        "accessors": [
+            { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0, 0, 0], "max": [1, 1, 0] },
+            { "bufferView": 1, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0, 0, 0], "max": [2, 2, 0] },
+            { "bufferView": 1, "byteOffset": 12, "componentType": 5126, "count": 3, "type": "VEC2" },
+            { "bufferView": 2, "componentType": 5121, "count": 3, "type": "SCALAR" },
+            { "bufferView": 3, "componentType": 5123, "count": 3, "type": "SCALAR" },
+            { "bufferView": 4, "componentType": 5125, "count": 3, "type": "SCALAR" },
+            { "bufferView": 5, "componentType": 5126, "count": 3, "type": "VEC3" }
+        ],
+        "bufferViews": [
+            { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+            { "buffer": 0, "byteOffset": 36, "byteLength": 60, "byteStride": 20 },
+            { "buffer": 0, "byteOffset": 96, "byteLength": 3 },
+            { "buffer": 0, "byteOffset": 100, "byteLength": 6 },
+            { "buffer": 0, "byteOffset": 108, "byteLength": 12 },
+            { "buffer": 0, "byteOffset": 120, "byteLength": 36 },
+            { "buffer": 0, "byteOffset": 156, "byteLength": 4 }
+        ],
+        "buffers": [{ BUFFER_URI "byteLength": 160 }],
+        "images": [
+            { "uri": "textures/albedo%20map.png" },
+            { "uri": "data:image/png;base64,AAEC" },
+            { "bufferView": 6, "mimeType": "image/png" }
+        ]
+    }"#;
+
+    fn build_buffer() -> Vec<u8> {
+        let mut buffer: Vec<u8> = Vec::new();
+        let floats = |buffer: &mut Vec<u8>, values: &[f32]| buffer.extend(values.iter().flat_map(|value| value.to_le_bytes()));
+        floats(&mut buffer, &[0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]);
+        floats(&mut buffer, &[0.0, 0.0, 0.0, 0.0, 0.0, 2.0, 0.0, 0.0, 1.0, 0.0, 0.0, 2.0, 0.0, 0.0, 1.0]);
+        buffer.extend_from_slice(&[0, 1, 2, 0]);
+        buffer.extend([2u16, 1, 0, 0].iter().flat_map(|index| index.to_le_bytes()));
+        buffer.extend([0u32, 2, 1].iter().flat_map(|index| index.to_le_bytes()));
+        floats(&mut buffer, &[0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0]);
+        buffer.extend_from_slice(&[1, 2, 3, 4]);
+        buffer
+    }
+
+    fn load(file_name: &str, data: &[u8]) -> Result<GltfModel, GltfError> {
+        let path = std::env::temp_dir().join(format!("resa_gltf_test_{}_{}", std::process::id(), file_name));
+        fs::write(&path, data).unwrap();
+        let model = GltfModel::load(&path);
+        fs::remove_file(&path).unwrap();
+        model
+    }
+
+    fn embedded_gltf() -> Vec<u8> {
+        let uri = format!("\"uri\": \"data:application/octet-stream;base64,{}\",", base64::encode(build_buffer()));
+        DOCUMENT.replace("BUFFER_URI", &uri).into_bytes()
+    }
+
+    fn glb() -> Vec<u8> {
+        let mut json = DOCUMENT.replace("BUFFER_URI", "").into_bytes();
+        json.resize(json.len().div_ceil(4) * 4, b' ');
+        let bin = build_buffer();
+        let mut glb = Vec::new();
+        glb.extend_from_slice(b"glTF");
+        glb.extend_from_slice(&2u32.to_le_bytes());
+        glb.extend_from_slice(&((12 + 8 + json.len() + 8 + bin.len()) as u32).to_le_bytes());
+        glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"JSON");
+        glb.extend_from_slice(&json);
+        glb.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"BIN\0");
+        glb.extend_from_slice(&bin);
+        glb
+    }
+
+    fn assert_near(a: [f32; 3], b: [f32; 3]) {
+        assert!(a.iter().zip(b.iter()).all(|(a, b)| (a - b).abs() < 1e-5), "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn embedded_and_binary_files_load_the_same_geometry() {
+        for model in [load("embedded.gltf", &embedded_gltf()).unwrap(), load("binary.glb", &glb()).unwrap()] {
+            let mesh = |index: usize| &model.meshes[index].primitives[0].mesh;
+            assert_eq!(&*mesh(0).indices, &[0, 1, 2]);
+            assert_eq!(&*mesh(1).indices, &[2, 1, 0]);
+            assert_eq!(&*mesh(2).indices, &[0, 2, 1]);
+
+            // Packed and interleaved accessors.
+            assert_eq!(mesh(0).vertices[1].position, [1.0, 0.0, 0.0]);
+            let interleaved: Vec<([f32; 3], [f32; 2])> = mesh(1).vertices.iter().map(|vertex| (vertex.position, vertex.uv)).collect();
+            assert_eq!(interleaved, vec![([0.0; 3], [0.0, 0.0]), ([2.0, 0.0, 0.0], [1.0, 0.0]), ([0.0, 2.0, 0.0], [0.0, 1.0])]);
+            assert!(mesh(1).vertices.iter().all(|vertex| vertex.normal == [0.0, 0.0, 1.0]));
+        }
+    }
+
+    #[test]
+    fn node_transforms_are_local_to_the_parent() {
+        let model = load("nodes.gltf", &embedded_gltf()).unwrap();
+        assert_eq!(model.root_nodes, vec![0, 2]);
+        assert_eq!(model.nodes[0].children, vec![1]);
+        assert_near(model.nodes[0].translation, [1.0, 2.0, 3.0]);
+        assert_near(model.nodes[0].scale, [2.0, 2.0, 2.0]);
+        assert_near(model.nodes[1].translation, [0.0, 0.0, 5.0]);
+        assert_near(model.nodes[1].scale, [1.0, 1.0, 1.0]);
+        assert_eq!(model.nodes[1].rotation, [0.0, 0.0, 0.0, 1.0]);
+
+        let meshes = model.get_world_meshes();
+        assert_eq!(meshes.len(), 3);
+        // Scaled by 2, turned by 90 degrees around y and moved by (1, 2, 3).
+        assert_near(meshes[0].vertices[1].position, [1.0, 2.0, 1.0]);
+        assert_near(meshes[0].vertices[1].normal, [1.0, 0.0, 0.0]);
+        // Moved by (0, 0, 5) first, then like its parent.
+        assert_near(meshes[1].vertices[1].position, [11.0, 2.0, -1.0]);
+        assert_near(meshes[2].vertices[1].position, [1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn images_are_referenced_or_embedded() {
+        let model = load("images.gltf", &embedded_gltf()).unwrap();
+        match &model.images[0].source {
+            GltfImageSource::File(path) => assert!(path.ends_with("textures/albedo map.png")),
+            GltfImageSource::Embedded { .. } => panic!("external image was embedded"),
+        }
+        match &model.images[1].source {
+            GltfImageSource::Embedded { mime_type, data } => assert_eq!((mime_type.as_str(), data.as_slice()), ("image/png", &[0u8, 1, 2][..])),
+            GltfImageSource::File(_) => panic!("data uri was referenced as file"),
+        }
+        match &model.images[2].source {
+            GltfImageSource::Embedded { mime_type, data } => assert_eq!((mime_type.as_str(), data.as_slice()), ("image/png", &[1u8, 2, 3, 4][..])),
+            GltfImageSource::File(_) => panic!("buffer view was referenced as file"),
+        }
+    }
+
+    #[test]
+    fn unknown_required_extensions_are_reported_by_name() {
+        let document = String::from_utf8(embedded_gltf()).unwrap()
+            .replacen('{', r#"{ "extensionsUsed": ["EXT_made_up"], "extensionsRequired": ["EXT_made_up"],"#, 1);
+        match load("extensions.gltf", document.as_bytes()) {
+            Err(e @ GltfError::UnsupportedExtensions { .. }) => assert!(e.to_string().contains("EXT_made_up")),
+            Err(e) => panic!("unexpected error {}", e),
+            Ok(_) => panic!("unknown required extension was accepted"),
+        }
+    }
+}
@@ -0,0 +1,274 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex, Weak};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+use crate::system::assets::model::Model;
+use crate::system::assets::shader::ShaderCode;
+use crate::system::assets::texture::TextureImage;
+use crate::system::rendering::material::MaterialInstance;
+
+/// Upper limit of worker threads, loading is mostly bound by the disk.
+const MAX_WORKER_THREADS: usize = 4;
+
+/// Something the asset manager can load from a file on a worker thread.
+pub trait Asset: Send + Sync + Sized + 'static {
+    /// Name of the kind of asset, used in messages.
+    const KIND: &'static str;
+    fn load(path: &Path) -> Result<Self, String>;
+}
+
+impl Asset for Model {
+    const KIND: &'static str = "model";
+
+    fn load(path: &Path) -> Result<Self, String> {
+        Model::load(path)
+    }
+}
+
+impl Asset for TextureImage {
+    const KIND: &'static str = "texture";
+
+    fn load(path: &Path) -> Result<Self, String> {
+        TextureImage::load(path).map_err(|e| e.to_string())
+    }
+}
+
+impl Asset for ShaderCode {
+    const KIND: &'static str = "shader";
+
+    fn load(path: &Path) -> Result<Self, String> {
+        ShaderCode::load(path).map_err(|e| e.to_string())
+    }
+}
+
+impl Asset for MaterialInstance {
+    const KIND: &'static str = "material";
+
+    fn load(path: &Path) -> Result<Self, String> {
+        MaterialInstance::load(path).map_err(|e| e.to_string())
+    }
+}
+
+pub enum AssetState<T> {
+    Loading,
+    Ready(Arc<T>),
+    /// Loading failed with the given message. The asset is not loaded again until every handle to it is dropped.
+    Failed(String),
+}
+
+impl<T> Clone for AssetState<T> {
+    fn clone(&self) -> Self {
+        match self {
+            AssetState::Loading => AssetState::Loading,
+            AssetState::Ready(asset) => AssetState::Ready(asset.clone()),
+            AssetState::Failed(message) => AssetState::Failed(message.clone()),
+        }
+    }
+}
+
+struct AssetSlot<T> {
+    path: PathBuf,
+    state: Mutex<AssetState<T>>,
+    finished: Condvar,
+}
+
+/// Typed, reference counted handle to an asset of the manager. The asset is unloaded when the last handle is dropped.
+pub struct Handle<T: Asset> {
+    slot: Arc<AssetSlot<T>>,
+}
+
+impl<T: Asset> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Handle { slot: self.slot.clone() }
+    }
+}
+
+impl<T: Asset> Handle<T> {
+    /// Path the asset was first requested with.
+    pub fn get_path(&self) -> &Path {
+        &self.slot.path
+    }
+
+    pub fn get_state(&self) -> AssetState<T> {
+        self.slot.state.lock().unwrap().clone()
+    }
+
+    /// Returns the asset if it is ready, without waiting.
+    pub fn get(&self) -> Option<Arc<T>> {
+        match &*self.slot.state.lock().unwrap() {
+            AssetState::Ready(asset) => Some(asset.clone()),
+            _ => None,
+        }
+    }
+
+    /// Blocks until the worker threads have finished loading the asset.
+    pub fn wait(&self) -> Result<Arc<T>, String> {
+        let mut state = self.slot.state.lock().unwrap();
+        loop {
+            match &*state {
+                AssetState::Loading => state = self.slot.finished.wait(state).unwrap(),
+                AssetState::Ready(asset) => return Ok(asset.clone()),
+                AssetState::Failed(message) => return Err(message.clone()),
+            }
+        }
+    }
+
+    /// Number of handles to the asset, including this one.
+    pub fn get_reference_count(&self) -> usize {
+        Arc::strong_count(&self.slot)
+    }
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Registry of all loaded assets. Loading an asset returns a handle at once, the file is read and decoded on worker threads.
+/// Assets are deduplicated by type and path: as long as a handle is alive, loading the same file again returns the same asset.
+pub struct AssetManager {
+    /// Weak references to the slots of all requested assets, by type and canonical path.
+    slots: Mutex<HashMap<(TypeId, PathBuf), Weak<dyn Any + Send + Sync>>>,
+    sender: Option<Sender<Job>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl AssetManager {
+    /// Starts one worker thread per spare cpu core, at least one and at most `MAX_WORKER_THREADS`.
+    pub fn new() -> Self {
+        let cpu_count = thread::available_parallelism().map(|count| count.get()).unwrap_or(1);
+        AssetManager::with_worker_threads((cpu_count.saturating_sub(1)).clamp(1, MAX_WORKER_THREADS))
+    }
+
+    pub fn with_worker_threads(worker_count: usize) -> Self {
+        let (sender, receiver) = channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let workers = (0..worker_count.max(1))
+            .map(|i| {
+                let receiver = receiver.clone();
+                thread::Builder::new()
+                    .name(format!("resa-assets-{}", i))
+                    .spawn(move || AssetManager::run_worker(&receiver))
+                    .expect("Spawning asset worker thread failed!")
+            })
+            .collect();
+
+        AssetManager {
+            slots: Mutex::new(HashMap::new()),
+            sender: Some(sender),
+            workers,
+        }
+    }
+
+    /// Returns the handle of the asset at the path and starts loading it, unless it is already loaded or loading.
+    pub fn load<T: Asset>(&self, path: &Path) -> Handle<T> {
+        // Different spellings of the same file share one asset. Missing files keep their path and fail on the worker.
+        let key = (TypeId::of::<T>(), fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()));
+        let mut slots = self.slots.lock().unwrap();
+        slots.retain(|_, slot| slot.strong_count() > 0);
+
+        if let Some(Ok(slot)) = slots.get(&key).and_then(|slot| slot.upgrade()).map(|slot| slot.downcast::<AssetSlot<T>>()) {
+            return Handle { slot };
+        }
+
+        let slot = Arc::new(AssetSlot {
+            path: path.to_path_buf(),
+            state: Mutex::new(AssetState::Loading),
+            finished: Condvar::new(),
+        });
+        let weak_slot: Weak<dyn Any + Send + Sync> = Arc::downgrade(&slot) as Weak<AssetSlot<T>>;
+        slots.insert(key, weak_slot);
+
+        // The job only holds a weak reference, so an asset whose handles are dropped before a worker gets to it is never loaded.
+        let job_slot = Arc::downgrade(&slot);
+        let job: Job = Box::new(move || {
+            if let Some(slot) = job_slot.upgrade() {
+                let result = panic::catch_unwind(AssertUnwindSafe(|| T::load(&slot.path)))
+                    .unwrap_or_else(|_| Err(format!("{}: the {} loader panicked", slot.path.display(), T::KIND)));
+                *slot.state.lock().unwrap() = match result {
+                    Ok(asset) => AssetState::Ready(Arc::new(asset)),
+                    Err(message) => AssetState::Failed(message),
+                };
+                slot.finished.notify_all();
+            }
+        });
+        self.sender.as_ref().unwrap().send(job).expect("Asset worker threads are gone!");
+
+        Handle { slot }
+    }
+
+    /// Number of assets that are loading or loaded and still have handles.
+    pub fn get_asset_count(&self) -> usize {
+        self.slots.lock().unwrap().values().filter(|slot| slot.strong_count() > 0).count()
+    }
+
+    fn run_worker(receiver: &Mutex<Receiver<Job>>) {
+        loop {
+            let job = match receiver.lock().unwrap().recv() {
+                Ok(job) => job,
+                Err(_) => return,
+            };
+            job();
+        }
+    }
+}
+
+impl Drop for AssetManager {
+    /// Lets the workers finish the queued jobs and waits for them.
+    fn drop(&mut self) {
+        self.sender = None;
+        for worker in self.workers.drain(..) {
+            worker.join().ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Text file, without any logging so the tests need no logger.
+    struct TextAsset(String);
+
+    impl Asset for TextAsset {
+        const KIND: &'static str = "text";
+
+        fn load(path: &Path) -> Result<Self, String> {
+            fs::read_to_string(path).map(TextAsset).map_err(|e| format!("{}: {}", path.display(), e))
+        }
+    }
+
+    #[test]
+    fn same_path_shares_one_asset() {
+        let manager = AssetManager::with_worker_threads(2);
+        let first = manager.load::<TextAsset>(Path::new("Cargo.toml"));
+        let second = manager.load::<TextAsset>(Path::new("./src/../Cargo.toml"));
+        assert!(Arc::ptr_eq(&first.wait().unwrap(), &second.wait().unwrap()));
+        assert_eq!(first.get_reference_count(), 2);
+        assert_eq!(manager.get_asset_count(), 1);
+        assert!(first.get().unwrap().0.contains("[package]"));
+    }
+
+    #[test]
+    fn dropped_assets_are_unloaded() {
+        let manager = AssetManager::with_worker_threads(1);
+        let handle = manager.load::<TextAsset>(Path::new("Cargo.toml"));
+        let asset = Arc::downgrade(&handle.wait().unwrap());
+        drop(handle);
+        assert!(asset.upgrade().is_none());
+        assert_eq!(manager.get_asset_count(), 0);
+
+        let handle = manager.load::<TextAsset>(Path::new("Cargo.toml"));
+        assert!(handle.wait().is_ok());
+    }
+
+    #[test]
+    fn failures_are_reported_per_handle() {
+        let manager = AssetManager::with_worker_threads(1);
+        let handle = manager.load::<TextAsset>(Path::new("does/not/exist.txt"));
+        assert!(matches!(handle.wait(), Err(message) if message.starts_with("does/not/exist.txt")));
+        assert!(matches!(handle.get_state(), AssetState::Failed(_)));
+        assert!(handle.get().is_none());
+    }
+}
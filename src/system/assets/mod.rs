@@ -0,0 +1,6 @@
+pub mod obj;
+pub mod gltf;
+pub mod texture;
+pub mod shader;
+pub mod model;
+pub mod manager;
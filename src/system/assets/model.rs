@@ -0,0 +1,48 @@
+use std::path::Path;
+use crate::system::assets::gltf::GltfModel;
+use crate::system::assets::obj::ObjModel;
+use crate::system::log::Log;
+use crate::system::rendering::mesh::binary::BINARY_MESH_EXTENSION;
+use crate::system::rendering::mesh::mesh::Mesh;
+
+/// Meshes of a model file as they are placed in the world, each with its material.
+pub struct Model {
+    pub meshes: Vec<Mesh>,
+}
+
+impl Model {
+    /// Loads an obj, gltf, glb or resamesh file, depending on the extension, and generates the levels of detail of its meshes.
+    pub fn load(path: &Path) -> Result<Model, String> {
+        let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or_default().to_lowercase();
+        let mut meshes = match extension.as_str() {
+            BINARY_MESH_EXTENSION => Mesh::read_binary(path).inspect(|meshes| {
+                Log::get().write(format!("Loaded {} with {} meshes", path.display(), meshes.len()).as_str());
+            }).map_err(|e| format!("{}: {}", path.display(), e))?,
+            "gltf" | "glb" => GltfModel::load(path).map(|model| {
+                Log::get().write(format!("Loaded {} with {} meshes, {} nodes, {} materials and {} images",
+                                         path.display(), model.meshes.len(), model.nodes.len(), model.materials.len(), model.images.len()).as_str());
+                model.get_world_meshes()
+            }).map_err(|e| e.to_string())?,
+            _ => ObjModel::load(path).map(|model| {
+                Log::get().write(format!("Loaded {} with {} objects and {} materials", path.display(), model.objects.len(), model.materials.len()).as_str());
+                for object in model.objects.iter() {
+                    Log::get().write(format!("Object {}: {} triangles, material {}", object.name, object.mesh.indices.len() / 3,
+                                             object.material.as_deref().unwrap_or("none")).as_str());
+                }
+                model.objects.into_iter().map(|object| object.mesh).collect()
+            }).map_err(|e| e.to_string())?,
+        };
+
+        for (i, mesh) in meshes.iter_mut().enumerate() {
+            if mesh.generate_lods() > 0 {
+                let triangle_counts: Vec<String> = mesh.lods.iter()
+                    .flat_map(|lods| lods.levels.iter())
+                    .map(|level| (level.indices.len() / 3).to_string())
+                    .collect();
+                Log::get().write(format!("Mesh {}: {} triangles, levels of detail with {} triangles",
+                                         i, mesh.indices.len() / 3, triangle_counts.join(", ")).as_str());
+            }
+        }
+        Ok(Model { meshes })
+    }
+}
@@ -0,0 +1,484 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use crate::system::rendering::material::{BASE_COLOR_PARAMETER, Material, MaterialInstance, MaterialParameter, MaterialTexture, MaterialValue};
+use crate::system::rendering::mesh::mesh::Mesh;
+use crate::system::rendering::mesh::processing::compute_smooth_normals;
+use crate::system::rendering::mesh::vertex::Vertex;
+
+/// Everything that can go wrong while importing an obj file or one of its material libraries.
+#[derive(Debug)]
+pub enum ObjError {
+    Io { path: PathBuf, error: io::Error },
+    Parse { path: PathBuf, line: usize, message: String },
+    NoFaces { path: PathBuf },
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjError::Io { path, error } => write!(f, "{}: {}", path.display(), error),
+            ObjError::Parse { path, line, message } => write!(f, "{}:{}: {}", path.display(), line, message),
+            ObjError::NoFaces { path } => write!(f, "{}: the file contains no faces", path.display()),
+        }
+    }
+}
+
+/// Material as described by a `newmtl` block of an mtl file. Texture paths are resolved relative to the mtl file.
+pub struct ObjMaterial {
+    pub name: String,
+    pub ambient: [f32; 3],
+    pub diffuse: [f32; 3],
+    pub specular: [f32; 3],
+    pub shininess: f32,
+    pub dissolve: f32,
+    pub diffuse_texture: Option<PathBuf>,
+    pub specular_texture: Option<PathBuf>,
+    pub normal_texture: Option<PathBuf>,
+}
+
+impl ObjMaterial {
+    fn new(name: &str) -> Self {
+        ObjMaterial {
+            name: name.to_string(),
+            ambient: [0.0, 0.0, 0.0],
+            diffuse: [1.0, 1.0, 1.0],
+            specular: [0.0, 0.0, 0.0],
+            shininess: 0.0,
+            dissolve: 1.0,
+            diffuse_texture: None,
+            specular_texture: None,
+            normal_texture: None,
+        }
+    }
+
+    /// Material for the default shaders. Partly transparent materials use the transparent pipeline.
+    pub fn to_material(&self) -> Material {
+        let [red, green, blue] = self.diffuse;
+        let texture = |name: &str, path: &Option<PathBuf>| MaterialTexture { name: name.to_string(), path: path.clone() };
+        let parameter = |name: &str, value: MaterialValue| MaterialParameter { name: name.to_string(), value };
+        Material {
+            name: self.name.clone(),
+            pipeline: if self.dissolve < 1.0 { "transparent" } else { "opaque" }.to_string(),
+            textures: vec![
+                texture(BASE_COLOR_PARAMETER, &self.diffuse_texture),
+                texture("Specular", &self.specular_texture),
+                texture("Normal", &self.normal_texture),
+            ],
+            parameters: vec![
+                parameter(BASE_COLOR_PARAMETER, MaterialValue::Vec4([red, green, blue, self.dissolve])),
+                parameter("Ambient", MaterialValue::Vec3(self.ambient)),
+                parameter("Specular", MaterialValue::Vec3(self.specular)),
+                parameter("Shininess", MaterialValue::Float(self.shininess)),
+            ],
+        }
+    }
+}
+
+/// One part of the model. A new object starts at every `o` or `g` statement and whenever the material changes.
+pub struct ObjObject {
+    pub name: String,
+    pub material: Option<String>,
+    pub mesh: Mesh,
+}
+
+pub struct ObjModel {
+    pub objects: Vec<ObjObject>,
+    pub materials: Vec<ObjMaterial>,
+}
+
+impl ObjModel {
+    /// Imports the obj file and every material library it references.
+    /// Polygons are triangulated as fans and every distinct position/uv/normal combination becomes one vertex.
+    /// The v coordinate is flipped, so uvs have their origin in the top left corner like vulkan images.
+    /// Objects with corners that have no normal get smooth normals for all of their vertices.
+    /// Every mesh references an instance of its mtl material, objects sharing a material share the instance.
+    pub fn load(path: &Path) -> Result<ObjModel, ObjError> {
+        let source = read_to_string(path)?;
+        let mut parser = ObjParser::new(path);
+        for (line_index, line) in source.lines().enumerate() {
+            parser.parse_line(line_index + 1, line)?;
+        }
+        parser.finish()
+    }
+}
+
+/// Indices of a face corner into the position, uv and normal lists.
+type Corner = (usize, Option<usize>, Option<usize>);
+
+struct ObjectBuilder {
+    name: String,
+    material: Option<String>,
+    vertices: Vec<Vertex>,
+    indices: Vec<u32>,
+    corner_lookup: HashMap<Corner, u32>,
+    missing_normals: bool,
+}
+
+impl ObjectBuilder {
+    fn new(name: &str, material: Option<String>) -> Self {
+        ObjectBuilder {
+            name: name.to_string(),
+            material,
+            vertices: Vec::new(),
+            indices: Vec::new(),
+            corner_lookup: HashMap::new(),
+            missing_normals: false,
+        }
+    }
+
+    fn build(self) -> ObjObject {
+        let mut mesh = Mesh {
+            vertices: self.vertices.into_boxed_slice(),
+            indices: self.indices.into_boxed_slice(),
+            faces: Box::new([]),
+            material: None,
+            lods: None,
+        };
+        if self.missing_normals {
+            compute_smooth_normals(&mut mesh);
+        }
+
+        ObjObject {
+            name: self.name,
+            material: self.material,
+            mesh,
+        }
+    }
+}
+
+struct ObjParser<'a> {
+    path: &'a Path,
+    positions: Vec<[f32; 3]>,
+    uvs: Vec<[f32; 2]>,
+    normals: Vec<[f32; 3]>,
+    materials: Vec<ObjMaterial>,
+    objects: Vec<ObjObject>,
+    current: ObjectBuilder,
+}
+
+impl<'a> ObjParser<'a> {
+    fn new(path: &'a Path) -> Self {
+        ObjParser {
+            path,
+            positions: Vec::new(),
+            uvs: Vec::new(),
+            normals: Vec::new(),
+            materials: Vec::new(),
+            objects: Vec::new(),
+            current: ObjectBuilder::new("default", None),
+        }
+    }
+
+    fn parse_line(&mut self, line_number: usize, line: &str) -> Result<(), ObjError> {
+        let line = strip_comment(line);
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => return Ok(()),
+        };
+        let arguments: Vec<&str> = tokens.collect();
+
+        match keyword {
+            "v" => {
+                let values = parse_floats(self.path, line_number, &arguments, 3)?;
+                self.positions.push([values[0], values[1], values[2]]);
+            }
+            "vt" => {
+                let values = parse_floats(self.path, line_number, &arguments, 1)?;
+                let v = values.get(1).copied().unwrap_or(0.0);
+                self.uvs.push([values[0], 1.0 - v]);
+            }
+            "vn" => {
+                let values = parse_floats(self.path, line_number, &arguments, 3)?;
+                self.normals.push([values[0], values[1], values[2]]);
+            }
+            "f" => self.parse_face(line_number, &arguments)?,
+            "o" | "g" => {
+                let name = if arguments.is_empty() { "default".to_string() } else { arguments.join(" ") };
+                let material = self.current.material.clone();
+                self.start_object(&name, material);
+            }
+            "usemtl" => {
+                let name = arguments.join(" ");
+                if !self.materials.iter().any(|material| material.name == name) {
+                    return Err(parse_error(self.path, line_number, format!("unknown material '{}'", name)));
+                }
+                if self.current.indices.is_empty() {
+                    self.current.material = Some(name);
+                } else {
+                    let object_name = self.current.name.clone();
+                    self.start_object(&object_name, Some(name));
+                }
+            }
+            "mtllib" => {
+                if arguments.is_empty() {
+                    return Err(parse_error(self.path, line_number, "mtllib without a file name".to_string()));
+                }
+                let directory = self.path.parent().unwrap_or_else(|| Path::new(""));
+                for file_name in arguments.iter() {
+                    let mut materials = load_material_library(&directory.join(file_name))?;
+                    self.materials.append(&mut materials);
+                }
+            }
+            // Smoothing groups, lines, points and free form geometry are not supported and skipped.
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn parse_face(&mut self, line_number: usize, arguments: &[&str]) -> Result<(), ObjError> {
+        if arguments.len() < 3 {
+            return Err(parse_error(self.path, line_number, format!("face with {} corners, at least 3 are needed", arguments.len())));
+        }
+
+        let mut corner_indices = Vec::with_capacity(arguments.len());
+        for argument in arguments.iter() {
+            let corner = self.parse_corner(line_number, argument)?;
+            corner_indices.push(self.get_vertex_index(corner));
+        }
+
+        for i in 1..corner_indices.len() - 1 {
+            self.current.indices.extend_from_slice(&[corner_indices[0], corner_indices[i], corner_indices[i + 1]]);
+        }
+        Ok(())
+    }
+
+    /// Parses one corner of a face in the form `v`, `v/vt`, `v//vn` or `v/vt/vn`.
+    fn parse_corner(&self, line_number: usize, argument: &str) -> Result<Corner, ObjError> {
+        let mut parts = argument.split('/');
+        let position = match parts.next() {
+            Some(part) if !part.is_empty() => resolve_index(self.path, line_number, part, self.positions.len())?,
+            _ => return Err(parse_error(self.path, line_number, format!("face corner '{}' has no position", argument))),
+        };
+        let uv = match parts.next() {
+            Some(part) if !part.is_empty() => Some(resolve_index(self.path, line_number, part, self.uvs.len())?),
+            _ => None,
+        };
+        let normal = match parts.next() {
+            Some(part) if !part.is_empty() => Some(resolve_index(self.path, line_number, part, self.normals.len())?),
+            _ => None,
+        };
+        if parts.next().is_some() {
+            return Err(parse_error(self.path, line_number, format!("face corner '{}' has too many components", argument)));
+        }
+        Ok((position, uv, normal))
+    }
+
+    fn get_vertex_index(&mut self, corner: Corner) -> u32 {
+        if let Some(index) = self.current.corner_lookup.get(&corner) {
+            return *index;
+        }
+
+        let (position, uv, normal) = corner;
+        self.current.missing_normals |= normal.is_none();
+        let vertex = Vertex {
+            position: self.positions[position],
+            normal: normal.map(|index| self.normals[index]).unwrap_or([0.0, 0.0, 0.0]),
+            uv: uv.map(|index| self.uvs[index]).unwrap_or([0.0, 0.0]),
+        };
+        let index = self.current.vertices.len() as u32;
+        self.current.vertices.push(vertex);
+        self.current.corner_lookup.insert(corner, index);
+        index
+    }
+
+    fn start_object(&mut self, name: &str, material: Option<String>) {
+        let previous = std::mem::replace(&mut self.current, ObjectBuilder::new(name, material));
+        if !previous.indices.is_empty() {
+            self.objects.push(previous.build());
+        }
+    }
+
+    fn finish(mut self) -> Result<ObjModel, ObjError> {
+        self.start_object("", None);
+        if self.objects.is_empty() {
+            return Err(ObjError::NoFaces { path: self.path.to_path_buf() });
+        }
+
+        let instances: HashMap<&str, Arc<MaterialInstance>> = self.materials.iter()
+            .map(|material| (material.name.as_str(), Arc::new(MaterialInstance::new(&material.name, Arc::new(material.to_material())))))
+            .collect();
+        for object in self.objects.iter_mut() {
+            object.mesh.material = object.material.as_deref().and_then(|name| instances.get(name)).cloned();
+        }
+
+        Ok(ObjModel {
+            objects: self.objects,
+            materials: self.materials,
+        })
+    }
+}
+
+/// Reads every material of an mtl file. Unknown statements are skipped.
+fn load_material_library(path: &Path) -> Result<Vec<ObjMaterial>, ObjError> {
+    let source = read_to_string(path)?;
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut materials: Vec<ObjMaterial> = Vec::new();
+
+    for (line_index, line) in source.lines().enumerate() {
+        let line_number = line_index + 1;
+        let line = strip_comment(line);
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let arguments: Vec<&str> = tokens.collect();
+
+        if keyword == "newmtl" {
+            if arguments.is_empty() {
+                return Err(parse_error(path, line_number, "newmtl without a name".to_string()));
+            }
+            materials.push(ObjMaterial::new(&arguments.join(" ")));
+            continue;
+        }
+
+        let material = match materials.last_mut() {
+            Some(material) => material,
+            None => return Err(parse_error(path, line_number, format!("'{}' before the first newmtl", keyword))),
+        };
+        match keyword {
+            "Ka" => material.ambient = parse_color(path, line_number, &arguments)?,
+            "Kd" => material.diffuse = parse_color(path, line_number, &arguments)?,
+            "Ks" => material.specular = parse_color(path, line_number, &arguments)?,
+            "Ns" => material.shininess = parse_floats(path, line_number, &arguments, 1)?[0],
+            "d" => material.dissolve = parse_floats(path, line_number, &arguments, 1)?[0],
+            "Tr" => material.dissolve = 1.0 - parse_floats(path, line_number, &arguments, 1)?[0],
+            "map_Kd" => material.diffuse_texture = Some(parse_texture_path(path, directory, line_number, &arguments)?),
+            "map_Ks" => material.specular_texture = Some(parse_texture_path(path, directory, line_number, &arguments)?),
+            "map_Bump" | "map_bump" | "bump" | "norm" => material.normal_texture = Some(parse_texture_path(path, directory, line_number, &arguments)?),
+            _ => {}
+        }
+    }
+    Ok(materials)
+}
+
+fn read_to_string(path: &Path) -> Result<String, ObjError> {
+    fs::read_to_string(path).map_err(|error| ObjError::Io { path: path.to_path_buf(), error })
+}
+
+fn parse_error(path: &Path, line: usize, message: String) -> ObjError {
+    ObjError::Parse { path: path.to_path_buf(), line, message }
+}
+
+fn strip_comment(line: &str) -> &str {
+    match line.find('#') {
+        Some(position) => &line[..position],
+        None => line,
+    }
+}
+
+/// Parses every argument as float and fails if there are less than `minimum_count` of them.
+fn parse_floats(path: &Path, line: usize, arguments: &[&str], minimum_count: usize) -> Result<Vec<f32>, ObjError> {
+    if arguments.len() < minimum_count {
+        return Err(parse_error(path, line, format!("expected {} values, found {}", minimum_count, arguments.len())));
+    }
+    arguments.iter()
+        .map(|argument| argument.parse::<f32>().map_err(|_| parse_error(path, line, format!("'{}' is not a number", argument))))
+        .collect()
+}
+
+/// Parses an rgb color. A single value is used for all three channels, as the mtl format allows.
+fn parse_color(path: &Path, line: usize, arguments: &[&str]) -> Result<[f32; 3], ObjError> {
+    let values = parse_floats(path, line, arguments, 1)?;
+    match values.len() {
+        1 | 2 => Ok([values[0], values[0], values[0]]),
+        _ => Ok([values[0], values[1], values[2]]),
+    }
+}
+
+/// The file name is the last argument, everything before it are texture options like `-bm 1.0`.
+fn parse_texture_path(path: &Path, directory: &Path, line: usize, arguments: &[&str]) -> Result<PathBuf, ObjError> {
+    match arguments.last() {
+        Some(file_name) => Ok(directory.join(file_name)),
+        None => Err(parse_error(path, line, "texture statement without a file name".to_string())),
+    }
+}
+
+/// Turns a one based or negative, relative obj index into an index into a list of the given length.
+fn resolve_index(path: &Path, line: usize, argument: &str, length: usize) -> Result<usize, ObjError> {
+    let index: i64 = argument.parse().map_err(|_| parse_error(path, line, format!("'{}' is not an index", argument)))?;
+    let resolved = if index > 0 { index - 1 } else { length as i64 + index };
+    if index == 0 || resolved < 0 || resolved >= length as i64 {
+        return Err(parse_error(path, line, format!("index {} is out of range, {} elements are defined", index, length)));
+    }
+    Ok(resolved as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes the files into a directory of their own and imports the first one.
+    fn load(test_name: &str, files: &[(&str, &str)]) -> Result<ObjModel, ObjError> {
+        let directory = std::env::temp_dir().join(format!("resa_obj_test_{}_{}", std::process::id(), test_name));
+        fs::create_dir_all(&directory).unwrap();
+        for (name, source) in files.iter() {
+            fs::write(directory.join(name), source).unwrap();
+        }
+        let model = ObjModel::load(&directory.join(files[0].0));
+        fs::remove_dir_all(&directory).unwrap();
+        model
+    }
+
+    const SQUARE: &str = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n";
+
+    fn assert_parse_error(result: Result<ObjModel, ObjError>, expected_line: usize) {
+        match result {
+            Err(ObjError::Parse { line, .. }) => assert_eq!(line, expected_line),
+            Err(e) => panic!("unexpected error {}", e),
+            Ok(_) => panic!("malformed data was accepted"),
+        }
+    }
+
+    #[test]
+    fn malformed_lines_are_errors() {
+        assert_parse_error(load("short_vertex", &[("a.obj", "v 1 2\n")]), 1);
+        assert_parse_error(load("vertex_text", &[("a.obj", "v 1 x 3\n")]), 1);
+        assert_parse_error(load("short_face", &[("a.obj", &format!("{}f 1 2\n", SQUARE))]), 5);
+        assert_parse_error(load("face_text", &[("a.obj", &format!("{}f 1 2 a\n", SQUARE))]), 5);
+        assert_parse_error(load("face_range", &[("a.obj", &format!("{}f 1 2 5\n", SQUARE))]), 5);
+        assert_parse_error(load("face_zero", &[("a.obj", &format!("{}f 0 1 2\n", SQUARE))]), 5);
+        assert_parse_error(load("face_relative_range", &[("a.obj", &format!("{}f -1 -2 -5\n", SQUARE))]), 5);
+        assert_parse_error(load("uv_range", &[("a.obj", &format!("{}vt 0 0\nf 1/2 2/1 3/1\n", SQUARE))]), 6);
+        assert!(matches!(load("no_faces", &[("a.obj", SQUARE)]), Err(ObjError::NoFaces { .. })));
+    }
+
+    #[test]
+    fn polygons_are_triangulated_with_relative_indices() {
+        let model = load("polygon", &[("a.obj", &format!("{}f -4 -3 -2 -1\n", SQUARE))]).unwrap();
+        assert_eq!(model.objects.len(), 1);
+        let mesh = &model.objects[0].mesh;
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(&*mesh.indices, &[0, 1, 2, 0, 2, 3]);
+        assert_eq!(mesh.vertices[2].position, [1.0, 1.0, 0.0]);
+        // Without normals in the file, smooth normals are computed.
+        assert!(mesh.vertices.iter().all(|vertex| vertex.normal[2].abs() > 0.99));
+    }
+
+    #[test]
+    fn objects_groups_and_materials_split_meshes() {
+        let mtl = "newmtl red\nKd 1 0 0\nnewmtl blue\nKd 0 0 1\nd 0.5\n";
+        let obj = format!("mtllib a.mtl\n{}o first\nusemtl red\nf 1 2 3\nusemtl blue\nf 1 3 4\ng second\nf 1 2 4\n", SQUARE);
+        let model = load("split", &[("a.obj", &obj), ("a.mtl", mtl)]).unwrap();
+
+        let names: Vec<(&str, Option<&str>)> = model.objects.iter().map(|object| (object.name.as_str(), object.material.as_deref())).collect();
+        assert_eq!(names, vec![("first", Some("red")), ("first", Some("blue")), ("second", Some("blue"))]);
+        assert!(model.objects.iter().all(|object| object.mesh.indices.len() == 3));
+        // Objects with the same material share its instance.
+        assert!(Arc::ptr_eq(model.objects[1].mesh.material.as_ref().unwrap(), model.objects[2].mesh.material.as_ref().unwrap()));
+        assert_eq!(model.materials[1].to_material().pipeline, "transparent");
+    }
+
+    #[test]
+    fn missing_material_libraries_are_errors() {
+        let obj = format!("mtllib missing.mtl\n{}f 1 2 3\n", SQUARE);
+        assert!(matches!(load("missing_mtl", &[("a.obj", &obj)]), Err(ObjError::Io { .. })));
+        assert_parse_error(load("unknown_material", &[("a.obj", &format!("{}usemtl red\nf 1 2 3\n", SQUARE))]), 5);
+        assert_parse_error(load("mtl_before_newmtl", &[("a.obj", "mtllib a.mtl\n"), ("a.mtl", "Kd 1 0 0\n")]), 1);
+    }
+}
@@ -0,0 +1,48 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const SPIRV_MAGIC_NUMBER: u32 = 0x0723_0203;
+
+/// Everything that can go wrong while reading a compiled shader.
+#[derive(Debug)]
+pub enum ShaderError {
+    Io { path: PathBuf, error: io::Error },
+    NoSpirv { path: PathBuf },
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShaderError::Io { path, error } => write!(f, "{}: {}", path.display(), error),
+            ShaderError::NoSpirv { path } => write!(f, "{}: the file is no SPIR-V module", path.display()),
+        }
+    }
+}
+
+/// Compiled SPIR-V module as it is passed to vulkan.
+pub struct ShaderCode {
+    pub code: Vec<u32>,
+}
+
+impl ShaderCode {
+    /// Reads a SPIR-V file. Modules written with the other byte order are swapped to the native one.
+    pub fn load(path: &Path) -> Result<ShaderCode, ShaderError> {
+        let bytes = fs::read(path).map_err(|error| ShaderError::Io { path: path.to_path_buf(), error })?;
+        if bytes.len() % 4 != 0 {
+            return Err(ShaderError::NoSpirv { path: path.to_path_buf() });
+        }
+
+        let mut code: Vec<u32> = bytes.chunks_exact(4)
+            .map(|word| u32::from_ne_bytes([word[0], word[1], word[2], word[3]]))
+            .collect();
+        if code.first() == Some(&SPIRV_MAGIC_NUMBER.swap_bytes()) {
+            code.iter_mut().for_each(|word| *word = word.swap_bytes());
+        }
+        if code.first() != Some(&SPIRV_MAGIC_NUMBER) {
+            return Err(ShaderError::NoSpirv { path: path.to_path_buf() });
+        }
+        Ok(ShaderCode { code })
+    }
+}
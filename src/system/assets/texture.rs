@@ -0,0 +1,179 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use image::ImageFormat;
+use crate::system::ini::{TextureFilter, TextureWrap};
+
+/// Everything that can go wrong while decoding a texture.
+#[derive(Debug)]
+pub enum TextureError {
+    Io { path: PathBuf, error: io::Error },
+    UnsupportedFormat { path: PathBuf },
+    Decode { path: PathBuf, message: String },
+}
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextureError::Io { path, error } => write!(f, "{}: {}", path.display(), error),
+            TextureError::UnsupportedFormat { path } => write!(f, "{}: only PNG, TGA and JPEG images are supported", path.display()),
+            TextureError::Decode { path, message } => write!(f, "{}: {}", path.display(), message),
+        }
+    }
+}
+
+/// Decoded image on the cpu. Pixels are stored row by row from the top left corner as RGBA with 8 bits per channel,
+/// so the top left pixel is at uv (0, 0).
+#[derive(Clone)]
+pub struct TextureImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl TextureImage {
+    /// Decodes a PNG, TGA or JPEG file. The format is taken from the extension, or guessed from the content if the extension is unknown.
+    /// Every pixel format is converted to RGBA8, TGA files stored bottom up are flipped.
+    pub fn load(path: &Path) -> Result<TextureImage, TextureError> {
+        let data = fs::read(path).map_err(|error| TextureError::Io { path: path.to_path_buf(), error })?;
+        let format = match ImageFormat::from_path(path).ok().or_else(|| image::guess_format(&data).ok()) {
+            Some(format @ (ImageFormat::Png | ImageFormat::Tga | ImageFormat::Jpeg)) => format,
+            _ => return Err(TextureError::UnsupportedFormat { path: path.to_path_buf() }),
+        };
+
+        let image = image::load_from_memory_with_format(&data, format)
+            .map_err(|e| TextureError::Decode { path: path.to_path_buf(), message: e.to_string() })?
+            .into_rgba8();
+        if image.width() == 0 || image.height() == 0 {
+            return Err(TextureError::Decode { path: path.to_path_buf(), message: "the image is empty".to_string() });
+        }
+
+        Ok(TextureImage {
+            width: image.width(),
+            height: image.height(),
+            pixels: image.into_raw(),
+        })
+    }
+
+    /// Single white pixel, used for texture slots without a texture.
+    pub fn white() -> Self {
+        TextureImage { width: 1, height: 1, pixels: vec![255; 4] }
+    }
+
+    /// Number of levels of a full mip chain, down to a size of one pixel.
+    pub fn get_mip_level_count(&self) -> u32 {
+        32 - self.width.max(self.height).max(1).leading_zeros()
+    }
+
+    /// Samples the image at the uv like a vulkan sampler without mip levels would. Channels are returned in [0, 1].
+    pub fn sample(&self, u: f32, v: f32, filter: TextureFilter, wrap: TextureWrap) -> [f32; 4] {
+        // Texel centers lie at half pixels.
+        let x = u * self.width as f32 - 0.5;
+        let y = v * self.height as f32 - 0.5;
+        match filter {
+            TextureFilter::Nearest => self.get_texel(x.round() as i64, y.round() as i64, wrap),
+            TextureFilter::Linear => {
+                let (x0, y0) = (x.floor(), y.floor());
+                let (tx, ty) = (x - x0, y - y0);
+                let (x0, y0) = (x0 as i64, y0 as i64);
+                let top = lerp(self.get_texel(x0, y0, wrap), self.get_texel(x0 + 1, y0, wrap), tx);
+                let bottom = lerp(self.get_texel(x0, y0 + 1, wrap), self.get_texel(x0 + 1, y0 + 1, wrap), tx);
+                lerp(top, bottom, ty)
+            }
+        }
+    }
+
+    fn get_texel(&self, x: i64, y: i64, wrap: TextureWrap) -> [f32; 4] {
+        let x = wrap_coordinate(x, self.width as i64, wrap);
+        let y = wrap_coordinate(y, self.height as i64, wrap);
+        let index = ((y * self.width as i64 + x) * 4) as usize;
+        [0, 1, 2, 3].map(|c| self.pixels[index + c] as f32 / 255.0)
+    }
+}
+
+/// Maps a texel coordinate outside of the image back into it.
+fn wrap_coordinate(coordinate: i64, size: i64, wrap: TextureWrap) -> i64 {
+    match wrap {
+        TextureWrap::Repeat => coordinate.rem_euclid(size),
+        TextureWrap::MirroredRepeat => {
+            let mirrored = coordinate.rem_euclid(2 * size);
+            if mirrored < size { mirrored } else { 2 * size - 1 - mirrored }
+        }
+        TextureWrap::ClampToEdge => coordinate.clamp(0, size - 1),
+    }
+}
+
+fn lerp(a: [f32; 4], b: [f32; 4], t: f32) -> [f32; 4] {
+    [0, 1, 2, 3].map(|c| a[c] + (b[c] - a[c]) * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encodes a 2x2 image with red, green, blue and white pixels and decodes it again.
+    fn round_trip(format: ImageFormat, extension: &str) -> TextureImage {
+        let pixels: Vec<u8> = vec![255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255, 255, 255, 255, 255];
+        let path = std::env::temp_dir().join(format!("resa_texture_test_{}.{}", std::process::id(), extension));
+        let image = image::RgbaImage::from_raw(2, 2, pixels).unwrap();
+        // JPEG has no alpha channel.
+        if format == ImageFormat::Jpeg {
+            image::DynamicImage::ImageRgba8(image).into_rgb8().save_with_format(&path, format).unwrap();
+        } else {
+            image.save_with_format(&path, format).unwrap();
+        }
+
+        let texture = TextureImage::load(&path);
+        fs::remove_file(&path).unwrap();
+        texture.unwrap()
+    }
+
+    #[test]
+    fn lossless_formats_keep_every_pixel() {
+        for (format, extension) in [(ImageFormat::Png, "png"), (ImageFormat::Tga, "tga")] {
+            let texture = round_trip(format, extension);
+            assert_eq!((texture.width, texture.height), (2, 2));
+            assert_eq!(texture.pixels, vec![255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255, 255, 255, 255, 255]);
+        }
+    }
+
+    #[test]
+    fn jpeg_is_converted_to_rgba() {
+        let texture = round_trip(ImageFormat::Jpeg, "jpg");
+        assert_eq!((texture.width, texture.height), (2, 2));
+        assert_eq!(texture.pixels.len(), 16);
+        assert!(texture.pixels.chunks_exact(4).all(|pixel| pixel[3] == 255));
+    }
+
+    #[test]
+    fn missing_and_unknown_files_are_errors() {
+        assert!(matches!(TextureImage::load(Path::new("does/not/exist.png")), Err(TextureError::Io { .. })));
+        assert!(matches!(TextureImage::load(Path::new("README.md")), Err(TextureError::UnsupportedFormat { .. })));
+    }
+
+    #[test]
+    fn mip_chain_ends_at_one_pixel() {
+        let texture = |width, height| TextureImage { width, height, pixels: Vec::new() };
+        assert_eq!(texture(1, 1).get_mip_level_count(), 1);
+        assert_eq!(texture(256, 256).get_mip_level_count(), 9);
+        assert_eq!(texture(300, 17).get_mip_level_count(), 9);
+    }
+
+    #[test]
+    fn sampling_follows_filter_and_wrap() {
+        // One row: black, white.
+        let texture = TextureImage { width: 2, height: 1, pixels: vec![0, 0, 0, 255, 255, 255, 255, 255] };
+        let red = |u: f32, filter, wrap| texture.sample(u, 0.5, filter, wrap)[0];
+
+        assert_eq!(red(0.3, TextureFilter::Nearest, TextureWrap::Repeat), 0.0);
+        assert_eq!(red(0.7, TextureFilter::Nearest, TextureWrap::Repeat), 1.0);
+        assert_eq!(red(0.5, TextureFilter::Linear, TextureWrap::ClampToEdge), 0.5);
+        assert_eq!(red(1.25, TextureFilter::Nearest, TextureWrap::Repeat), 0.0);
+        assert_eq!(red(1.25, TextureFilter::Nearest, TextureWrap::MirroredRepeat), 1.0);
+        assert_eq!(red(1.25, TextureFilter::Nearest, TextureWrap::ClampToEdge), 1.0);
+        // Between the last and the first texel the repeating sampler blends across the border.
+        assert_eq!(red(0.0, TextureFilter::Linear, TextureWrap::Repeat), 0.5);
+        assert_eq!(red(0.0, TextureFilter::Linear, TextureWrap::ClampToEdge), 0.0);
+    }
+}
@@ -0,0 +1,61 @@
+/// Handle of an entity. A handle of a despawned entity stays invalid, even if its index is reused.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Entity {
+    index: u32,
+    generation: u32,
+}
+
+impl Entity {
+    /// Position of the entity in the sparse arrays of the component storages.
+    pub fn get_index(&self) -> usize {
+        self.index as usize
+    }
+}
+
+/// Hands out entity handles and reuses the indices of despawned entities with a new generation.
+#[derive(Default)]
+pub struct EntityAllocator {
+    generations: Vec<u32>,
+    alive: Vec<bool>,
+    free_indices: Vec<u32>,
+    count: usize,
+}
+
+impl EntityAllocator {
+    pub fn allocate(&mut self) -> Entity {
+        self.count += 1;
+        match self.free_indices.pop() {
+            Some(index) => {
+                self.alive[index as usize] = true;
+                Entity { index, generation: self.generations[index as usize] }
+            }
+            None => {
+                self.generations.push(0);
+                self.alive.push(true);
+                Entity { index: (self.generations.len() - 1) as u32, generation: 0 }
+            }
+        }
+    }
+
+    /// Returns false if the entity was already freed.
+    pub fn free(&mut self, entity: Entity) -> bool {
+        if !self.is_alive(entity) {
+            return false;
+        }
+        let index = entity.index as usize;
+        self.alive[index] = false;
+        self.generations[index] = self.generations[index].wrapping_add(1);
+        self.free_indices.push(entity.index);
+        self.count -= 1;
+        true
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        let index = entity.index as usize;
+        index < self.alive.len() && self.alive[index] && self.generations[index] == entity.generation
+    }
+
+    pub fn get_count(&self) -> usize {
+        self.count
+    }
+}
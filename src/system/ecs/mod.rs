@@ -0,0 +1,5 @@
+pub mod entity;
+pub mod storage;
+pub mod query;
+pub mod world;
+pub mod schedule;
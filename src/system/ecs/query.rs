@@ -0,0 +1,79 @@
+use std::cell::{Ref, RefMut};
+use crate::system::ecs::entity::Entity;
+use crate::system::ecs::storage::SparseSet;
+use crate::system::ecs::world::World;
+
+/// Components a query visits: `&T`, `&mut T` or a tuple of up to four of them.
+pub trait Query {
+    /// Borrowed storages for the duration of the query.
+    type Borrow<'w>;
+    /// Components of one entity, handed to the callback of `World::query`.
+    type Item<'b>;
+
+    /// None if one of the storages does not exist, then no entity can match.
+    fn borrow(world: &World) -> Option<Self::Borrow<'_>>;
+    /// Entities to test, those of the smallest storage.
+    fn get_entities<'b>(borrow: &'b Self::Borrow<'_>) -> &'b [Entity];
+    /// None if the entity lacks one of the components.
+    fn fetch<'b>(borrow: &'b mut Self::Borrow<'_>, entity: Entity) -> Option<Self::Item<'b>>;
+}
+
+impl<T: 'static> Query for &T {
+    type Borrow<'w> = Ref<'w, SparseSet<T>>;
+    type Item<'b> = &'b T;
+
+    fn borrow(world: &World) -> Option<Self::Borrow<'_>> {
+        world.get_storage::<T>()
+    }
+
+    fn get_entities<'b>(borrow: &'b Self::Borrow<'_>) -> &'b [Entity] {
+        borrow.get_entities()
+    }
+
+    fn fetch<'b>(borrow: &'b mut Self::Borrow<'_>, entity: Entity) -> Option<Self::Item<'b>> {
+        borrow.get(entity)
+    }
+}
+
+impl<T: 'static> Query for &mut T {
+    type Borrow<'w> = RefMut<'w, SparseSet<T>>;
+    type Item<'b> = &'b mut T;
+
+    fn borrow(world: &World) -> Option<Self::Borrow<'_>> {
+        world.get_storage_mut::<T>()
+    }
+
+    fn get_entities<'b>(borrow: &'b Self::Borrow<'_>) -> &'b [Entity] {
+        borrow.get_entities()
+    }
+
+    fn fetch<'b>(borrow: &'b mut Self::Borrow<'_>, entity: Entity) -> Option<Self::Item<'b>> {
+        borrow.get_mut(entity)
+    }
+}
+
+macro_rules! impl_query_tuple {
+    ($($name:ident $index:tt),+) => {
+        impl<$($name: Query),+> Query for ($($name,)+) {
+            type Borrow<'w> = ($($name::Borrow<'w>,)+);
+            type Item<'b> = ($($name::Item<'b>,)+);
+
+            fn borrow(world: &World) -> Option<Self::Borrow<'_>> {
+                Some(($($name::borrow(world)?,)+))
+            }
+
+            fn get_entities<'b>(borrow: &'b Self::Borrow<'_>) -> &'b [Entity] {
+                [$($name::get_entities(&borrow.$index)),+].into_iter().min_by_key(|entities| entities.len()).unwrap()
+            }
+
+            fn fetch<'b>(borrow: &'b mut Self::Borrow<'_>, entity: Entity) -> Option<Self::Item<'b>> {
+                Some(($($name::fetch(&mut borrow.$index, entity)?,)+))
+            }
+        }
+    };
+}
+
+impl_query_tuple!(A 0);
+impl_query_tuple!(A 0, B 1);
+impl_query_tuple!(A 0, B 1, C 2);
+impl_query_tuple!(A 0, B 1, C 2, D 3);
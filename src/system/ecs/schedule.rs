@@ -0,0 +1,76 @@
+use crate::system::ecs::world::World;
+
+/// Stages run in this order once per frame. Systems within a stage run in the order they were added.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Stage {
+    /// Turns window events into input state and advances the frame time.
+    Input,
+    /// Gameplay: moves the camera and everything else in the world.
+    Update,
+    /// Collects what the renderer draws this frame from the world.
+    RenderExtract,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 3] = [Stage::Input, Stage::Update, Stage::RenderExtract];
+}
+
+struct SystemEntry {
+    name: String,
+    run: Box<dyn FnMut(&mut World)>,
+}
+
+/// Systems sorted into stages, run against a world every frame.
+#[derive(Default)]
+pub struct Schedule {
+    stages: [Vec<SystemEntry>; Stage::ALL.len()],
+}
+
+impl Schedule {
+    pub fn new() -> Self {
+        Schedule::default()
+    }
+
+    /// Adds the system to the end of the stage. The name shows up in the log.
+    pub fn add_system(&mut self, stage: Stage, name: &str, system: impl FnMut(&mut World) + 'static) {
+        self.stages[stage as usize].push(SystemEntry { name: name.to_string(), run: Box::new(system) });
+    }
+
+    /// Names of the systems of the stage, in the order they run.
+    pub fn get_system_names(&self, stage: Stage) -> Vec<&str> {
+        self.stages[stage as usize].iter().map(|system| system.name.as_str()).collect()
+    }
+
+    pub fn run_stage(&mut self, stage: Stage, world: &mut World) {
+        for system in self.stages[stage as usize].iter_mut() {
+            (system.run)(world);
+        }
+    }
+
+    /// Runs all stages, i.e. one frame.
+    pub fn run(&mut self, world: &mut World) {
+        for stage in Stage::ALL {
+            self.run_stage(stage, world);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stages_run_in_order() {
+        let mut world = World::new();
+        world.insert_resource(Vec::<&str>::new());
+        let mut schedule = Schedule::new();
+        schedule.add_system(Stage::RenderExtract, "extract", |world| world.resource_mut::<Vec<&str>>().push("extract"));
+        schedule.add_system(Stage::Update, "first", |world| world.resource_mut::<Vec<&str>>().push("first"));
+        schedule.add_system(Stage::Input, "input", |world| world.resource_mut::<Vec<&str>>().push("input"));
+        schedule.add_system(Stage::Update, "second", |world| world.resource_mut::<Vec<&str>>().push("second"));
+
+        schedule.run(&mut world);
+        assert_eq!(*world.resource::<Vec<&str>>(), vec!["input", "first", "second", "extract"]);
+        assert_eq!(schedule.get_system_names(Stage::Update), vec!["first", "second"]);
+    }
+}
@@ -0,0 +1,132 @@
+use std::any::Any;
+use crate::system::ecs::entity::Entity;
+
+/// No dense slot, the entity does not have the component.
+const EMPTY: u32 = u32::MAX;
+
+/// Components of one type in a sparse set. The components are packed densely, so iterating them touches no holes,
+/// and the sparse array maps entity indices to their dense slot, so lookups, inserts and removals are constant time.
+pub struct SparseSet<T> {
+    sparse: Vec<u32>,
+    entities: Vec<Entity>,
+    components: Vec<T>,
+}
+
+impl<T> Default for SparseSet<T> {
+    fn default() -> Self {
+        SparseSet { sparse: Vec::new(), entities: Vec::new(), components: Vec::new() }
+    }
+}
+
+impl<T> SparseSet<T> {
+    /// Adds the component to the entity, or replaces its component and returns the old one.
+    pub fn insert(&mut self, entity: Entity, component: T) -> Option<T> {
+        if let Some(slot) = self.get_slot(entity) {
+            return Some(std::mem::replace(&mut self.components[slot], component));
+        }
+
+        let index = entity.get_index();
+        if index >= self.sparse.len() {
+            self.sparse.resize(index + 1, EMPTY);
+        }
+        self.sparse[index] = self.entities.len() as u32;
+        self.entities.push(entity);
+        self.components.push(component);
+        None
+    }
+
+    /// Removes the component of the entity. The last component moves into the freed slot.
+    pub fn remove(&mut self, entity: Entity) -> Option<T> {
+        let slot = self.get_slot(entity)?;
+        self.sparse[entity.get_index()] = EMPTY;
+        let last = self.entities.len() - 1;
+        if slot != last {
+            self.sparse[self.entities[last].get_index()] = slot as u32;
+        }
+        self.entities.swap_remove(slot);
+        Some(self.components.swap_remove(slot))
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<&T> {
+        self.get_slot(entity).map(|slot| &self.components[slot])
+    }
+
+    pub fn get_mut(&mut self, entity: Entity) -> Option<&mut T> {
+        self.get_slot(entity).map(|slot| &mut self.components[slot])
+    }
+
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.get_slot(entity).is_some()
+    }
+
+    /// Entities that have the component, in the order of `get_components`.
+    pub fn get_entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    pub fn get_components(&self) -> &[T] {
+        &self.components
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    fn get_slot(&self, entity: Entity) -> Option<usize> {
+        let slot = *self.sparse.get(entity.get_index())?;
+        // The slot may belong to an older entity with the same index.
+        if slot == EMPTY || self.entities[slot as usize] != entity {
+            return None;
+        }
+        Some(slot as usize)
+    }
+}
+
+/// Storage of any component type, so the world can keep all of them in one map and remove despawned entities from each.
+pub trait ComponentStorage: Any {
+    fn remove_entity(&mut self, entity: Entity);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: 'static> ComponentStorage for SparseSet<T> {
+    fn remove_entity(&mut self, entity: Entity) {
+        self.remove(entity);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::ecs::entity::EntityAllocator;
+
+    #[test]
+    fn removal_keeps_the_components_packed() {
+        let mut entities = EntityAllocator::default();
+        let [a, b, c] = [entities.allocate(), entities.allocate(), entities.allocate()];
+        let mut set = SparseSet::default();
+        set.insert(a, 'a');
+        set.insert(b, 'b');
+        set.insert(c, 'c');
+
+        assert_eq!(set.remove(a), Some('a'));
+        assert_eq!(set.get_entities(), &[c, b]);
+        assert_eq!(set.get_components(), &['c', 'b']);
+        assert_eq!(set.get(c), Some(&'c'));
+        assert_eq!(set.insert(b, 'B'), Some('b'));
+
+        // A new entity with the index of a does not see a's component.
+        entities.free(a);
+        let d = entities.allocate();
+        assert_eq!(d.get_index(), a.get_index());
+        assert!(!set.contains(d) && !set.contains(a));
+    }
+}
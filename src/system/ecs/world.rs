@@ -0,0 +1,171 @@
+use std::any::{type_name, Any, TypeId};
+use std::cell::{Ref, RefCell, RefMut};
+use std::collections::HashMap;
+use crate::system::ecs::entity::{Entity, EntityAllocator};
+use crate::system::ecs::query::Query;
+use crate::system::ecs::storage::{ComponentStorage, SparseSet};
+
+/// Entities with their components and the resources, single values that systems share, like the input state or the camera.
+/// Storages and resources are borrowed separately at runtime, so a query can run while a system holds a resource.
+/// Borrowing the same type mutably twice at once panics.
+#[derive(Default)]
+pub struct World {
+    entities: EntityAllocator,
+    storages: HashMap<TypeId, RefCell<Box<dyn ComponentStorage>>>,
+    resources: HashMap<TypeId, RefCell<Box<dyn Any>>>,
+}
+
+impl World {
+    pub fn new() -> Self {
+        World::default()
+    }
+
+    /// Creates an entity without components.
+    pub fn spawn(&mut self) -> Entity {
+        self.entities.allocate()
+    }
+
+    /// Removes the entity with all of its components. Returns false if it was already despawned.
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        if !self.entities.free(entity) {
+            return false;
+        }
+        for storage in self.storages.values_mut() {
+            storage.get_mut().remove_entity(entity);
+        }
+        true
+    }
+
+    pub fn get_entity_count(&self) -> usize {
+        self.entities.get_count()
+    }
+
+    /// Adds the component to the entity, or replaces its component of the same type and returns the old one.
+    /// Components of despawned entities are dropped.
+    pub fn insert<T: 'static>(&mut self, entity: Entity, component: T) -> Option<T> {
+        if !self.entities.is_alive(entity) {
+            return None;
+        }
+        let storage = self.storages.entry(TypeId::of::<T>())
+            .or_insert_with(|| RefCell::new(Box::new(SparseSet::<T>::default())));
+        downcast_mut::<T>(storage.get_mut().as_mut()).insert(entity, component)
+    }
+
+    pub fn get<T: 'static>(&self, entity: Entity) -> Option<Ref<'_, T>> {
+        let storage = self.get_storage::<T>()?;
+        Ref::filter_map(storage, |storage| storage.get(entity)).ok()
+    }
+
+    /// Borrows the storage of the component type. None if no entity ever had such a component.
+    pub fn get_storage<T: 'static>(&self) -> Option<Ref<'_, SparseSet<T>>> {
+        let storage = self.storages.get(&TypeId::of::<T>())?;
+        Some(Ref::map(storage.borrow(), |storage| downcast_ref::<T>(storage.as_ref())))
+    }
+
+    pub fn get_storage_mut<T: 'static>(&self) -> Option<RefMut<'_, SparseSet<T>>> {
+        let storage = self.storages.get(&TypeId::of::<T>())?;
+        Some(RefMut::map(storage.borrow_mut(), |storage| downcast_mut::<T>(storage.as_mut())))
+    }
+
+    /// Calls `f` for every entity that has all components of the query, e.g. `world.query::<(&Transform, &mut Velocity)>(|entity, (transform, velocity)| ..)`.
+    /// The storage with the fewest components drives the iteration.
+    pub fn query<Q: Query>(&self, mut f: impl FnMut(Entity, Q::Item<'_>)) {
+        let mut borrow = match Q::borrow(self) {
+            Some(borrow) => borrow,
+            None => return,
+        };
+        for i in 0..Q::get_entities(&borrow).len() {
+            let entity = Q::get_entities(&borrow)[i];
+            if let Some(item) = Q::fetch(&mut borrow, entity) {
+                f(entity, item);
+            }
+        }
+    }
+
+    /// Adds the resource, or replaces the resource of the same type and returns the old one.
+    pub fn insert_resource<R: 'static>(&mut self, resource: R) -> Option<R> {
+        self.resources.insert(TypeId::of::<R>(), RefCell::new(Box::new(resource)))
+            .and_then(|old| old.into_inner().downcast::<R>().ok())
+            .map(|old| *old)
+    }
+
+    pub fn get_resource<R: 'static>(&self) -> Option<Ref<'_, R>> {
+        let resource = self.resources.get(&TypeId::of::<R>())?;
+        Ref::filter_map(resource.borrow(), |resource| resource.downcast_ref::<R>()).ok()
+    }
+
+    pub fn get_resource_mut<R: 'static>(&self) -> Option<RefMut<'_, R>> {
+        let resource = self.resources.get(&TypeId::of::<R>())?;
+        RefMut::filter_map(resource.borrow_mut(), |resource| resource.downcast_mut::<R>()).ok()
+    }
+
+    /// Like `get_resource`, for resources the systems can not work without. Panics if the resource is missing.
+    pub fn resource<R: 'static>(&self) -> Ref<'_, R> {
+        self.get_resource().unwrap_or_else(|| panic!("Resource {} is missing!", type_name::<R>()))
+    }
+
+    pub fn resource_mut<R: 'static>(&self) -> RefMut<'_, R> {
+        self.get_resource_mut().unwrap_or_else(|| panic!("Resource {} is missing!", type_name::<R>()))
+    }
+}
+
+fn downcast_ref<T: 'static>(storage: &dyn ComponentStorage) -> &SparseSet<T> {
+    storage.as_any().downcast_ref().expect("Component storage has the wrong type!")
+}
+
+fn downcast_mut<T: 'static>(storage: &mut dyn ComponentStorage) -> &mut SparseSet<T> {
+    storage.as_any_mut().downcast_mut().expect("Component storage has the wrong type!")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Position(f32);
+    #[derive(Debug, PartialEq)]
+    struct Velocity(f32);
+
+    #[test]
+    fn queries_visit_entities_with_all_components() {
+        let mut world = World::new();
+        let moving = world.spawn();
+        world.insert(moving, Position(1.0));
+        world.insert(moving, Velocity(2.0));
+        let resting = world.spawn();
+        world.insert(resting, Position(5.0));
+
+        world.query::<(&mut Position, &Velocity)>(|_, (position, velocity)| position.0 += velocity.0);
+        assert_eq!(*world.get::<Position>(moving).unwrap(), Position(3.0));
+        assert_eq!(*world.get::<Position>(resting).unwrap(), Position(5.0));
+
+        let mut visited = Vec::new();
+        world.query::<&Position>(|entity, _| visited.push(entity));
+        assert_eq!(visited.len(), 2);
+    }
+
+    #[test]
+    fn despawning_removes_all_components() {
+        let mut world = World::new();
+        let entity = world.spawn();
+        world.insert(entity, Position(1.0));
+        world.insert(entity, Velocity(1.0));
+        assert!(world.despawn(entity));
+        assert!(!world.despawn(entity));
+
+        assert!(world.get::<Position>(entity).is_none());
+        assert_eq!(world.insert(entity, Position(2.0)), None);
+        assert!(world.get_storage::<Position>().unwrap().is_empty());
+        assert_eq!(world.get_entity_count(), 0);
+    }
+
+    #[test]
+    fn resources_are_replaced_by_type() {
+        let mut world = World::new();
+        assert_eq!(world.insert_resource(Velocity(1.0)), None);
+        assert_eq!(world.insert_resource(Velocity(2.0)), Some(Velocity(1.0)));
+        world.resource_mut::<Velocity>().0 += 1.0;
+        assert_eq!(*world.resource::<Velocity>(), Velocity(3.0));
+        assert!(world.get_resource::<Position>().is_none());
+    }
+}
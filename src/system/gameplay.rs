@@ -0,0 +1,341 @@
+use std::f32::consts::FRAC_PI_3;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
+use winit::event::VirtualKeyCode;
+use crate::system::assets::manager::{AssetManager, AssetState, Handle};
+use crate::system::assets::model::Model;
+use crate::system::ecs::entity::Entity;
+use crate::system::ecs::schedule::{Schedule, Stage};
+use crate::system::ecs::world::World;
+use crate::system::input::InputState;
+use crate::system::log::Log;
+use crate::system::math::transform::Transform;
+use crate::system::math::vector::Vec3;
+use crate::system::rendering::camera::Camera;
+use crate::system::rendering::camera_controller::{CameraController, FlyController, OrbitController};
+use crate::system::rendering::material::MaterialInstance;
+use crate::system::rendering::mesh::bounds::BoundingSphere;
+use crate::system::rendering::mesh::mesh::Mesh;
+use crate::system::rendering::render_list::{CullStats, RenderList};
+use crate::system::scene::{NodeId, Renderable, Scene};
+use crate::system::window::{CursorGrab, DeviceEvents, RenderFrame, WindowEvents, WindowSize};
+
+/// Seconds between two log entries of the render statistics.
+const STATISTICS_INTERVAL: f32 = 5.0;
+
+/// Seconds since the previous frame, measured by the time system at the start of every frame.
+pub struct FrameTime {
+    pub delta_seconds: f32,
+    last_frame: Instant,
+}
+
+/// Component of the camera entity next to its `Camera`: the controllers it can be moved by. C switches between them.
+pub struct CameraControl {
+    controllers: Vec<Box<dyn CameraController>>,
+    active_controller: usize,
+}
+
+impl CameraControl {
+    fn get_active(&mut self) -> &mut dyn CameraController {
+        self.controllers[self.active_controller].as_mut()
+    }
+}
+
+/// Component of entities drawn at the world matrix of a node of the `Scene` resource, next to their `Renderable`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SceneNode(pub NodeId);
+
+/// Swaps the model and the material given on the command line into the scene once the asset workers have loaded them,
+/// so the frame never waits for the disk. Until then the test cube is shown.
+pub struct SceneLoader {
+    /// Node whose children are the nodes of the meshes of the test cube or of the model.
+    root: NodeId,
+    model: Option<Handle<Model>>,
+    material: Option<Handle<MaterialInstance>>,
+}
+
+/// Culling results summed up over the frames since the last log entry.
+#[derive(Default)]
+pub struct RenderStatistics {
+    frames: usize,
+    visible: usize,
+    culled: usize,
+    elapsed_seconds: f32,
+}
+
+/// Adds the resources and systems of the interactive window: input, camera controllers, asset loading, render statistics and render extraction.
+pub fn add_window_systems(world: &mut World, schedule: &mut Schedule, assets: &AssetManager, window_size: WindowSize,
+                          model_path: Option<&str>, material_path: Option<&str>) {
+    let mut camera = Camera::perspective(FRAC_PI_3, 1.0, 0.1, 100.0);
+    camera.set_viewport_size(window_size.width, window_size.height);
+    let eye = Vec3::new(1.5, 1.2, 2.0);
+    camera.look_at(eye, Vec3::ZERO, Vec3::Y);
+    let mut control = CameraControl {
+        controllers: vec![Box::new(OrbitController::new(Vec3::ZERO, eye.length())), Box::new(FlyController::new(2.0))],
+        active_controller: 0,
+    };
+    control.get_active().attach(&camera);
+    let camera_entity = world.spawn();
+    world.insert(camera_entity, camera);
+    world.insert(camera_entity, control);
+
+    world.insert_resource(Scene::new());
+    let root = spawn_meshes(world, "cube", &[Mesh::cube(1.0, 1)]);
+
+    world.insert_resource(WindowEvents::default());
+    world.insert_resource(DeviceEvents::default());
+    world.insert_resource(CursorGrab::default());
+    world.insert_resource(window_size);
+    world.insert_resource(InputState::default());
+    world.insert_resource(FrameTime { delta_seconds: 0.0, last_frame: Instant::now() });
+    world.insert_resource(SceneLoader {
+        root,
+        model: model_path.map(|model_path| assets.load(Path::new(model_path))),
+        material: material_path.map(|material_path| assets.load(Path::new(material_path))),
+    });
+    world.insert_resource(RenderFrame::default());
+    world.insert_resource(CullStats::default());
+    world.insert_resource(RenderStatistics::default());
+
+    schedule.add_system(Stage::Input, "time", update_time);
+    schedule.add_system(Stage::Input, "input", update_input);
+    schedule.add_system(Stage::Update, "scene loading", load_scene);
+    schedule.add_system(Stage::Update, "camera", update_camera);
+    schedule.add_system(Stage::Update, "render statistics", log_render_statistics);
+    schedule.add_system(Stage::RenderExtract, "render extraction", extract_render_frame);
+}
+
+pub fn update_time(world: &mut World) {
+    let mut time = world.resource_mut::<FrameTime>();
+    let now = Instant::now();
+    time.delta_seconds = now.duration_since(time.last_frame).as_secs_f32();
+    time.last_frame = now;
+}
+
+/// Resets the deltas of the last frame and applies the window events that arrived since.
+pub fn update_input(world: &mut World) {
+    let mut input = world.resource_mut::<InputState>();
+    input.end_frame();
+    for event in world.resource_mut::<WindowEvents>().0.drain(..) {
+        input.handle_event(&event);
+    }
+    for event in world.resource_mut::<DeviceEvents>().0.drain(..) {
+        input.handle_device_event(&event);
+    }
+}
+
+/// Swaps in the model and the material once both are no longer loading. Failures are logged and keep the test cube or its material.
+/// The camera frames the model once it is swapped in.
+pub fn load_scene(world: &mut World) {
+    let (model, material) = {
+        let mut loader = world.resource_mut::<SceneLoader>();
+        let model_loading = matches!(loader.model.as_ref().map(|model| model.get_state()), Some(AssetState::Loading));
+        let material_loading = matches!(loader.material.as_ref().map(|material| material.get_state()), Some(AssetState::Loading));
+        if model_loading || material_loading {
+            return;
+        }
+        (loader.model.take(), loader.material.take())
+    };
+
+    if let Some(model) = model {
+        match model.get_state() {
+            AssetState::Ready(model) => {
+                let old_root = world.resource::<SceneLoader>().root;
+                despawn_meshes(world, old_root);
+                world.resource_mut::<SceneLoader>().root = spawn_meshes(world, "model", &model.meshes);
+                if let Some(bounds) = BoundingSphere::from_meshes(&model.meshes) {
+                    world.query::<(&mut Camera, &mut CameraControl)>(|_, (camera, control)| control.get_active().frame(camera, &bounds));
+                }
+            }
+            AssetState::Failed(e) => Log::get().write_error(format!("Loading model failed: {}", e).as_str()),
+            AssetState::Loading => (),
+        }
+    }
+    if let Some(material) = material {
+        match material.get_state() {
+            AssetState::Ready(material) => {
+                Log::get().write(format!("Drawing all meshes with material {} of {}", material.name, material.material.name).as_str());
+                world.query::<&mut Renderable>(|_, renderable| renderable.material = Some(material.clone()));
+            }
+            AssetState::Failed(e) => Log::get().write_error(format!("Loading material failed: {}", e).as_str()),
+            AssetState::Loading => (),
+        }
+    }
+}
+
+/// Switches the controller on C, follows the window size and moves every camera with its active controller.
+/// The cursor is grabbed while a controller turns its camera with the mouse.
+pub fn update_camera(world: &mut World) {
+    let input = world.resource::<InputState>();
+    let window_size = world.resource::<WindowSize>();
+    let delta_seconds = world.resource::<FrameTime>().delta_seconds;
+    let mut grab = false;
+    world.query::<(&mut Camera, &mut CameraControl)>(|_, (camera, control)| {
+        if input.was_key_pressed(VirtualKeyCode::C) {
+            control.active_controller = (control.active_controller + 1) % control.controllers.len();
+            control.get_active().attach(camera);
+        }
+        camera.set_viewport_size(window_size.width, window_size.height);
+        control.get_active().update(camera, &input, delta_seconds);
+        grab |= control.get_active().is_looking(&input);
+    });
+    world.resource_mut::<CursorGrab>().0 = grab;
+}
+
+/// Adds the culling results of the last frame and logs their average every few seconds.
+pub fn log_render_statistics(world: &mut World) {
+    let frame_stats = *world.resource::<CullStats>();
+    let delta_seconds = world.resource::<FrameTime>().delta_seconds;
+    let mut statistics = world.resource_mut::<RenderStatistics>();
+    statistics.frames += 1;
+    statistics.visible += frame_stats.visible;
+    statistics.culled += frame_stats.culled;
+    statistics.elapsed_seconds += delta_seconds;
+    if statistics.elapsed_seconds < STATISTICS_INTERVAL {
+        return;
+    }
+
+    let frames = statistics.frames as f32;
+    Log::get().write(format!("{:.1} fps, {:.1} visible and {:.1} culled render items per frame",
+                             frames / statistics.elapsed_seconds, statistics.visible as f32 / frames, statistics.culled as f32 / frames).as_str());
+    *statistics = RenderStatistics::default();
+}
+
+/// Hands the camera and the renderables to the renderer, the latter at the world matrices of their scene nodes.
+pub fn extract_render_frame(world: &mut World) {
+    let render_list = extract_render_list(world);
+    let mut frame = world.resource_mut::<RenderFrame>();
+    world.query::<&Camera>(|_, camera| frame.camera = camera.get_uniform());
+    frame.render_list = render_list;
+}
+
+/// Updates the transforms of the `Scene` resource and collects every entity with a `Renderable` at the world matrix of its node.
+/// Windows and headless frames both draw what this returns.
+pub fn extract_render_list(world: &World) -> RenderList {
+    let mut scene = world.resource_mut::<Scene>();
+    scene.update_transforms();
+    let mut render_list = RenderList::new();
+    world.query::<(&SceneNode, &Renderable)>(|_, (node, renderable)| {
+        if let Ok(transform) = scene.get_world_matrix(node.0) {
+            render_list.items.push(renderable.to_render_item(transform));
+        }
+    });
+    render_list
+}
+
+/// Adds a group node with one child node per mesh to the scene and spawns an entity drawing each mesh at its node.
+/// Returns the group node.
+pub fn spawn_meshes(world: &mut World, name: &str, meshes: &[Mesh]) -> NodeId {
+    let (root, nodes) = {
+        let mut scene = world.resource_mut::<Scene>();
+        let root = scene.add_node(name, None, Transform::IDENTITY).expect("Adding a root node failed!");
+        let nodes: Vec<NodeId> = (0..meshes.len())
+            .map(|i| scene.add_node(&format!("{}.{}", name, i), Some(root), Transform::IDENTITY).expect("Adding a mesh node failed!"))
+            .collect();
+        (root, nodes)
+    };
+    for (node, mesh) in nodes.into_iter().zip(meshes) {
+        let entity = world.spawn();
+        world.insert(entity, SceneNode(node));
+        world.insert(entity, Renderable::new(Arc::new(mesh.clone())));
+    }
+    root
+}
+
+/// Removes the node with its descendants from the scene and despawns the entities drawn at them.
+fn despawn_meshes(world: &mut World, root: NodeId) {
+    let mut removed: Vec<Entity> = Vec::new();
+    {
+        let mut scene = world.resource_mut::<Scene>();
+        scene.remove_node(root).ok();
+        world.query::<&SceneNode>(|entity, node| if !scene.contains(node.0) { removed.push(entity) });
+    }
+    for entity in removed {
+        world.despawn(entity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use winit::event::{DeviceEvent, DeviceId, ElementState, KeyboardInput, MouseButton, WindowEvent};
+    use super::*;
+
+    #[allow(deprecated)]
+    fn key_press(key: VirtualKeyCode) -> WindowEvent<'static> {
+        let input = KeyboardInput { scancode: 0, state: ElementState::Pressed, virtual_keycode: Some(key), modifiers: Default::default() };
+        WindowEvent::KeyboardInput { device_id: unsafe { DeviceId::dummy() }, input, is_synthetic: false }
+    }
+
+    #[test]
+    fn window_systems_move_the_camera_and_extract_the_scene() {
+        let mut world = World::new();
+        let mut schedule = Schedule::new();
+        let assets = AssetManager::new();
+        add_window_systems(&mut world, &mut schedule, &assets, WindowSize { width: 200, height: 100 }, None, None);
+
+        world.resource_mut::<WindowEvents>().0.push(key_press(VirtualKeyCode::C));
+        schedule.run(&mut world);
+        world.query::<(&Camera, &CameraControl)>(|_, (camera, control)| {
+            assert_eq!(control.active_controller, 1);
+            assert!((camera.get_aspect_ratio() - 2.0).abs() < 1e-6);
+        });
+        assert_eq!(world.resource::<RenderFrame>().render_list.len(), 1);
+        assert_eq!(world.resource::<RenderFrame>().camera.view, world.get::<Camera>(camera_entity(&world)).unwrap().get_view_matrix());
+
+        // The key stays down, but it only switches the controller in the frame it was pressed.
+        schedule.run(&mut world);
+        world.query::<&CameraControl>(|_, control| assert_eq!(control.active_controller, 1));
+    }
+
+    #[allow(deprecated)]
+    fn button(state: ElementState) -> WindowEvent<'static> {
+        WindowEvent::MouseInput { device_id: unsafe { DeviceId::dummy() }, state, button: MouseButton::Left, modifiers: Default::default() }
+    }
+
+    #[test]
+    fn dragging_turns_the_camera_with_the_cursor_grabbed() {
+        let mut world = World::new();
+        let mut schedule = Schedule::new();
+        let assets = AssetManager::new();
+        add_window_systems(&mut world, &mut schedule, &assets, WindowSize { width: 200, height: 100 }, None, None);
+        schedule.run(&mut world);
+        let forward = world.get::<Camera>(camera_entity(&world)).unwrap().get_forward();
+
+        world.resource_mut::<WindowEvents>().0.push(button(ElementState::Pressed));
+        world.resource_mut::<DeviceEvents>().0.push(DeviceEvent::MouseMotion { delta: (100.0, 0.0) });
+        schedule.run(&mut world);
+        assert!(world.resource::<CursorGrab>().0);
+        assert!((world.get::<Camera>(camera_entity(&world)).unwrap().get_forward() - forward).length() > 0.1);
+
+        world.resource_mut::<WindowEvents>().0.push(button(ElementState::Released));
+        schedule.run(&mut world);
+        assert!(!world.resource::<CursorGrab>().0);
+    }
+
+    #[test]
+    fn replacing_the_meshes_despawns_their_entities() {
+        let mut world = World::new();
+        world.insert_resource(Scene::new());
+        let cube = spawn_meshes(&mut world, "cube", &[Mesh::cube(1.0, 1)]);
+        let model = spawn_meshes(&mut world, "model", &[Mesh::cube(1.0, 1), Mesh::cube(2.0, 1)]);
+        despawn_meshes(&mut world, cube);
+        assert_eq!(extract_render_list(&world).len(), 2);
+
+        let mut nodes = Vec::new();
+        world.query::<(&SceneNode, &Renderable)>(|_, (node, _)| nodes.push(world.resource::<Scene>().get_name(node.0).unwrap().to_string()));
+        nodes.sort();
+        assert_eq!(nodes, vec!["model.0", "model.1"]);
+        assert_eq!(world.get_entity_count(), 2);
+
+        despawn_meshes(&mut world, model);
+        assert!(extract_render_list(&world).is_empty());
+    }
+
+    fn camera_entity(world: &World) -> Entity {
+        let mut cameras = Vec::new();
+        world.query::<&Camera>(|entity, _| cameras.push(entity));
+        assert_eq!(cameras.len(), 1);
+        cameras[0]
+    }
+}
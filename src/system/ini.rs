@@ -7,6 +7,21 @@ pub enum WindowMode {
     Fullscreen,
 }
 
+/// Filter of texture samplers, used for magnification, minification and between mip levels.
+#[derive(Clone, Copy, PartialEq)]
+pub enum TextureFilter {
+    Nearest,
+    Linear,
+}
+
+/// What texture samplers do with uvs outside of [0, 1].
+#[derive(Clone, Copy, PartialEq)]
+pub enum TextureWrap {
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+}
+
 pub struct WindowIniData {
     pub window_mode: WindowMode,
     pub window_width: u32,
@@ -14,8 +29,20 @@ pub struct WindowIniData {
     pub window_title: String,
 }
 
+pub struct RenderingIniData {
+    pub frames_in_flight: u32,
+    pub shader_path: String,
+    pub texture_filter: TextureFilter,
+    pub texture_wrap: TextureWrap,
+    /// Maximum anisotropy of texture samplers. Values of one or less turn anisotropic filtering off.
+    pub anisotropy: f32,
+    /// Largest error in pixels a simplified level of detail may show on screen.
+    pub lod_pixel_error: f32,
+}
+
 pub struct IniFileData {
     pub window_data: WindowIniData,
+    pub rendering_data: RenderingIniData,
     pub logging_path: String,
 
 }
@@ -52,6 +79,7 @@ fn string_to_data(content: &str) -> IniFileData {
 
         match fields_vec[0] {
             "Window" => { string_to_window_data(&fields_vec, &mut ini_data) }
+            "Rendering" => { string_to_rendering_data(&fields_vec, &mut ini_data) }
             "Logging" => { string_to_log_data(&fields_vec, &mut ini_data) }
             _ => {}
         }
@@ -80,6 +108,36 @@ fn string_to_window_data(lines: &Vec<&str>, ini_data: &mut IniFileData) {
     ini_data.window_data = window_data;
 }
 
+fn string_to_rendering_data(lines: &Vec<&str>, ini_data: &mut IniFileData) {
+    for (i, _) in lines.iter().enumerate() {
+        if i == 0 { continue; }
+
+        let content: Vec<&str> = lines[i].split("=").collect();
+        match content[0] {
+            "FramesInFlight" => { ini_data.rendering_data.frames_in_flight = content[1].parse::<u32>().unwrap().max(1) }
+            "ShaderPath" => { ini_data.rendering_data.shader_path = content[1].to_string() }
+            "TextureFilter" => {
+                match content[1] {
+                    "Nearest" => ini_data.rendering_data.texture_filter = TextureFilter::Nearest,
+                    "Linear" => ini_data.rendering_data.texture_filter = TextureFilter::Linear,
+                    _ => {}
+                }
+            }
+            "TextureWrap" => {
+                match content[1] {
+                    "Repeat" => ini_data.rendering_data.texture_wrap = TextureWrap::Repeat,
+                    "MirroredRepeat" => ini_data.rendering_data.texture_wrap = TextureWrap::MirroredRepeat,
+                    "ClampToEdge" => ini_data.rendering_data.texture_wrap = TextureWrap::ClampToEdge,
+                    _ => {}
+                }
+            }
+            "Anisotropy" => { ini_data.rendering_data.anisotropy = content[1].parse::<f32>().unwrap_or(1.0) }
+            "LodPixelError" => { ini_data.rendering_data.lod_pixel_error = content[1].parse::<f32>().unwrap_or(1.0).max(0.0) }
+            _ => {}
+        }
+    }
+}
+
 fn string_to_log_data(lines: &Vec<&str>, ini_data: &mut IniFileData) {
     for (i, _) in lines.iter().enumerate() {
         if i == 0 { continue; }
@@ -96,6 +154,7 @@ fn string_to_log_data(lines: &Vec<&str>, ini_data: &mut IniFileData) {
 fn ini_to_str(ini: &IniFileData) -> String {
     let mut output = String::new();
     output += &window_ini_to_string(&ini.window_data);
+    output += &rendering_ini_to_string(&ini.rendering_data);
     output += &logging_ini_to_string(&ini.logging_path);
 
     output
@@ -110,6 +169,14 @@ fn setup_default_ini() -> IniFileData {
             window_width: 640,
             window_title: "RESA by Sebastian Borsch".to_string(),
         },
+        rendering_data: RenderingIniData {
+            frames_in_flight: 2,
+            shader_path: "assets/shaders".to_string(),
+            texture_filter: TextureFilter::Linear,
+            texture_wrap: TextureWrap::Repeat,
+            anisotropy: 16.0,
+            lod_pixel_error: 1.0,
+        },
     };
 
     default_data
@@ -138,6 +205,33 @@ fn window_ini_to_string(window_data: &WindowIniData) -> String {
     output
 }
 
+fn rendering_ini_to_string(rendering_data: &RenderingIniData) -> String {
+    let mut output = String::new();
+
+    output += "\n#Rendering";
+    output += "\nFramesInFlight=";
+    output += &rendering_data.frames_in_flight.to_string();
+    output += "\nShaderPath=";
+    output += &rendering_data.shader_path;
+    output += "\nTextureFilter=";
+    output += match rendering_data.texture_filter {
+        TextureFilter::Nearest => "Nearest",
+        TextureFilter::Linear => "Linear",
+    };
+    output += "\nTextureWrap=";
+    output += match rendering_data.texture_wrap {
+        TextureWrap::Repeat => "Repeat",
+        TextureWrap::MirroredRepeat => "MirroredRepeat",
+        TextureWrap::ClampToEdge => "ClampToEdge",
+    };
+    output += "\nAnisotropy=";
+    output += &rendering_data.anisotropy.to_string();
+    output += "\nLodPixelError=";
+    output += &rendering_data.lod_pixel_error.to_string();
+
+    output
+}
+
 fn logging_ini_to_string(log_path: &str) -> String {
     let mut output = String::new();
 
@@ -0,0 +1,88 @@
+use std::collections::HashSet;
+use winit::event::{DeviceEvent, ElementState, KeyboardInput, MouseButton, MouseScrollDelta, VirtualKeyCode, WindowEvent};
+use crate::system::math::vector::Vec2;
+
+/// Pixels of a touchpad scroll that count as one line of a mouse wheel.
+const PIXELS_PER_SCROLL_LINE: f32 = 40.0;
+
+/// Keyboard and mouse state of the window, collected from its events and the motion events of the mouse.
+/// Deltas add up over all events of a frame and are reset by `end_frame`.
+#[derive(Default)]
+pub struct InputState {
+    pub pressed_keys: HashSet<VirtualKeyCode>,
+    pub pressed_buttons: HashSet<MouseButton>,
+    /// Keys that went down during this frame.
+    pub pressed_this_frame: HashSet<VirtualKeyCode>,
+    /// Cursor position in physical pixels, None while the cursor is outside the window. Meant for pointing at things, not for turning a camera.
+    pub cursor_position: Option<Vec2>,
+    /// Raw mouse movement, y points down. Unlike the cursor it neither accelerates nor stops at the border of the window or the screen.
+    pub mouse_delta: Vec2,
+    /// Wheel movement in lines, positive away from the user.
+    pub scroll_delta: f32,
+}
+
+impl InputState {
+    pub fn handle_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::KeyboardInput { input: KeyboardInput { virtual_keycode: Some(key), state, .. }, .. } => {
+                match state {
+                    ElementState::Pressed => {
+                        if self.pressed_keys.insert(*key) {
+                            self.pressed_this_frame.insert(*key);
+                        }
+                    }
+                    ElementState::Released => {
+                        self.pressed_keys.remove(key);
+                    }
+                }
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                match state {
+                    ElementState::Pressed => self.pressed_buttons.insert(*button),
+                    ElementState::Released => self.pressed_buttons.remove(button),
+                };
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor_position = Some(Vec2::new(position.x as f32, position.y as f32));
+            }
+            WindowEvent::CursorLeft { .. } => self.cursor_position = None,
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.scroll_delta += match delta {
+                    MouseScrollDelta::LineDelta(_, lines) => *lines,
+                    MouseScrollDelta::PixelDelta(position) => position.y as f32 / PIXELS_PER_SCROLL_LINE,
+                };
+            }
+            // Keys released while the window is in the background would otherwise stay pressed.
+            WindowEvent::Focused(false) => {
+                self.pressed_keys.clear();
+                self.pressed_buttons.clear();
+            }
+            _ => (),
+        }
+    }
+
+    pub fn handle_device_event(&mut self, event: &DeviceEvent) {
+        if let DeviceEvent::MouseMotion { delta: (x, y) } = event {
+            self.mouse_delta += Vec2::new(*x as f32, *y as f32);
+        }
+    }
+
+    pub fn is_key_down(&self, key: VirtualKeyCode) -> bool {
+        self.pressed_keys.contains(&key)
+    }
+
+    pub fn was_key_pressed(&self, key: VirtualKeyCode) -> bool {
+        self.pressed_this_frame.contains(&key)
+    }
+
+    pub fn is_button_down(&self, button: MouseButton) -> bool {
+        self.pressed_buttons.contains(&button)
+    }
+
+    /// Resets the deltas after the frame has used them.
+    pub fn end_frame(&mut self) {
+        self.pressed_this_frame.clear();
+        self.mouse_delta = Vec2::ZERO;
+        self.scroll_delta = 0.0;
+    }
+}
@@ -1,14 +1,35 @@
 use std::borrow::Borrow;
+use std::f32::consts::FRAC_PI_3;
 use std::ops::Deref;
+use std::path::Path;
+use std::sync::Arc;
+use crate::system::assets::manager::{AssetManager, Handle};
+use crate::system::assets::model::Model;
+use crate::system::assets::texture::TextureImage;
 use crate::system::device_info_collector::DeviceInfo;
+use crate::system::ecs::schedule::{Schedule, Stage};
+use crate::system::ecs::world::World;
+use crate::system::gameplay;
 use crate::system::ini;
 use crate::system::file::Serializable;
 use crate::system::log::Log;
+use crate::system::rendering::IRenderer;
+use crate::system::rendering::camera::Camera;
+use crate::system::rendering::material::MaterialInstance;
+use crate::system::rendering::mesh::bounds::BoundingSphere;
+use crate::system::rendering::mesh::mesh::Mesh;
+use crate::system::rendering::software::renderer::SoftwareRenderer;
+use crate::system::rendering::vulkan::renderer::VkRenderer;
+use crate::system::scene::Scene;
 use crate::system::window::ResaWindow;
 
 pub struct System {
     pub device_info: DeviceInfo,
     pub window: ResaWindow,
+    /// Entities, components and resources of the running application.
+    pub world: World,
+    /// Systems that update the world every frame.
+    pub schedule: Schedule,
 }
 
 impl System {
@@ -19,8 +40,8 @@ impl System {
         let mut device_info = DeviceInfo::new();
         device_info.collect_data();
 
-        let window = ResaWindow::init(&ini_data.window_data, device_info.os_name);
-        let mut sys = System { device_info, window };
+        let window = ResaWindow::init(&ini_data.window_data, &ini_data.rendering_data, device_info.os_name);
+        let mut sys = System { device_info, window, world: World::new(), schedule: Schedule::new() };
 
         let gpu_name = sys.window.get_gpu_name().clone();
         sys.device_info.set_gpu_data(gpu_name.as_str(), 0);
@@ -29,6 +50,107 @@ impl System {
         sys
     }
 
+    /// Adds the systems of the interactive window and runs its event loop until the window is closed.
+    /// The model and the material are loaded in the background and drawn as soon as they are ready.
+    pub fn run(self, model_path: Option<&str>, material_path: Option<&str>) {
+        let System { window, mut world, mut schedule, .. } = self;
+        gameplay::add_window_systems(&mut world, &mut schedule, &window.get_assets(), window.get_size(), model_path, material_path);
+        for stage in Stage::ALL {
+            Log::get().write(format!("Stage {:?}: {}", stage, schedule.get_system_names(stage).join(", ")).as_str());
+        }
+        Log::get().write(format!("World starts with {} entities", world.get_entity_count()).as_str());
+        window.run_window_loop(world, schedule);
+    }
+
+    /// Renders a single frame without a window and writes it as ppm image to the given path.
+    /// The software renderer is used if requested or if no vulkan loader is available.
+    /// Without a model a cube is drawn, otherwise the camera is placed so the whole model is visible.
+    /// A material file replaces the materials of all meshes.
+    pub fn render_headless(log_output: &str, image_path: &str, use_software_renderer: bool, model_path: Option<&str>, material_path: Option<&str>) {
+        Log::init(log_output);
+
+        // A single frame is rendered, so everything it shows has to be loaded before.
+        let assets = Arc::new(AssetManager::new());
+        let model = model_path.map(|model_path| assets.load::<Model>(Path::new(model_path)));
+        let material = material_path.map(|material_path| assets.load::<MaterialInstance>(Path::new(material_path)));
+        let meshes = match model.map(|model| model.wait()) {
+            Some(Ok(model)) => model.meshes.clone(),
+            Some(Err(e)) => {
+                Log::get().write_error(format!("Loading model failed: {}", e).as_str());
+                return;
+            }
+            None => vec![Mesh::cube(1.0, 1)],
+        };
+        let meshes = match material.map(|material| material.wait()) {
+            Some(Ok(material)) => {
+                Log::get().write(format!("Drawing all meshes with material {} of {}", material.name, material.material.name).as_str());
+                meshes.into_iter().map(|mesh| Mesh { material: Some(material.clone()), ..mesh }).collect()
+            }
+            Some(Err(e)) => {
+                Log::get().write_error(format!("Loading material failed: {}", e).as_str());
+                return;
+            }
+            None => meshes,
+        };
+        // The renderers get the same textures from the manager as long as these handles are alive. Failures are reported by the renderer.
+        let textures: Vec<Handle<TextureImage>> = meshes.iter()
+            .filter_map(|mesh| mesh.material.as_ref())
+            .flat_map(|material| material.get_textures().iter().flatten())
+            .map(|path| assets.load(path))
+            .collect();
+        for texture in textures.iter() {
+            texture.wait().ok();
+        }
+
+        let ini_data = ini::IniFileData::load("settings.ini", "");
+        let width = ini_data.window_data.window_width;
+        let height = ini_data.window_data.window_height;
+        let mut renderer: Box<dyn IRenderer> = if use_software_renderer || !VkRenderer::is_available() {
+            Box::new(SoftwareRenderer::new_headless(width, height, &ini_data.rendering_data, assets.clone()))
+        } else {
+            Box::new(VkRenderer::new_headless(width, height, &ini_data.rendering_data, assets.clone()))
+        };
+
+        let bounds = BoundingSphere::from_meshes(&meshes).unwrap_or(BoundingSphere { center: [0.0; 3], radius: 1.0 });
+        let mut camera = Camera::perspective(FRAC_PI_3, 1.0, 0.1, 100.0);
+        camera.set_viewport_size(width, height);
+        camera.frame_sphere(&bounds);
+        // The frame is extracted from entities, just like the frames of the window.
+        let mut world = World::new();
+        world.insert_resource(Scene::new());
+        gameplay::spawn_meshes(&mut world, "model", &meshes);
+        renderer.render(&camera.get_uniform(), &gameplay::extract_render_list(&world));
+        let cull_stats = renderer.get_cull_stats();
+        Log::get().write(format!("Drew {} render items, {} culled", cull_stats.visible, cull_stats.culled).as_str());
+
+        match renderer.read_frame() {
+            Some(image) => {
+                if let Err(e) = image.save_as_ppm(image_path) {
+                    Log::get().write_error(format!("Writing headless frame to {} failed: {}", image_path, e).as_str());
+                }
+            }
+            None => Log::get().write_error("Headless renderer did not produce a frame!"),
+        }
+        renderer.dispose();
+    }
+
+    /// Converts a model into the binary mesh format, so it loads without parsing the next time.
+    pub fn convert_model(log_output: &str, model_path: &str, output_path: &str) {
+        Log::init(log_output);
+
+        let model = match Model::load(Path::new(model_path)) {
+            Ok(model) => model,
+            Err(e) => {
+                Log::get().write_error(format!("Loading model failed: {}", e).as_str());
+                return;
+            }
+        };
+        match Mesh::write_binary(Path::new(output_path), &model.meshes) {
+            Ok(()) => Log::get().write(format!("Wrote {} meshes to {}", model.meshes.len(), output_path).as_str()),
+            Err(e) => Log::get().write_error(format!("Writing binary meshes failed: {}", e).as_str()),
+        }
+    }
+
     pub fn init_logging(self, output_path: &str) {
         Log::init(output_path);
         self.device_info.write_to_log();
@@ -6,6 +6,8 @@ use crate::system::log_flags::LogFlags;
 use ansi_term::Colour;
 use lazy_static::lazy_static;
 use std::sync::{Mutex};
+#[cfg(test)]
+use std::sync::Once;
 use std::fs::{File, OpenOptions};
 use std::io::{Write, Error};
 
@@ -128,6 +130,12 @@ impl Log {
             eprintln!("Could not write to file: {}", e);
         }
     }
-}
-
 
+    /// Initializes the logger once for all tests of the binary, with the log file in the temp directory.
+    /// Tests run in parallel, so code paths that log call this instead of `init`.
+    #[cfg(test)]
+    pub fn init_for_tests() {
+        static INIT: Once = Once::new();
+        INIT.call_once(|| Log::init(std::env::temp_dir().to_str().expect("Temp directory is no valid UTF-8!")));
+    }
+}
@@ -0,0 +1,347 @@
+use std::ops::{Mul, MulAssign};
+use bytemuck::{Pod, Zeroable};
+use crate::system::math::quaternion::Quat;
+use crate::system::math::vector::{Vec3, Vec4};
+
+/// Column major 3x3 matrix, mostly for rotations and normal transforms.
+/// It is tightly packed, uniform blocks expect a padded mat3, so upload a Mat4 instead.
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Debug, Pod, Zeroable)]
+pub struct Mat3 {
+    pub columns: [Vec3; 3],
+}
+
+/// Column major 4x4 matrix with the memory layout of a glsl mat4, so it can be copied into uniform buffers as it is.
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Debug, Pod, Zeroable)]
+pub struct Mat4 {
+    pub columns: [Vec4; 4],
+}
+
+impl Default for Mat3 {
+    fn default() -> Self {
+        Mat3::IDENTITY
+    }
+}
+
+impl Default for Mat4 {
+    fn default() -> Self {
+        Mat4::IDENTITY
+    }
+}
+
+impl Mat3 {
+    pub const IDENTITY: Mat3 = Mat3 { columns: [Vec3::X, Vec3::Y, Vec3::Z] };
+
+    pub const fn from_columns(x_axis: Vec3, y_axis: Vec3, z_axis: Vec3) -> Self {
+        Mat3 { columns: [x_axis, y_axis, z_axis] }
+    }
+
+    pub fn from_quat(rotation: Quat) -> Self {
+        Mat3::from_columns(rotation * Vec3::X, rotation * Vec3::Y, rotation * Vec3::Z)
+    }
+
+    pub fn from_scale(scale: Vec3) -> Self {
+        Mat3::from_columns(Vec3::X * scale.x, Vec3::Y * scale.y, Vec3::Z * scale.z)
+    }
+
+    /// Upper left 3x3 part, the rotation and scale of an affine transform.
+    pub fn from_mat4(matrix: &Mat4) -> Self {
+        let [x_axis, y_axis, z_axis, _] = matrix.columns;
+        Mat3::from_columns(x_axis.truncate(), y_axis.truncate(), z_axis.truncate())
+    }
+
+    pub fn get_row(&self, row: usize) -> Vec3 {
+        Vec3::new(self.columns[0][row], self.columns[1][row], self.columns[2][row])
+    }
+
+    pub fn transpose(&self) -> Self {
+        Mat3::from_columns(self.get_row(0), self.get_row(1), self.get_row(2))
+    }
+
+    pub fn determinant(&self) -> f32 {
+        let [x_axis, y_axis, z_axis] = self.columns;
+        x_axis.dot(y_axis.cross(z_axis))
+    }
+
+    /// Inverse matrix, not finite if the matrix is singular.
+    pub fn inverse(&self) -> Self {
+        let [x_axis, y_axis, z_axis] = self.columns;
+        let determinant = self.determinant();
+        // The rows of the inverse are the cross products of the columns, divided by the determinant.
+        Mat3::from_columns(y_axis.cross(z_axis) / determinant, z_axis.cross(x_axis) / determinant, x_axis.cross(y_axis) / determinant)
+            .transpose()
+    }
+
+    /// Inverse transpose times the determinant. Moves normals through a transform with non uniform scale up to their length,
+    /// and unlike the inverse it stays finite for singular matrices.
+    pub fn cofactor(&self) -> Self {
+        let [x_axis, y_axis, z_axis] = self.columns;
+        Mat3::from_columns(y_axis.cross(z_axis), z_axis.cross(x_axis), x_axis.cross(y_axis))
+    }
+
+    pub fn to_cols_array(self) -> [[f32; 3]; 3] {
+        self.columns.map(Vec3::to_array)
+    }
+}
+
+impl Mat4 {
+    pub const IDENTITY: Mat4 = Mat4 { columns: [Vec4::X, Vec4::Y, Vec4::Z, Vec4::W] };
+
+    pub const fn from_columns(x_axis: Vec4, y_axis: Vec4, z_axis: Vec4, w_axis: Vec4) -> Self {
+        Mat4 { columns: [x_axis, y_axis, z_axis, w_axis] }
+    }
+
+    pub fn from_cols_array(columns: [[f32; 4]; 4]) -> Self {
+        Mat4 { columns: columns.map(Vec4::from) }
+    }
+
+    pub fn from_translation(translation: Vec3) -> Self {
+        Mat4::from_columns(Vec4::X, Vec4::Y, Vec4::Z, translation.extend(1.0))
+    }
+
+    pub fn from_scale(scale: Vec3) -> Self {
+        Mat4::from_columns(Vec4::X * scale.x, Vec4::Y * scale.y, Vec4::Z * scale.z, Vec4::W)
+    }
+
+    pub fn from_quat(rotation: Quat) -> Self {
+        Mat4::from_mat3(&Mat3::from_quat(rotation), Vec3::ZERO)
+    }
+
+    /// Affine transform with the matrix as rotation and scale part.
+    pub fn from_mat3(matrix: &Mat3, translation: Vec3) -> Self {
+        let [x_axis, y_axis, z_axis] = matrix.columns;
+        Mat4::from_columns(x_axis.extend(0.0), y_axis.extend(0.0), z_axis.extend(0.0), translation.extend(1.0))
+    }
+
+    /// Scales first, then rotates and translates last.
+    pub fn from_scale_rotation_translation(scale: Vec3, rotation: Quat, translation: Vec3) -> Self {
+        let rotation = Mat3::from_quat(rotation);
+        let [x_axis, y_axis, z_axis] = rotation.columns;
+        Mat4::from_mat3(&Mat3::from_columns(x_axis * scale.x, y_axis * scale.y, z_axis * scale.z), translation)
+    }
+
+    /// View matrix of a right handed camera at `eye` that looks at `target`. The camera looks down its negative z axis with y up.
+    pub fn look_at(eye: Vec3, target: Vec3, up: Vec3) -> Self {
+        let forward = (target - eye).normalize();
+        let side = forward.cross(up).normalize();
+        let camera_up = side.cross(forward);
+
+        Mat4::from_columns(
+            Vec4::new(side.x, camera_up.x, -forward.x, 0.0),
+            Vec4::new(side.y, camera_up.y, -forward.y, 0.0),
+            Vec4::new(side.z, camera_up.z, -forward.z, 0.0),
+            Vec4::new(-side.dot(eye), -camera_up.dot(eye), forward.dot(eye), 1.0),
+        )
+    }
+
+    /// Perspective projection for vulkan: view space is right handed, depth is mapped from `near` to 0 and from `far` to 1
+    /// and y is flipped, because the y axis of vulkan's clip space points down. The vertical field of view is given in radians.
+    pub fn perspective(fov_y: f32, aspect_ratio: f32, near: f32, far: f32) -> Self {
+        let focal_length = 1.0 / (fov_y * 0.5).tan();
+        Mat4::from_columns(
+            Vec4::new(focal_length / aspect_ratio, 0.0, 0.0, 0.0),
+            Vec4::new(0.0, -focal_length, 0.0, 0.0),
+            Vec4::new(0.0, 0.0, far / (near - far), -1.0),
+            Vec4::new(0.0, 0.0, near * far / (near - far), 0.0),
+        )
+    }
+
+    /// Orthographic projection of the view space box for vulkan, with the same depth range and y flip as `perspective`.
+    pub fn orthographic(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> Self {
+        let width = right - left;
+        let height = top - bottom;
+        let depth = far - near;
+        Mat4::from_columns(
+            Vec4::new(2.0 / width, 0.0, 0.0, 0.0),
+            Vec4::new(0.0, -2.0 / height, 0.0, 0.0),
+            Vec4::new(0.0, 0.0, -1.0 / depth, 0.0),
+            Vec4::new(-(right + left) / width, (top + bottom) / height, -near / depth, 1.0),
+        )
+    }
+
+    pub fn get_row(&self, row: usize) -> Vec4 {
+        Vec4::new(self.columns[0][row], self.columns[1][row], self.columns[2][row], self.columns[3][row])
+    }
+
+    pub fn transpose(&self) -> Self {
+        Mat4::from_columns(self.get_row(0), self.get_row(1), self.get_row(2), self.get_row(3))
+    }
+
+    pub fn determinant(&self) -> f32 {
+        let [a, b, c, d] = self.columns;
+        let s0 = a.x * b.y - b.x * a.y;
+        let s1 = a.x * b.z - b.x * a.z;
+        let s2 = a.x * b.w - b.x * a.w;
+        let s3 = a.y * b.z - b.y * a.z;
+        let s4 = a.y * b.w - b.y * a.w;
+        let s5 = a.z * b.w - b.z * a.w;
+        let c5 = c.z * d.w - d.z * c.w;
+        let c4 = c.y * d.w - d.y * c.w;
+        let c3 = c.y * d.z - d.y * c.z;
+        let c2 = c.x * d.w - d.x * c.w;
+        let c1 = c.x * d.z - d.x * c.z;
+        let c0 = c.x * d.y - d.x * c.y;
+        s0 * c5 - s1 * c4 + s2 * c3 + s3 * c2 - s4 * c1 + s5 * c0
+    }
+
+    /// Inverse of a general matrix, not finite if the matrix is singular.
+    pub fn inverse(&self) -> Self {
+        let [a, b, c, d] = self.columns;
+        let s0 = a.x * b.y - b.x * a.y;
+        let s1 = a.x * b.z - b.x * a.z;
+        let s2 = a.x * b.w - b.x * a.w;
+        let s3 = a.y * b.z - b.y * a.z;
+        let s4 = a.y * b.w - b.y * a.w;
+        let s5 = a.z * b.w - b.z * a.w;
+        let c5 = c.z * d.w - d.z * c.w;
+        let c4 = c.y * d.w - d.y * c.w;
+        let c3 = c.y * d.z - d.y * c.z;
+        let c2 = c.x * d.w - d.x * c.w;
+        let c1 = c.x * d.z - d.x * c.z;
+        let c0 = c.x * d.y - d.x * c.y;
+        let inverse_determinant = 1.0 / (s0 * c5 - s1 * c4 + s2 * c3 + s3 * c2 - s4 * c1 + s5 * c0);
+
+        Mat4::from_columns(
+            Vec4::new(b.y * c5 - b.z * c4 + b.w * c3, -a.y * c5 + a.z * c4 - a.w * c3,
+                      d.y * s5 - d.z * s4 + d.w * s3, -c.y * s5 + c.z * s4 - c.w * s3),
+            Vec4::new(-b.x * c5 + b.z * c2 - b.w * c1, a.x * c5 - a.z * c2 + a.w * c1,
+                      -d.x * s5 + d.z * s2 - d.w * s1, c.x * s5 - c.z * s2 + c.w * s1),
+            Vec4::new(b.x * c4 - b.y * c2 + b.w * c0, -a.x * c4 + a.y * c2 - a.w * c0,
+                      d.x * s4 - d.y * s2 + d.w * s0, -c.x * s4 + c.y * s2 - c.w * s0),
+            Vec4::new(-b.x * c3 + b.y * c1 - b.z * c0, a.x * c3 - a.y * c1 + a.z * c0,
+                      -d.x * s3 + d.y * s1 - d.z * s0, c.x * s3 - c.y * s1 + c.z * s0),
+        ) * inverse_determinant
+    }
+
+    /// Transforms a position, including the translation. The result is not divided by w.
+    pub fn transform_point(&self, point: Vec3) -> Vec3 {
+        (*self * point.extend(1.0)).truncate()
+    }
+
+    /// Transforms a direction, without the translation.
+    pub fn transform_vector(&self, vector: Vec3) -> Vec3 {
+        (*self * vector.extend(0.0)).truncate()
+    }
+
+    /// Transforms a position and divides by w, e.g. from view space to normalized device coordinates.
+    pub fn project_point(&self, point: Vec3) -> Vec3 {
+        let result = *self * point.extend(1.0);
+        result.truncate() / result.w
+    }
+
+    pub fn get_translation(&self) -> Vec3 {
+        self.columns[3].truncate()
+    }
+
+    /// Largest factor the matrix stretches a direction by, e.g. to scale the radius of a bounding sphere.
+    pub fn get_max_scale(&self) -> f32 {
+        self.columns[..3].iter().map(|column| column.truncate().length()).fold(0.0, f32::max)
+    }
+
+    pub fn to_cols_array(self) -> [[f32; 4]; 4] {
+        self.columns.map(Vec4::to_array)
+    }
+}
+
+impl Mul for Mat3 {
+    type Output = Mat3;
+
+    fn mul(self, other: Mat3) -> Mat3 {
+        Mat3 { columns: other.columns.map(|column| self * column) }
+    }
+}
+
+impl Mul<Vec3> for Mat3 {
+    type Output = Vec3;
+
+    fn mul(self, vector: Vec3) -> Vec3 {
+        self.columns[0] * vector.x + self.columns[1] * vector.y + self.columns[2] * vector.z
+    }
+}
+
+impl Mul for Mat4 {
+    type Output = Mat4;
+
+    fn mul(self, other: Mat4) -> Mat4 {
+        Mat4 { columns: other.columns.map(|column| self * column) }
+    }
+}
+
+impl Mul<Vec4> for Mat4 {
+    type Output = Vec4;
+
+    fn mul(self, vector: Vec4) -> Vec4 {
+        self.columns[0] * vector.x + self.columns[1] * vector.y + self.columns[2] * vector.z + self.columns[3] * vector.w
+    }
+}
+
+impl Mul<f32> for Mat4 {
+    type Output = Mat4;
+
+    fn mul(self, factor: f32) -> Mat4 {
+        Mat4 { columns: self.columns.map(|column| column * factor) }
+    }
+}
+
+impl MulAssign for Mat4 {
+    fn mul_assign(&mut self, other: Mat4) {
+        *self = *self * other;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1e-5, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn perspective_uses_vulkan_clip_space() {
+        let projection = Mat4::perspective(1.0, 1.5, 0.5, 20.0);
+        assert!(projection.project_point(Vec3::new(0.0, 0.0, -0.5)).z.abs() < 1e-6);
+        assert!((projection.project_point(Vec3::new(0.0, 0.0, -20.0)).z - 1.0).abs() < 1e-6);
+        // Up in view space is the top of the image, where vulkan's y is negative.
+        assert!(projection.project_point(Vec3::new(0.0, 1.0, -2.0)).y < 0.0);
+        assert!(projection.project_point(Vec3::new(1.0, 0.0, -2.0)).x > 0.0);
+    }
+
+    #[test]
+    fn orthographic_maps_the_box_to_clip_space() {
+        let projection = Mat4::orthographic(-2.0, 4.0, -1.0, 3.0, 1.0, 11.0);
+        assert_near(projection.project_point(Vec3::new(-2.0, 3.0, -1.0)), Vec3::new(-1.0, -1.0, 0.0));
+        assert_near(projection.project_point(Vec3::new(4.0, -1.0, -11.0)), Vec3::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn look_at_moves_the_target_in_front_of_the_camera() {
+        let view = Mat4::look_at(Vec3::new(3.0, 2.0, 5.0), Vec3::new(1.0, 2.0, -1.0), Vec3::Y);
+        let distance = Vec3::new(2.0, 0.0, 6.0).length();
+        assert_near(view.transform_point(Vec3::new(1.0, 2.0, -1.0)), Vec3::new(0.0, 0.0, -distance));
+        assert_near(view.inverse().transform_point(Vec3::ZERO), Vec3::new(3.0, 2.0, 5.0));
+    }
+
+    #[test]
+    fn inverses_undo_the_transform() {
+        let transform = Mat4::from_scale_rotation_translation(Vec3::new(1.0, 2.0, 3.0), Quat::from_rotation_y(0.7), Vec3::new(4.0, -1.0, 2.0));
+        let point = Vec3::new(0.3, -2.0, 5.0);
+        assert_near(transform.inverse().transform_point(transform.transform_point(point)), point);
+        assert!((transform.determinant() - 6.0).abs() < 1e-4);
+
+        let matrix = Mat3::from_mat4(&transform);
+        assert_near((matrix.inverse() * matrix) * point, point);
+    }
+
+    #[test]
+    fn cofactors_keep_normals_perpendicular() {
+        let matrix = Mat3::from_scale(Vec3::new(1.0, 4.0, 1.0)) * Mat3::from_quat(Quat::from_rotation_z(0.5));
+        let (tangent, normal) = (Vec3::new(1.0, -1.0, 0.0), Vec3::new(1.0, 1.0, 0.0));
+        assert!(((matrix * tangent).dot(matrix.cofactor() * normal)).abs() < 1e-5);
+        assert_near(matrix.cofactor().transpose() * (matrix * normal), normal * matrix.determinant());
+        // A flattened transform still has a finite normal.
+        let flat = Mat3::from_scale(Vec3::new(1.0, 0.0, 1.0));
+        assert_near(flat.cofactor() * Vec3::Y, Vec3::Y);
+    }
+}
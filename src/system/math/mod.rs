@@ -0,0 +1,4 @@
+pub mod vector;
+pub mod matrix;
+pub mod quaternion;
+pub mod transform;
@@ -0,0 +1,200 @@
+use std::ops::{Mul, MulAssign};
+use bytemuck::{Pod, Zeroable};
+use crate::system::math::matrix::Mat3;
+use crate::system::math::vector::{Vec3, Vec4};
+
+/// Rotation as unit quaternion. `w` is the real part, rotations follow the right hand rule around their axis.
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Debug, Pod, Zeroable)]
+pub struct Quat {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+impl Default for Quat {
+    fn default() -> Self {
+        Quat::IDENTITY
+    }
+}
+
+impl Quat {
+    pub const IDENTITY: Quat = Quat { x: 0.0, y: 0.0, z: 0.0, w: 1.0 };
+
+    /// Rotation by `angle` radians around the axis, which does not have to be normalized.
+    pub fn from_axis_angle(axis: Vec3, angle: f32) -> Self {
+        let axis = axis.normalize_or_zero();
+        let (sin, cos) = (angle * 0.5).sin_cos();
+        Quat { x: axis.x * sin, y: axis.y * sin, z: axis.z * sin, w: cos }
+    }
+
+    pub fn from_rotation_x(angle: f32) -> Self {
+        Quat::from_axis_angle(Vec3::X, angle)
+    }
+
+    pub fn from_rotation_y(angle: f32) -> Self {
+        Quat::from_axis_angle(Vec3::Y, angle)
+    }
+
+    pub fn from_rotation_z(angle: f32) -> Self {
+        Quat::from_axis_angle(Vec3::Z, angle)
+    }
+
+    /// Yaw around y, then pitch around the rotated x axis, then roll around the rotated z axis, all in radians.
+    /// With all angles zero the rotation looks down the negative z axis.
+    pub fn from_yaw_pitch_roll(yaw: f32, pitch: f32, roll: f32) -> Self {
+        Quat::from_rotation_y(yaw) * Quat::from_rotation_x(pitch) * Quat::from_rotation_z(roll)
+    }
+
+    /// Rotation of an orthonormal basis, given as the columns of the matrix.
+    pub fn from_mat3(matrix: &Mat3) -> Self {
+        let [x_axis, y_axis, z_axis] = matrix.columns;
+        let trace = x_axis.x + y_axis.y + z_axis.z;
+        let quaternion = if trace > 0.0 {
+            let s = (trace + 1.0).sqrt() * 2.0;
+            Quat { x: (y_axis.z - z_axis.y) / s, y: (z_axis.x - x_axis.z) / s, z: (x_axis.y - y_axis.x) / s, w: 0.25 * s }
+        } else if x_axis.x > y_axis.y && x_axis.x > z_axis.z {
+            let s = (1.0 + x_axis.x - y_axis.y - z_axis.z).sqrt() * 2.0;
+            Quat { x: 0.25 * s, y: (y_axis.x + x_axis.y) / s, z: (z_axis.x + x_axis.z) / s, w: (y_axis.z - z_axis.y) / s }
+        } else if y_axis.y > z_axis.z {
+            let s = (1.0 + y_axis.y - x_axis.x - z_axis.z).sqrt() * 2.0;
+            Quat { x: (y_axis.x + x_axis.y) / s, y: 0.25 * s, z: (z_axis.y + y_axis.z) / s, w: (z_axis.x - x_axis.z) / s }
+        } else {
+            let s = (1.0 + z_axis.z - x_axis.x - y_axis.y).sqrt() * 2.0;
+            Quat { x: (z_axis.x + x_axis.z) / s, y: (z_axis.y + y_axis.z) / s, z: 0.25 * s, w: (x_axis.y - y_axis.x) / s }
+        };
+        quaternion.normalize()
+    }
+
+    /// Rotation that turns the negative z axis towards `direction` while keeping the x axis perpendicular to `up`.
+    pub fn look_rotation(direction: Vec3, up: Vec3) -> Self {
+        let back = -direction.normalize_or_zero();
+        let side = up.cross(back).normalize_or_zero();
+        if side == Vec3::ZERO || back == Vec3::ZERO {
+            return Quat::IDENTITY;
+        }
+        Quat::from_mat3(&Mat3::from_columns(side, back.cross(side), back))
+    }
+
+    pub fn dot(self, other: Quat) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z + self.w * other.w
+    }
+
+    pub fn length(self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    pub fn normalize(self) -> Self {
+        let length = self.length();
+        if length <= f32::EPSILON {
+            return Quat::IDENTITY;
+        }
+        Quat { x: self.x / length, y: self.y / length, z: self.z / length, w: self.w / length }
+    }
+
+    /// Inverse of a unit quaternion.
+    pub fn conjugate(self) -> Self {
+        Quat { x: -self.x, y: -self.y, z: -self.z, w: self.w }
+    }
+
+    pub fn inverse(self) -> Self {
+        let length_squared = self.dot(self);
+        let conjugate = self.conjugate();
+        Quat { x: conjugate.x / length_squared, y: conjugate.y / length_squared, z: conjugate.z / length_squared, w: conjugate.w / length_squared }
+    }
+
+    /// Spherical interpolation along the shorter arc, `self` at 0 and `other` at 1.
+    pub fn slerp(self, other: Quat, t: f32) -> Self {
+        let mut cos = self.dot(other);
+        let mut other = other;
+        if cos < 0.0 {
+            cos = -cos;
+            other = Quat { x: -other.x, y: -other.y, z: -other.z, w: -other.w };
+        }
+
+        // Nearly parallel rotations are interpolated linearly, the sine below would be close to zero.
+        let (from_weight, to_weight) = if cos > 0.9995 {
+            (1.0 - t, t)
+        } else {
+            let angle = cos.acos();
+            let sin = angle.sin();
+            (((1.0 - t) * angle).sin() / sin, (t * angle).sin() / sin)
+        };
+        Quat {
+            x: self.x * from_weight + other.x * to_weight,
+            y: self.y * from_weight + other.y * to_weight,
+            z: self.z * from_weight + other.z * to_weight,
+            w: self.w * from_weight + other.w * to_weight,
+        }.normalize()
+    }
+
+    pub fn to_vec4(self) -> Vec4 {
+        Vec4::new(self.x, self.y, self.z, self.w)
+    }
+}
+
+/// Applies `other` first and `self` second, like matrix products.
+impl Mul for Quat {
+    type Output = Quat;
+
+    fn mul(self, other: Quat) -> Quat {
+        Quat {
+            x: self.w * other.x + self.x * other.w + self.y * other.z - self.z * other.y,
+            y: self.w * other.y - self.x * other.z + self.y * other.w + self.z * other.x,
+            z: self.w * other.z + self.x * other.y - self.y * other.x + self.z * other.w,
+            w: self.w * other.w - self.x * other.x - self.y * other.y - self.z * other.z,
+        }
+    }
+}
+
+impl MulAssign for Quat {
+    fn mul_assign(&mut self, other: Quat) {
+        *self = *self * other;
+    }
+}
+
+/// Rotates the vector.
+impl Mul<Vec3> for Quat {
+    type Output = Vec3;
+
+    fn mul(self, vector: Vec3) -> Vec3 {
+        let axis = Vec3::new(self.x, self.y, self.z);
+        let t = axis.cross(vector) * 2.0;
+        vector + t * self.w + axis.cross(t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+    use super::*;
+
+    fn assert_near(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1e-5, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn rotations_follow_the_right_hand_rule() {
+        assert_near(Quat::from_rotation_z(FRAC_PI_2) * Vec3::X, Vec3::Y);
+        assert_near(Quat::from_rotation_y(FRAC_PI_2) * Vec3::Z, Vec3::X);
+        // The right hand rotation is applied first.
+        let rotation = Quat::from_rotation_x(FRAC_PI_2) * Quat::from_rotation_z(FRAC_PI_2);
+        assert_near(rotation * Vec3::X, Vec3::Z);
+    }
+
+    #[test]
+    fn look_rotation_turns_negative_z_towards_the_direction() {
+        let direction = Vec3::new(1.0, 1.0, 0.0).normalize();
+        let rotation = Quat::look_rotation(direction, Vec3::Y);
+        assert_near(rotation * -Vec3::Z, direction);
+        assert!((rotation * Vec3::X).dot(Vec3::Y).abs() < 1e-5);
+    }
+
+    #[test]
+    fn slerp_interpolates_the_angle() {
+        let half = Quat::IDENTITY.slerp(Quat::from_rotation_y(FRAC_PI_2), 0.5);
+        assert_near(half * Vec3::Z, Vec3::new(1.0, 0.0, 1.0).normalize());
+        assert_near(half.inverse() * (half * Vec3::X), Vec3::X);
+    }
+}
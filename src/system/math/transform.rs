@@ -0,0 +1,126 @@
+use std::ops::Mul;
+use bytemuck::{Pod, Zeroable};
+use crate::system::math::matrix::{Mat3, Mat4};
+use crate::system::math::quaternion::Quat;
+use crate::system::math::vector::Vec3;
+
+/// Translation, rotation and scale of an object. Points are scaled first, then rotated and translated last.
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Debug, Pod, Zeroable)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::IDENTITY
+    }
+}
+
+impl Transform {
+    pub const IDENTITY: Transform = Transform { translation: Vec3::ZERO, rotation: Quat::IDENTITY, scale: Vec3::ONE };
+
+    pub fn from_translation(translation: Vec3) -> Self {
+        Transform { translation, ..Transform::IDENTITY }
+    }
+
+    pub fn from_rotation(rotation: Quat) -> Self {
+        Transform { rotation, ..Transform::IDENTITY }
+    }
+
+    pub fn from_scale(scale: Vec3) -> Self {
+        Transform { scale, ..Transform::IDENTITY }
+    }
+
+    /// Splits an affine matrix without shear into its parts. Negative scale is put on the x axis.
+    pub fn from_matrix(matrix: &Mat4) -> Self {
+        let rotation_scale = Mat3::from_mat4(matrix);
+        let [x_axis, y_axis, z_axis] = rotation_scale.columns;
+        let sign = if rotation_scale.determinant() < 0.0 { -1.0 } else { 1.0 };
+        let scale = Vec3::new(x_axis.length() * sign, y_axis.length(), z_axis.length());
+        let rotation = Quat::from_mat3(&Mat3::from_columns(x_axis / scale.x, y_axis / scale.y, z_axis / scale.z));
+        Transform { translation: matrix.get_translation(), rotation, scale }
+    }
+
+    /// Transform at `position` that turns its negative z axis towards `target`.
+    pub fn looking_at(position: Vec3, target: Vec3, up: Vec3) -> Self {
+        Transform { translation: position, rotation: Quat::look_rotation(target - position, up), scale: Vec3::ONE }
+    }
+
+    pub fn to_matrix(self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+
+    /// Direction of the negative z axis, where cameras and lights point.
+    pub fn get_forward(&self) -> Vec3 {
+        self.rotation * -Vec3::Z
+    }
+
+    pub fn get_right(&self) -> Vec3 {
+        self.rotation * Vec3::X
+    }
+
+    pub fn get_up(&self) -> Vec3 {
+        self.rotation * Vec3::Y
+    }
+
+    pub fn transform_point(&self, point: Vec3) -> Vec3 {
+        self.rotation * (point * self.scale) + self.translation
+    }
+
+    pub fn transform_vector(&self, vector: Vec3) -> Vec3 {
+        self.rotation * (vector * self.scale)
+    }
+
+    /// Inverse transform. Exact for uniform scale, with non uniform scale and rotation it needs shear and only approximates the inverse.
+    pub fn inverse(&self) -> Self {
+        let rotation = self.rotation.conjugate();
+        let scale = Vec3::ONE / self.scale;
+        Transform { translation: rotation * -self.translation * scale, rotation, scale }
+    }
+}
+
+/// Applies `other` first and `self` second, like matrix products. Exact for uniform scale.
+impl Mul for Transform {
+    type Output = Transform;
+
+    fn mul(self, other: Transform) -> Transform {
+        Transform {
+            translation: self.transform_point(other.translation),
+            rotation: self.rotation * other.rotation,
+            scale: self.scale * other.scale,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1e-4, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn matrix_matches_the_transform() {
+        let transform = Transform { translation: Vec3::new(1.0, 2.0, 3.0), rotation: Quat::from_yaw_pitch_roll(0.4, -0.3, 0.2), scale: Vec3::new(2.0, 0.5, 1.5) };
+        let point = Vec3::new(-1.0, 4.0, 0.5);
+        assert_near(transform.to_matrix().transform_point(point), transform.transform_point(point));
+
+        let decomposed = Transform::from_matrix(&transform.to_matrix());
+        assert_near(decomposed.translation, transform.translation);
+        assert_near(decomposed.scale, transform.scale);
+        assert_near(decomposed.transform_point(point), transform.transform_point(point));
+    }
+
+    #[test]
+    fn composition_and_inverse_with_uniform_scale() {
+        let parent = Transform { translation: Vec3::new(0.0, 1.0, 0.0), rotation: Quat::from_rotation_y(1.2), scale: Vec3::splat(2.0) };
+        let child = Transform { translation: Vec3::new(3.0, 0.0, -1.0), rotation: Quat::from_rotation_x(0.3), scale: Vec3::splat(0.5) };
+        let point = Vec3::new(0.2, -0.7, 1.1);
+        assert_near((parent * child).transform_point(point), parent.transform_point(child.transform_point(point)));
+        assert_near(parent.inverse().transform_point(parent.transform_point(point)), point);
+    }
+}
@@ -0,0 +1,278 @@
+use std::ops::{Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Neg, Sub, SubAssign};
+use bytemuck::{Pod, Zeroable};
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Debug, Default, Pod, Zeroable)]
+pub struct Vec2 {
+    pub x: f32,
+    pub y: f32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Debug, Default, Pod, Zeroable)]
+pub struct Vec3 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Debug, Default, Pod, Zeroable)]
+pub struct Vec4 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+/// Component wise operators, conversions from and to arrays and the operations every vector type shares.
+macro_rules! impl_vector {
+    ($vector:ident, $count:literal, $($component:ident),+) => {
+        impl $vector {
+            pub const ZERO: $vector = $vector { $($component: 0.0),+ };
+            pub const ONE: $vector = $vector { $($component: 1.0),+ };
+
+            pub const fn new($($component: f32),+) -> Self {
+                $vector { $($component),+ }
+            }
+
+            /// Vector with every component set to the value.
+            pub const fn splat(value: f32) -> Self {
+                $vector { $($component: value),+ }
+            }
+
+            pub fn dot(self, other: $vector) -> f32 {
+                0.0 $(+ self.$component * other.$component)+
+            }
+
+            pub fn length_squared(self) -> f32 {
+                self.dot(self)
+            }
+
+            pub fn length(self) -> f32 {
+                self.length_squared().sqrt()
+            }
+
+            pub fn distance(self, other: $vector) -> f32 {
+                (self - other).length()
+            }
+
+            /// Vector with length one in the same direction. The result is not finite for the zero vector.
+            pub fn normalize(self) -> Self {
+                self / self.length()
+            }
+
+            /// Like `normalize`, but returns the zero vector for vectors too short to have a direction.
+            pub fn normalize_or_zero(self) -> Self {
+                let length = self.length();
+                if length <= f32::EPSILON {
+                    return $vector::ZERO;
+                }
+                self / length
+            }
+
+            /// Linear interpolation, `self` at 0 and `other` at 1.
+            pub fn lerp(self, other: $vector, t: f32) -> Self {
+                self + (other - self) * t
+            }
+
+            pub fn min(self, other: $vector) -> Self {
+                $vector { $($component: self.$component.min(other.$component)),+ }
+            }
+
+            pub fn max(self, other: $vector) -> Self {
+                $vector { $($component: self.$component.max(other.$component)),+ }
+            }
+
+            pub fn abs(self) -> Self {
+                $vector { $($component: self.$component.abs()),+ }
+            }
+
+            pub fn to_array(self) -> [f32; $count] {
+                [$(self.$component),+]
+            }
+        }
+
+        impl From<[f32; $count]> for $vector {
+            fn from(array: [f32; $count]) -> Self {
+                let [$($component),+] = array;
+                $vector { $($component),+ }
+            }
+        }
+
+        impl From<$vector> for [f32; $count] {
+            fn from(vector: $vector) -> Self {
+                vector.to_array()
+            }
+        }
+
+        impl Index<usize> for $vector {
+            type Output = f32;
+
+            fn index(&self, index: usize) -> &f32 {
+                &bytemuck::cast_ref::<$vector, [f32; $count]>(self)[index]
+            }
+        }
+
+        impl IndexMut<usize> for $vector {
+            fn index_mut(&mut self, index: usize) -> &mut f32 {
+                &mut bytemuck::cast_mut::<$vector, [f32; $count]>(self)[index]
+            }
+        }
+
+        impl Add for $vector {
+            type Output = $vector;
+
+            fn add(self, other: $vector) -> $vector {
+                $vector { $($component: self.$component + other.$component),+ }
+            }
+        }
+
+        impl Sub for $vector {
+            type Output = $vector;
+
+            fn sub(self, other: $vector) -> $vector {
+                $vector { $($component: self.$component - other.$component),+ }
+            }
+        }
+
+        /// Component wise product.
+        impl Mul for $vector {
+            type Output = $vector;
+
+            fn mul(self, other: $vector) -> $vector {
+                $vector { $($component: self.$component * other.$component),+ }
+            }
+        }
+
+        impl Mul<f32> for $vector {
+            type Output = $vector;
+
+            fn mul(self, factor: f32) -> $vector {
+                $vector { $($component: self.$component * factor),+ }
+            }
+        }
+
+        impl Mul<$vector> for f32 {
+            type Output = $vector;
+
+            fn mul(self, vector: $vector) -> $vector {
+                vector * self
+            }
+        }
+
+        /// Component wise quotient.
+        impl Div for $vector {
+            type Output = $vector;
+
+            fn div(self, other: $vector) -> $vector {
+                $vector { $($component: self.$component / other.$component),+ }
+            }
+        }
+
+        impl Div<f32> for $vector {
+            type Output = $vector;
+
+            fn div(self, divisor: f32) -> $vector {
+                $vector { $($component: self.$component / divisor),+ }
+            }
+        }
+
+        impl Neg for $vector {
+            type Output = $vector;
+
+            fn neg(self) -> $vector {
+                $vector { $($component: -self.$component),+ }
+            }
+        }
+
+        impl AddAssign for $vector {
+            fn add_assign(&mut self, other: $vector) {
+                *self = *self + other;
+            }
+        }
+
+        impl SubAssign for $vector {
+            fn sub_assign(&mut self, other: $vector) {
+                *self = *self - other;
+            }
+        }
+
+        impl MulAssign<f32> for $vector {
+            fn mul_assign(&mut self, factor: f32) {
+                *self = *self * factor;
+            }
+        }
+
+        impl DivAssign<f32> for $vector {
+            fn div_assign(&mut self, divisor: f32) {
+                *self = *self / divisor;
+            }
+        }
+    };
+}
+
+impl_vector!(Vec2, 2, x, y);
+impl_vector!(Vec3, 3, x, y, z);
+impl_vector!(Vec4, 4, x, y, z, w);
+
+impl Vec2 {
+    pub const X: Vec2 = Vec2::new(1.0, 0.0);
+    pub const Y: Vec2 = Vec2::new(0.0, 1.0);
+
+    pub fn extend(self, z: f32) -> Vec3 {
+        Vec3::new(self.x, self.y, z)
+    }
+}
+
+/// World space is right handed with y up, so `X` points right, `Y` up and `Z` towards a viewer looking down the negative z axis.
+impl Vec3 {
+    pub const X: Vec3 = Vec3::new(1.0, 0.0, 0.0);
+    pub const Y: Vec3 = Vec3::new(0.0, 1.0, 0.0);
+    pub const Z: Vec3 = Vec3::new(0.0, 0.0, 1.0);
+
+    pub fn cross(self, other: Vec3) -> Vec3 {
+        Vec3::new(self.y * other.z - self.z * other.y, self.z * other.x - self.x * other.z, self.x * other.y - self.y * other.x)
+    }
+
+    pub fn extend(self, w: f32) -> Vec4 {
+        Vec4::new(self.x, self.y, self.z, w)
+    }
+
+    pub fn truncate(self) -> Vec2 {
+        Vec2::new(self.x, self.y)
+    }
+}
+
+impl Vec4 {
+    pub const X: Vec4 = Vec4::new(1.0, 0.0, 0.0, 0.0);
+    pub const Y: Vec4 = Vec4::new(0.0, 1.0, 0.0, 0.0);
+    pub const Z: Vec4 = Vec4::new(0.0, 0.0, 1.0, 0.0);
+    pub const W: Vec4 = Vec4::new(0.0, 0.0, 0.0, 1.0);
+
+    pub fn truncate(self) -> Vec3 {
+        Vec3::new(self.x, self.y, self.z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cross_product_is_right_handed() {
+        assert_eq!(Vec3::X.cross(Vec3::Y), Vec3::Z);
+        assert_eq!(Vec3::Y.cross(Vec3::Z), Vec3::X);
+        assert_eq!(Vec3::Z.cross(Vec3::X), Vec3::Y);
+    }
+
+    #[test]
+    fn vectors_convert_to_arrays_and_bytes() {
+        let vector = Vec4::from([1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(vector[2], 3.0);
+        assert_eq!(<[f32; 4]>::from(vector * 2.0), [2.0, 4.0, 6.0, 8.0]);
+        assert_eq!(bytemuck::bytes_of(&vector).len(), 16);
+        assert_eq!(Vec3::new(3.0, 0.0, 4.0).normalize_or_zero(), Vec3::new(0.6, 0.0, 0.8));
+        assert_eq!(Vec2::ZERO.normalize_or_zero(), Vec2::ZERO);
+    }
+}
@@ -7,4 +7,10 @@ mod window;
 mod file;
 mod ini;
 mod rendering;
+mod assets;
 mod error;
+mod math;
+mod input;
+mod scene;
+mod ecs;
+mod gameplay;
@@ -0,0 +1,144 @@
+use crate::system::math::matrix::Mat4;
+use crate::system::math::transform::Transform;
+use crate::system::math::vector::Vec3;
+use crate::system::rendering::camera_uniform::CameraUniform;
+use crate::system::rendering::mesh::bounds::BoundingSphere;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Projection {
+    /// Vertical field of view in radians.
+    Perspective { fov_y: f32, near: f32, far: f32 },
+    /// Height of the visible box in world units, its width follows from the aspect ratio.
+    Orthographic { height: f32, near: f32, far: f32 },
+}
+
+/// Camera with projection parameters and a transform in world space. It looks down the negative z axis of its transform with y up.
+/// Scale of the transform is ignored.
+#[derive(Clone, Copy, Debug)]
+pub struct Camera {
+    pub projection: Projection,
+    pub transform: Transform,
+    aspect_ratio: f32,
+}
+
+impl Camera {
+    pub fn perspective(fov_y: f32, aspect_ratio: f32, near: f32, far: f32) -> Self {
+        Camera { projection: Projection::Perspective { fov_y, near, far }, transform: Transform::IDENTITY, aspect_ratio }
+    }
+
+    pub fn orthographic(height: f32, aspect_ratio: f32, near: f32, far: f32) -> Self {
+        Camera { projection: Projection::Orthographic { height, near, far }, transform: Transform::IDENTITY, aspect_ratio }
+    }
+
+    pub fn get_aspect_ratio(&self) -> f32 {
+        self.aspect_ratio
+    }
+
+    pub fn set_aspect_ratio(&mut self, aspect_ratio: f32) {
+        self.aspect_ratio = aspect_ratio;
+    }
+
+    /// Sets the aspect ratio from the size of the viewport. A minimized window keeps the last aspect ratio.
+    pub fn set_viewport_size(&mut self, width: u32, height: u32) {
+        if width > 0 && height > 0 {
+            self.aspect_ratio = width as f32 / height as f32;
+        }
+    }
+
+    pub fn get_position(&self) -> Vec3 {
+        self.transform.translation
+    }
+
+    pub fn get_forward(&self) -> Vec3 {
+        self.transform.get_forward()
+    }
+
+    /// Moves the camera to `position` and turns it towards `target`.
+    pub fn look_at(&mut self, position: Vec3, target: Vec3, up: Vec3) {
+        self.transform = Transform::looking_at(position, target, up);
+    }
+
+    /// Keeps the direction of the camera and moves it back until the whole sphere is visible.
+    /// Near and far plane leave room to move around the sphere.
+    pub fn frame_sphere(&mut self, sphere: &BoundingSphere) {
+        let center = Vec3::from(sphere.center);
+        let radius = sphere.radius.max(0.001);
+        let distance = match &mut self.projection {
+            Projection::Perspective { fov_y, near, far } => {
+                // The sphere has to fit into the narrower of both fields of view.
+                let half_fov = (*fov_y * 0.5).min((self.aspect_ratio * (*fov_y * 0.5).tan()).atan());
+                let distance = radius / half_fov.sin();
+                *near = radius * 0.01;
+                *far = distance + radius * 100.0;
+                distance
+            }
+            Projection::Orthographic { height, near, far } => {
+                *height = radius * 2.0 / self.aspect_ratio.min(1.0);
+                *near = radius * 0.01;
+                *far = radius * 100.0;
+                radius * 2.0
+            }
+        };
+        self.transform.translation = center - self.get_forward() * distance;
+    }
+
+    /// World to view space. The inverse of the transform without its scale.
+    pub fn get_view_matrix(&self) -> Mat4 {
+        let rotation = self.transform.rotation.conjugate();
+        Mat4::from_quat(rotation) * Mat4::from_translation(-self.transform.translation)
+    }
+
+    /// View to clip space, with vulkan's depth range and y axis.
+    pub fn get_projection_matrix(&self) -> Mat4 {
+        match self.projection {
+            Projection::Perspective { fov_y, near, far } => Mat4::perspective(fov_y, self.aspect_ratio, near, far),
+            Projection::Orthographic { height, near, far } => {
+                let half_height = height * 0.5;
+                let half_width = half_height * self.aspect_ratio;
+                Mat4::orthographic(-half_width, half_width, -half_height, half_height, near, far)
+            }
+        }
+    }
+
+    /// Matrices in the layout the renderers upload to the camera uniform block.
+    pub fn get_uniform(&self) -> CameraUniform {
+        CameraUniform { view: self.get_view_matrix(), projection: self.get_projection_matrix() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_3;
+    use super::*;
+
+    #[test]
+    fn view_matrix_matches_look_at() {
+        let mut camera = Camera::perspective(FRAC_PI_3, 1.5, 0.1, 100.0);
+        camera.look_at(Vec3::new(1.5, 1.2, 2.0), Vec3::ZERO, Vec3::Y);
+        let expected = Mat4::look_at(Vec3::new(1.5, 1.2, 2.0), Vec3::ZERO, Vec3::Y);
+        let point = Vec3::new(0.3, -0.4, 0.5);
+        assert!((camera.get_view_matrix().transform_point(point) - expected.transform_point(point)).length() < 1e-5);
+    }
+
+    #[test]
+    fn framed_spheres_are_inside_the_frustum() {
+        let sphere = BoundingSphere { center: [1.0, 2.0, 3.0], radius: 2.0 };
+        for mut camera in [Camera::perspective(FRAC_PI_3, 0.5, 0.1, 100.0), Camera::orthographic(1.0, 2.0, 0.1, 100.0)] {
+            camera.frame_sphere(&sphere);
+            let uniform = camera.get_uniform();
+            for offset in [Vec3::X, -Vec3::X, Vec3::Y, -Vec3::Y, Vec3::Z, -Vec3::Z] {
+                let clip = uniform.project((Vec3::from(sphere.center) + offset * sphere.radius * 0.99).into());
+                assert!(clip[0].abs() <= clip[3] && clip[1].abs() <= clip[3] && clip[2] >= 0.0 && clip[2] <= clip[3], "{:?}", clip);
+            }
+        }
+    }
+
+    #[test]
+    fn resizing_updates_the_aspect_ratio() {
+        let mut camera = Camera::perspective(FRAC_PI_3, 1.0, 0.1, 100.0);
+        camera.set_viewport_size(1920, 1080);
+        assert!((camera.get_aspect_ratio() - 16.0 / 9.0).abs() < 1e-6);
+        camera.set_viewport_size(0, 0);
+        assert!((camera.get_aspect_ratio() - 16.0 / 9.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,169 @@
+use std::f32::consts::FRAC_PI_2;
+use winit::event::{MouseButton, VirtualKeyCode};
+use crate::system::input::InputState;
+use crate::system::math::quaternion::Quat;
+use crate::system::math::vector::Vec3;
+use crate::system::rendering::camera::Camera;
+use crate::system::rendering::mesh::bounds::BoundingSphere;
+
+/// Radians the camera turns per unit of raw mouse motion, which is about a pixel on most systems.
+const MOUSE_SENSITIVITY: f32 = 0.005;
+/// Pitch stays a little below straight up and down, where yaw is no longer defined.
+const MAX_PITCH: f32 = FRAC_PI_2 - 0.01;
+/// Factor the orbit distance changes by per line of the mouse wheel.
+const ZOOM_PER_SCROLL_LINE: f32 = 0.9;
+/// Speed factor of the fly controller while shift is held.
+const FAST_FACTOR: f32 = 4.0;
+
+/// Moves a camera from window input. Controllers keep their own yaw and pitch and overwrite the camera's transform on every update.
+pub trait CameraController {
+    /// Takes over the current position and orientation of the camera, so switching controllers does not make the camera jump.
+    fn attach(&mut self, camera: &Camera);
+    /// Adapts target, distance or speed to an object of the size of the sphere and moves the camera so it is visible.
+    fn frame(&mut self, camera: &mut Camera, sphere: &BoundingSphere);
+    fn update(&mut self, camera: &mut Camera, input: &InputState, delta_seconds: f32);
+    /// Whether the controller turns the camera with the mouse right now, so the window should grab the cursor.
+    fn is_looking(&self, input: &InputState) -> bool;
+}
+
+/// Yaw around the y axis and pitch above the horizon of a rotation looking in the direction.
+fn get_yaw_pitch(direction: Vec3) -> (f32, f32) {
+    let direction = direction.normalize_or_zero();
+    ((-direction.x).atan2(-direction.z), direction.y.clamp(-1.0, 1.0).asin().clamp(-MAX_PITCH, MAX_PITCH))
+}
+
+/// Free flying camera: WASD moves, Q and E move down and up, shift moves faster and the mouse turns the camera while the right button is held.
+pub struct FlyController {
+    /// Units per second.
+    pub speed: f32,
+    yaw: f32,
+    pitch: f32,
+}
+
+impl FlyController {
+    pub fn new(speed: f32) -> Self {
+        FlyController { speed, yaw: 0.0, pitch: 0.0 }
+    }
+}
+
+impl CameraController for FlyController {
+    fn attach(&mut self, camera: &Camera) {
+        (self.yaw, self.pitch) = get_yaw_pitch(camera.get_forward());
+    }
+
+    fn frame(&mut self, camera: &mut Camera, sphere: &BoundingSphere) {
+        self.speed = sphere.radius.max(0.001);
+        camera.frame_sphere(sphere);
+    }
+
+    fn update(&mut self, camera: &mut Camera, input: &InputState, delta_seconds: f32) {
+        if self.is_looking(input) {
+            self.yaw -= input.mouse_delta.x * MOUSE_SENSITIVITY;
+            self.pitch = (self.pitch - input.mouse_delta.y * MOUSE_SENSITIVITY).clamp(-MAX_PITCH, MAX_PITCH);
+        }
+        camera.transform.rotation = Quat::from_yaw_pitch_roll(self.yaw, self.pitch, 0.0);
+
+        let axis = |positive: VirtualKeyCode, negative: VirtualKeyCode| {
+            input.is_key_down(positive) as i32 as f32 - input.is_key_down(negative) as i32 as f32
+        };
+        let direction = camera.transform.get_forward() * axis(VirtualKeyCode::W, VirtualKeyCode::S)
+            + camera.transform.get_right() * axis(VirtualKeyCode::D, VirtualKeyCode::A)
+            + Vec3::Y * axis(VirtualKeyCode::E, VirtualKeyCode::Q);
+        let speed = if input.is_key_down(VirtualKeyCode::LShift) { self.speed * FAST_FACTOR } else { self.speed };
+        camera.transform.translation += direction.normalize_or_zero() * speed * delta_seconds;
+    }
+
+    fn is_looking(&self, input: &InputState) -> bool {
+        input.is_button_down(MouseButton::Right)
+    }
+}
+
+/// Camera circling a target point: dragging with the left button turns it around the target and the mouse wheel zooms.
+pub struct OrbitController {
+    pub target: Vec3,
+    pub distance: f32,
+    yaw: f32,
+    pitch: f32,
+}
+
+impl OrbitController {
+    pub fn new(target: Vec3, distance: f32) -> Self {
+        OrbitController { target, distance, yaw: 0.0, pitch: 0.0 }
+    }
+
+    fn place_camera(&self, camera: &mut Camera) {
+        camera.transform.rotation = Quat::from_yaw_pitch_roll(self.yaw, self.pitch, 0.0);
+        camera.transform.translation = self.target - camera.transform.get_forward() * self.distance;
+    }
+}
+
+impl CameraController for OrbitController {
+    /// Keeps the distance and orbits the point that far in front of the camera.
+    fn attach(&mut self, camera: &Camera) {
+        (self.yaw, self.pitch) = get_yaw_pitch(camera.get_forward());
+        self.target = camera.get_position() + camera.get_forward() * self.distance;
+    }
+
+    fn frame(&mut self, camera: &mut Camera, sphere: &BoundingSphere) {
+        camera.frame_sphere(sphere);
+        self.target = Vec3::from(sphere.center);
+        self.distance = (camera.get_position() - self.target).length();
+        (self.yaw, self.pitch) = get_yaw_pitch(camera.get_forward());
+    }
+
+    fn update(&mut self, camera: &mut Camera, input: &InputState, _delta_seconds: f32) {
+        if self.is_looking(input) {
+            self.yaw -= input.mouse_delta.x * MOUSE_SENSITIVITY;
+            self.pitch = (self.pitch - input.mouse_delta.y * MOUSE_SENSITIVITY).clamp(-MAX_PITCH, MAX_PITCH);
+        }
+        self.distance *= ZOOM_PER_SCROLL_LINE.powf(input.scroll_delta);
+        self.place_camera(camera);
+    }
+
+    fn is_looking(&self, input: &InputState) -> bool {
+        input.is_button_down(MouseButton::Left)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_3;
+    use super::*;
+
+    fn assert_near(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1e-4, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn fly_controller_moves_along_the_view_direction() {
+        let mut camera = Camera::perspective(FRAC_PI_3, 1.0, 0.1, 100.0);
+        camera.look_at(Vec3::ZERO, Vec3::new(1.0, 0.0, 0.0), Vec3::Y);
+        let mut controller = FlyController::new(2.0);
+        controller.attach(&camera);
+
+        let mut input = InputState::default();
+        input.pressed_keys.insert(VirtualKeyCode::W);
+        controller.update(&mut camera, &input, 0.5);
+        assert_near(camera.get_position(), Vec3::new(1.0, 0.0, 0.0));
+        assert_near(camera.get_forward(), Vec3::X);
+    }
+
+    #[test]
+    fn orbit_controller_keeps_its_distance() {
+        let mut camera = Camera::perspective(FRAC_PI_3, 1.0, 0.1, 100.0);
+        let mut controller = OrbitController::new(Vec3::ZERO, 1.0);
+        controller.frame(&mut camera, &BoundingSphere { center: [0.0, 1.0, 0.0], radius: 1.0 });
+        let distance = controller.distance;
+
+        let mut input = InputState::default();
+        input.pressed_buttons.insert(MouseButton::Left);
+        input.mouse_delta.x = 200.0;
+        controller.update(&mut camera, &input, 0.016);
+        assert!(((camera.get_position() - Vec3::Y).length() - distance).abs() < 1e-4);
+        assert_near(camera.get_position() + camera.get_forward() * distance, Vec3::Y);
+
+        input.scroll_delta = 1.0;
+        controller.update(&mut camera, &input, 0.016);
+        assert!((controller.distance - distance * ZOOM_PER_SCROLL_LINE).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,37 @@
+use bytemuck::{Pod, Zeroable};
+use crate::system::math::matrix::Mat4;
+use crate::system::math::vector::Vec3;
+
+/// Camera matrices as the shaders see them in the uniform block at set 0, binding 0.
+/// World space is right handed with y up. The projection maps depth to [0, 1] and flips y, as vulkan expects.
+#[repr(C)]
+#[derive(Clone, Copy, Default, Pod, Zeroable)]
+pub struct CameraUniform {
+    pub view: Mat4,
+    pub projection: Mat4,
+}
+
+impl CameraUniform {
+    /// Perspective camera at `eye` looking at `target`. The vertical field of view is given in radians.
+    pub fn look_at(eye: [f32; 3], target: [f32; 3], up: [f32; 3], fov_y: f32, aspect_ratio: f32, near: f32, far: f32) -> Self {
+        CameraUniform {
+            view: Mat4::look_at(eye.into(), target.into(), up.into()),
+            projection: Mat4::perspective(fov_y, aspect_ratio, near, far),
+        }
+    }
+
+    /// Transforms a position from world space into clip space.
+    pub fn project(&self, position: [f32; 3]) -> [f32; 4] {
+        (self.projection * (self.view * Vec3::from(position).extend(1.0))).to_array()
+    }
+
+    /// Distance of a world space position from the eye.
+    pub fn get_distance(&self, position: [f32; 3]) -> f32 {
+        self.view.transform_point(position.into()).length()
+    }
+
+    /// Number of pixels one world unit covers at distance one, on a viewport with the given height.
+    pub fn get_pixels_per_unit(&self, viewport_height: u32) -> f32 {
+        self.projection.columns[1].y.abs() * viewport_height as f32 * 0.5
+    }
+}
@@ -0,0 +1,83 @@
+use crate::system::math::matrix::Mat4;
+use crate::system::math::vector::{Vec3, Vec4};
+use crate::system::rendering::camera_uniform::CameraUniform;
+use crate::system::rendering::mesh::bounds::Aabb;
+
+/// The six planes around the volume a camera sees, in world space. The normals point inwards and are normalized,
+/// so the dot product of a plane with a point gives its distance.
+#[derive(Clone, Copy, Debug)]
+pub struct Frustum {
+    planes: [Vec4; 6],
+}
+
+impl Frustum {
+    /// Extracts the planes from a world to clip space matrix with vulkan's depth range [0, 1].
+    pub fn from_matrix(matrix: &Mat4) -> Self {
+        let [x, y, z, w] = [0, 1, 2, 3].map(|row| matrix.get_row(row));
+        let planes = [w + x, w - x, w + y, w - y, z, w - z].map(|plane| {
+            let length = plane.truncate().length();
+            if length > 0.0 { plane / length } else { plane }
+        });
+        Frustum { planes }
+    }
+
+    pub fn from_camera(camera: &CameraUniform) -> Self {
+        Frustum::from_matrix(&(camera.projection * camera.view))
+    }
+
+    /// False only if the sphere is completely outside of one plane.
+    pub fn intersects_sphere(&self, center: Vec3, radius: f32) -> bool {
+        self.planes.iter().all(|plane| plane.truncate().dot(center) + plane.w >= -radius)
+    }
+
+    /// Tests the box after moving it into world space with the transform. The transformed box is enclosed by a new axis aligned one,
+    /// so boxes close to a corner of the frustum may count as visible although they are not.
+    pub fn intersects_aabb(&self, aabb: &Aabb, transform: &Mat4) -> bool {
+        let center = transform.transform_point(Vec3::from(aabb.get_center()));
+        let extents = Vec3::from(aabb.get_extents());
+        // Every world axis gets the extents the rotated and scaled local axes reach along it.
+        let world_extents = Vec3::new(
+            transform.get_row(0).truncate().abs().dot(extents),
+            transform.get_row(1).truncate().abs().dot(extents),
+            transform.get_row(2).truncate().abs().dot(extents),
+        );
+        self.planes.iter().all(|plane| {
+            let normal = plane.truncate();
+            normal.dot(center) + plane.w >= -normal.abs().dot(world_extents)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+    use super::*;
+
+    fn camera() -> CameraUniform {
+        // Looks down the negative z axis from the origin, 90 degrees wide.
+        CameraUniform::look_at([0.0; 3], [0.0, 0.0, -1.0], [0.0, 1.0, 0.0], FRAC_PI_2, 1.0, 0.1, 100.0)
+    }
+
+    #[test]
+    fn spheres_are_tested_against_all_planes() {
+        let frustum = Frustum::from_camera(&camera());
+        assert!(frustum.intersects_sphere(Vec3::new(0.0, 0.0, -10.0), 1.0));
+        assert!(!frustum.intersects_sphere(Vec3::new(0.0, 0.0, 10.0), 1.0));
+        assert!(!frustum.intersects_sphere(Vec3::new(0.0, 0.0, -200.0), 1.0));
+        assert!(!frustum.intersects_sphere(Vec3::new(20.0, 0.0, -10.0), 1.0));
+        // Partially inside the left plane.
+        assert!(frustum.intersects_sphere(Vec3::new(-10.5, 0.0, -10.0), 1.0));
+    }
+
+    #[test]
+    fn boxes_are_moved_by_their_transform() {
+        let frustum = Frustum::from_camera(&camera());
+        let aabb = Aabb { min: [-1.0; 3], max: [1.0; 3] };
+        assert!(!frustum.intersects_aabb(&aabb, &Mat4::from_translation(Vec3::new(0.0, 0.0, 10.0))));
+        assert!(frustum.intersects_aabb(&aabb, &Mat4::from_translation(Vec3::new(0.0, 0.0, -10.0))));
+        assert!(!frustum.intersects_aabb(&aabb, &Mat4::from_translation(Vec3::new(15.0, 0.0, -10.0))));
+        // Scaled up, the same box reaches into the frustum.
+        let scaled = Mat4::from_translation(Vec3::new(15.0, 0.0, -10.0)) * Mat4::from_scale(Vec3::splat(10.0));
+        assert!(frustum.intersects_aabb(&aabb, &scaled));
+    }
+}
@@ -0,0 +1,360 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Pipeline materials use if they don't name one.
+pub const DEFAULT_MATERIAL_PIPELINE: &str = "opaque";
+/// Parameter the default shaders multiply their color with. Importers always declare it first.
+pub const BASE_COLOR_PARAMETER: &str = "BaseColor";
+
+/// Everything that can go wrong while loading a material description.
+#[derive(Debug)]
+pub enum MaterialError {
+    Io { path: PathBuf, error: io::Error },
+    Parse { path: PathBuf, line: usize, message: String },
+}
+
+impl fmt::Display for MaterialError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MaterialError::Io { path, error } => write!(f, "{}: {}", path.display(), error),
+            MaterialError::Parse { path, line, message } => write!(f, "{}:{}: {}", path.display(), line, message),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MaterialValue {
+    Float(f32),
+    Vec2([f32; 2]),
+    Vec3([f32; 3]),
+    Vec4([f32; 4]),
+}
+
+impl MaterialValue {
+    /// The value padded with zeros. Every parameter takes a whole vec4 in the uniform block of the material.
+    pub fn to_vec4(self) -> [f32; 4] {
+        match self {
+            MaterialValue::Float(x) => [x, 0.0, 0.0, 0.0],
+            MaterialValue::Vec2([x, y]) => [x, y, 0.0, 0.0],
+            MaterialValue::Vec3([x, y, z]) => [x, y, z, 0.0],
+            MaterialValue::Vec4(value) => value,
+        }
+    }
+
+    fn get_component_count(&self) -> usize {
+        match self {
+            MaterialValue::Float(_) => 1,
+            MaterialValue::Vec2(_) => 2,
+            MaterialValue::Vec3(_) => 3,
+            MaterialValue::Vec4(_) => 4,
+        }
+    }
+
+    /// Parses one to four numbers separated by whitespace.
+    fn parse(text: &str) -> Option<MaterialValue> {
+        let numbers: Vec<f32> = text.split_whitespace().map(|number| number.parse::<f32>()).collect::<Result<_, _>>().ok()?;
+        match numbers.as_slice() {
+            [x] => Some(MaterialValue::Float(*x)),
+            [x, y] => Some(MaterialValue::Vec2([*x, *y])),
+            [x, y, z] => Some(MaterialValue::Vec3([*x, *y, *z])),
+            [x, y, z, w] => Some(MaterialValue::Vec4([*x, *y, *z, *w])),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct MaterialParameter {
+    pub name: String,
+    pub value: MaterialValue,
+}
+
+/// Texture slot of a material. Slots without a path are left for instances to fill.
+#[derive(Clone, PartialEq, Debug)]
+pub struct MaterialTexture {
+    pub name: String,
+    pub path: Option<PathBuf>,
+}
+
+/// Describes how a surface is drawn: the pipeline, the textures and the parameters of its shaders.
+/// The parameters end up in a uniform block in the order they are declared, so they have to match the shader.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Material {
+    pub name: String,
+    pub pipeline: String,
+    pub textures: Vec<MaterialTexture>,
+    pub parameters: Vec<MaterialParameter>,
+}
+
+impl Material {
+    /// Material of meshes that have none. It draws the default shaders with a white base color.
+    pub fn fallback() -> Self {
+        Material {
+            name: "Default".to_string(),
+            pipeline: DEFAULT_MATERIAL_PIPELINE.to_string(),
+            textures: Vec::new(),
+            parameters: vec![MaterialParameter { name: BASE_COLOR_PARAMETER.to_string(), value: MaterialValue::Vec4([1.0, 1.0, 1.0, 1.0]) }],
+        }
+    }
+
+    /// Loads a material description. Files that override another material have to be loaded as `MaterialInstance`.
+    pub fn load(path: &Path) -> Result<Material, MaterialError> {
+        let file = MaterialFile::parse(path)?;
+        if file.parent.is_some() {
+            return Err(parse_error(path, file.parent_line, "the file describes a material instance".to_string()));
+        }
+        file.into_material(path)
+    }
+
+    fn find_parameter(&self, name: &str) -> Option<usize> {
+        self.parameters.iter().position(|parameter| parameter.name == name)
+    }
+
+    fn find_texture(&self, name: &str) -> Option<usize> {
+        self.textures.iter().position(|texture| texture.name == name)
+    }
+}
+
+/// Shares a material and overrides some of its parameters and textures. Meshes always reference an instance,
+/// which may also override nothing at all.
+#[derive(Clone, PartialEq, Debug)]
+pub struct MaterialInstance {
+    pub name: String,
+    pub material: Arc<Material>,
+    /// Current values, in the order of the material parameters.
+    parameters: Vec<MaterialValue>,
+    textures: Vec<Option<PathBuf>>,
+}
+
+impl MaterialInstance {
+    pub fn new(name: &str, material: Arc<Material>) -> Self {
+        MaterialInstance {
+            name: name.to_string(),
+            parameters: material.parameters.iter().map(|parameter| parameter.value).collect(),
+            textures: material.textures.iter().map(|texture| texture.path.clone()).collect(),
+            material,
+        }
+    }
+
+    /// Instance of `Material::fallback`, used for meshes without a material.
+    pub fn fallback() -> Self {
+        let material = Material::fallback();
+        MaterialInstance::new(&material.name.clone(), Arc::new(material))
+    }
+
+    /// Loads a material description. With a `Parent` entry the file is an instance of that material
+    /// and may only override what the parent declares, otherwise it is an instance of the material it describes.
+    pub fn load(path: &Path) -> Result<MaterialInstance, MaterialError> {
+        let file = MaterialFile::parse(path)?;
+        let parent_path = match &file.parent {
+            Some(parent) => path.parent().unwrap_or_else(|| Path::new("")).join(parent),
+            None => {
+                let material = file.into_material(path)?;
+                return Ok(MaterialInstance::new(&material.name.clone(), Arc::new(material)));
+            }
+        };
+        if file.pipeline.is_some() {
+            return Err(parse_error(path, file.parent_line, "instances can not change the pipeline".to_string()));
+        }
+
+        let material = Arc::new(Material::load(&parent_path)?);
+        let mut instance = MaterialInstance::new(&file.name, material);
+        for (name, value, line) in file.parameters {
+            if !instance.set_parameter(&name, value) {
+                return Err(parse_error(path, line, format!("parameter {} is not declared by the parent or has a different type", name)));
+            }
+        }
+        for (name, texture_path, line) in file.textures {
+            if !instance.set_texture(&name, texture_path) {
+                return Err(parse_error(path, line, format!("texture {} is not declared by the parent", name)));
+            }
+        }
+        Ok(instance)
+    }
+
+    pub fn get_pipeline(&self) -> &str {
+        &self.material.pipeline
+    }
+
+    pub fn get_parameter(&self, name: &str) -> Option<MaterialValue> {
+        self.material.find_parameter(name).map(|index| self.parameters[index])
+    }
+
+    /// Overrides a parameter. Returns false if the material has no such parameter or it has a different type.
+    pub fn set_parameter(&mut self, name: &str, value: MaterialValue) -> bool {
+        match self.material.find_parameter(name) {
+            Some(index) if self.parameters[index].get_component_count() == value.get_component_count() => {
+                self.parameters[index] = value;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn get_texture(&self, name: &str) -> Option<&Path> {
+        self.material.find_texture(name).and_then(|index| self.textures[index].as_deref())
+    }
+
+    /// Paths of all texture slots in the order the material declares them, `None` for empty slots.
+    pub fn get_textures(&self) -> &[Option<PathBuf>] {
+        &self.textures
+    }
+
+    /// Overrides a texture slot. Returns false if the material has no such slot.
+    pub fn set_texture(&mut self, name: &str, path: Option<PathBuf>) -> bool {
+        match self.material.find_texture(name) {
+            Some(index) => {
+                self.textures[index] = path;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Contents of the uniform block of the material: one vec4 per parameter, in declaration order.
+    pub fn get_uniform_data(&self) -> Vec<[f32; 4]> {
+        self.parameters.iter().map(|value| value.to_vec4()).collect()
+    }
+}
+
+/// Contents of a material description before it is checked against a parent. The format follows settings.ini:
+///
+/// ```text
+/// #Material
+/// Name=Brick
+/// Pipeline=opaque
+/// #Textures
+/// BaseColor=textures/brick.png
+/// #Parameters
+/// BaseColor=1.0 0.9 0.8 1.0
+/// Roughness=0.7
+/// ```
+///
+/// Instances name their material with `Parent=brick.material` instead of a pipeline. Lines starting with `//` are comments.
+struct MaterialFile {
+    name: String,
+    parent: Option<PathBuf>,
+    parent_line: usize,
+    pipeline: Option<String>,
+    textures: Vec<(String, Option<PathBuf>, usize)>,
+    parameters: Vec<(String, MaterialValue, usize)>,
+}
+
+impl MaterialFile {
+    fn parse(path: &Path) -> Result<MaterialFile, MaterialError> {
+        let source = fs::read_to_string(path).map_err(|error| MaterialError::Io { path: path.to_path_buf(), error })?;
+        let directory = path.parent().unwrap_or_else(|| Path::new(""));
+        let mut file = MaterialFile {
+            name: path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default(),
+            parent: None,
+            parent_line: 0,
+            pipeline: None,
+            textures: Vec::new(),
+            parameters: Vec::new(),
+        };
+
+        let mut section = "";
+        for (line_index, line) in source.lines().enumerate() {
+            let line_number = line_index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with("//") {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('#') {
+                section = name.trim();
+                if !matches!(section, "Material" | "Textures" | "Parameters") {
+                    return Err(parse_error(path, line_number, format!("unknown section {}", section)));
+                }
+                continue;
+            }
+
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => return Err(parse_error(path, line_number, format!("expected Key=Value, found {}", line))),
+            };
+            match (section, key) {
+                ("Material", "Name") => file.name = value.to_string(),
+                ("Material", "Pipeline") => file.pipeline = Some(value.to_string()),
+                ("Material", "Parent") => {
+                    file.parent = Some(PathBuf::from(value));
+                    file.parent_line = line_number;
+                }
+                ("Textures", _) => {
+                    let texture_path = if value.is_empty() { None } else { Some(directory.join(value)) };
+                    file.textures.push((key.to_string(), texture_path, line_number));
+                }
+                ("Parameters", _) => match MaterialValue::parse(value) {
+                    Some(value) => file.parameters.push((key.to_string(), value, line_number)),
+                    None => return Err(parse_error(path, line_number, format!("parameter {} needs one to four numbers", key))),
+                },
+                _ => return Err(parse_error(path, line_number, format!("unexpected entry {}", key))),
+            }
+        }
+        Ok(file)
+    }
+
+    fn into_material(self, path: &Path) -> Result<Material, MaterialError> {
+        let mut material = Material {
+            name: self.name,
+            pipeline: self.pipeline.unwrap_or_else(|| DEFAULT_MATERIAL_PIPELINE.to_string()),
+            textures: Vec::new(),
+            parameters: Vec::new(),
+        };
+        for (name, texture_path, line) in self.textures {
+            if material.find_texture(&name).is_some() {
+                return Err(parse_error(path, line, format!("texture {} is declared twice", name)));
+            }
+            material.textures.push(MaterialTexture { name, path: texture_path });
+        }
+        for (name, value, line) in self.parameters {
+            if material.find_parameter(&name).is_some() {
+                return Err(parse_error(path, line, format!("parameter {} is declared twice", name)));
+            }
+            material.parameters.push(MaterialParameter { name, value });
+        }
+        Ok(material)
+    }
+}
+
+fn parse_error(path: &Path, line: usize, message: String) -> MaterialError {
+    MaterialError::Parse { path: path.to_path_buf(), line, message }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn instance_overrides_its_parent() {
+        let instance = MaterialInstance::load(Path::new("assets/materials/checker_red.material")).unwrap();
+        assert_eq!(instance.name, "CheckerRed");
+        assert_eq!(instance.material.name, "Checker");
+        assert_eq!(instance.get_pipeline(), "opaque");
+        assert_eq!(instance.get_parameter(BASE_COLOR_PARAMETER), Some(MaterialValue::Vec4([1.0, 0.0, 0.0, 1.0])));
+        assert_eq!(instance.get_parameter("Roughness"), Some(MaterialValue::Float(0.5)));
+        assert!(instance.get_texture(BASE_COLOR_PARAMETER).unwrap().ends_with("textures/checker.png"));
+        assert_eq!(instance.get_uniform_data(), vec![[1.0, 0.0, 0.0, 1.0], [0.5, 0.0, 0.0, 0.0]]);
+    }
+
+    #[test]
+    fn overrides_must_match_the_parent() {
+        let mut instance = MaterialInstance::new("Test", Arc::new(Material::fallback()));
+        assert!(!instance.set_parameter(BASE_COLOR_PARAMETER, MaterialValue::Vec3([1.0, 0.0, 0.0])));
+        assert!(!instance.set_parameter("Roughness", MaterialValue::Float(1.0)));
+        assert!(!instance.set_texture("Normal", None));
+        assert!(instance.set_parameter(BASE_COLOR_PARAMETER, MaterialValue::Vec4([0.0, 1.0, 0.0, 1.0])));
+        assert!(matches!(Material::load(Path::new("assets/materials/checker_red.material")), Err(MaterialError::Parse { line: 4, .. })));
+    }
+
+    #[test]
+    fn values_have_one_to_four_components() {
+        assert_eq!(MaterialValue::parse("0.5"), Some(MaterialValue::Float(0.5)));
+        assert_eq!(MaterialValue::parse(" 1 2  3 "), Some(MaterialValue::Vec3([1.0, 2.0, 3.0])));
+        assert_eq!(MaterialValue::parse(""), None);
+        assert_eq!(MaterialValue::parse("1 2 3 4 5"), None);
+        assert_eq!(MaterialValue::parse("1 red"), None);
+    }
+}
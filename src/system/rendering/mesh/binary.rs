@@ -0,0 +1,453 @@
+use std::fmt;
+use std::fs::{self, File};
+use std::io;
+use std::mem;
+use std::path::{Path, PathBuf};
+use bytemuck::{Pod, Zeroable};
+use memmap2::Mmap;
+use crate::system::rendering::mesh::bounds::Aabb;
+use crate::system::rendering::mesh::mesh::Mesh;
+use crate::system::rendering::mesh::vertex::{AttributeFormat, VertexAttribute, VertexFormat};
+
+pub const BINARY_MESH_EXTENSION: &str = "resamesh";
+pub const BINARY_MESH_VERSION: u32 = 1;
+const BINARY_MESH_MAGIC: [u8; 8] = *b"RESAMESH";
+/// Alignment of the vertex and index data inside the file, enough for every vertex attribute.
+const DATA_ALIGNMENT: usize = 16;
+
+/// Everything that can go wrong while reading a `.resamesh` file.
+#[derive(Debug)]
+pub enum BinaryMeshError {
+    Io { path: PathBuf, error: io::Error },
+    NoMeshFile,
+    Version { version: u32 },
+    Checksum { expected: u32, actual: u32 },
+    Corrupt { message: String },
+    /// The vertices in the file have another layout than the requested vertex type.
+    Layout,
+}
+
+impl fmt::Display for BinaryMeshError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BinaryMeshError::Io { path, error } => write!(f, "{}: {}", path.display(), error),
+            BinaryMeshError::NoMeshFile => write!(f, "the file is no resamesh file"),
+            BinaryMeshError::Version { version } => write!(f, "version {} is not supported, only version {}", version, BINARY_MESH_VERSION),
+            BinaryMeshError::Checksum { expected, actual } => write!(f, "checksum {:08x} does not match the content {:08x}", expected, actual),
+            BinaryMeshError::Corrupt { message } => write!(f, "the file is corrupt: {}", message),
+            BinaryMeshError::Layout => write!(f, "the vertex layout does not match the requested vertex type"),
+        }
+    }
+}
+
+/// Start of every file. All numbers are stored in native byte order, the vertex and index data exactly as the gpu reads it,
+/// so files can only be exchanged between machines of the same endianness.
+/// The checksum is the CRC-32 of the whole file, computed with the checksum itself set to zero.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct FileHeader {
+    magic: [u8; 8],
+    version: u32,
+    checksum: u32,
+    vertex_stride: u32,
+    attribute_count: u32,
+    submesh_count: u32,
+    vertex_count: u32,
+    index_count: u32,
+    /// Size of the material names, which follow the submeshes.
+    names_size: u32,
+    bounds_min: [f32; 3],
+    bounds_max: [f32; 3],
+    vertex_data_offset: u64,
+    index_data_offset: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct AttributeRecord {
+    location: u32,
+    format: u32,
+    offset: u32,
+    reserved: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct SubmeshRecord {
+    first_index: u32,
+    index_count: u32,
+    first_vertex: u32,
+    vertex_count: u32,
+    name_offset: u32,
+    name_size: u32,
+    bounds_min: [f32; 3],
+    bounds_max: [f32; 3],
+}
+
+/// Range of the vertex and index data that was one mesh, drawn with one material.
+/// Indices are relative to the first vertex of the submesh, so it is drawn with `first_vertex` as vertex offset.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Submesh {
+    pub first_index: u32,
+    pub index_count: u32,
+    pub first_vertex: u32,
+    pub vertex_count: u32,
+    /// Name of the material instance of the mesh, empty if it had none. Materials themselves are not stored.
+    pub material_name: String,
+    pub bounds: Aabb,
+}
+
+/// Parsed `.resamesh` file that borrows its vertex and index data, e.g. from a memory mapped file.
+/// The data is aligned and stored as the gpu reads it, so it can be copied into staging buffers unchanged.
+pub struct BinaryMeshView<'a> {
+    pub vertex_stride: u32,
+    pub attributes: Vec<VertexAttribute>,
+    pub submeshes: Vec<Submesh>,
+    /// Box around all submeshes.
+    pub bounds: Aabb,
+    pub vertex_data: &'a [u8],
+    /// Indices as `u32`.
+    pub index_data: &'a [u8],
+}
+
+impl<'a> BinaryMeshView<'a> {
+    /// Checks magic number, version, checksum and all offsets of the file content.
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, BinaryMeshError> {
+        let header_size = mem::size_of::<FileHeader>();
+        if bytes.len() < header_size || bytes[..BINARY_MESH_MAGIC.len()] != BINARY_MESH_MAGIC {
+            return Err(BinaryMeshError::NoMeshFile);
+        }
+        let header: FileHeader = bytemuck::pod_read_unaligned(&bytes[..header_size]);
+        if header.version != BINARY_MESH_VERSION {
+            return Err(BinaryMeshError::Version { version: header.version });
+        }
+        let actual = get_checksum(bytes);
+        if actual != header.checksum {
+            return Err(BinaryMeshError::Checksum { expected: header.checksum, actual });
+        }
+
+        let attributes_end = header_size + header.attribute_count as usize * mem::size_of::<AttributeRecord>();
+        let submeshes_end = attributes_end + header.submesh_count as usize * mem::size_of::<SubmeshRecord>();
+        let names_end = submeshes_end + header.names_size as usize;
+        let vertex_start = header.vertex_data_offset as usize;
+        let vertex_end = (header.vertex_count as usize).checked_mul(header.vertex_stride as usize)
+            .and_then(|vertex_size| vertex_start.checked_add(vertex_size))
+            .ok_or_else(|| corrupt("the vertex data is larger than the address space"))?;
+        let index_start = header.index_data_offset as usize;
+        let index_end = (header.index_count as usize).checked_mul(mem::size_of::<u32>())
+            .and_then(|index_size| index_start.checked_add(index_size))
+            .ok_or_else(|| corrupt("the index data is larger than the address space"))?;
+        if names_end > vertex_start || vertex_end > index_start || index_end != bytes.len()
+            || !vertex_start.is_multiple_of(DATA_ALIGNMENT) || !index_start.is_multiple_of(DATA_ALIGNMENT) {
+            return Err(corrupt("the sections do not fit into the file"));
+        }
+
+        let attributes = bytes[header_size..attributes_end].chunks_exact(mem::size_of::<AttributeRecord>())
+            .map(|record| {
+                let record: AttributeRecord = bytemuck::pod_read_unaligned(record);
+                let format = format_from_u32(record.format).ok_or_else(|| corrupt("unknown attribute format"))?;
+                Ok(VertexAttribute { location: record.location, format, offset: record.offset })
+            })
+            .collect::<Result<Vec<VertexAttribute>, BinaryMeshError>>()?;
+
+        let names = &bytes[submeshes_end..names_end];
+        let submeshes = bytes[attributes_end..submeshes_end].chunks_exact(mem::size_of::<SubmeshRecord>())
+            .map(|record| {
+                let record: SubmeshRecord = bytemuck::pod_read_unaligned(record);
+                let name = names.get(record.name_offset as usize..record.name_offset as usize + record.name_size as usize)
+                    .and_then(|name| std::str::from_utf8(name).ok())
+                    .ok_or_else(|| corrupt("invalid material name"))?;
+                if record.first_index as u64 + record.index_count as u64 > header.index_count as u64
+                    || record.first_vertex as u64 + record.vertex_count as u64 > header.vertex_count as u64 {
+                    return Err(corrupt("a submesh lies outside of the data"));
+                }
+                Ok(Submesh {
+                    first_index: record.first_index,
+                    index_count: record.index_count,
+                    first_vertex: record.first_vertex,
+                    vertex_count: record.vertex_count,
+                    material_name: name.to_string(),
+                    bounds: Aabb { min: record.bounds_min, max: record.bounds_max },
+                })
+            })
+            .collect::<Result<Vec<Submesh>, BinaryMeshError>>()?;
+
+        Ok(BinaryMeshView {
+            vertex_stride: header.vertex_stride,
+            attributes,
+            submeshes,
+            bounds: Aabb { min: header.bounds_min, max: header.bounds_max },
+            vertex_data: &bytes[vertex_start..vertex_end],
+            index_data: &bytes[index_start..index_end],
+        })
+    }
+
+    /// True if the vertices are stored with exactly the layout of the vertex type.
+    pub fn matches_format<V: VertexFormat>(&self) -> bool {
+        let attributes = V::get_attributes();
+        self.vertex_stride == V::get_stride()
+            && self.attributes.len() == attributes.len()
+            && self.attributes.iter().zip(attributes.iter())
+                .all(|(a, b)| a.location == b.location && a.format == b.format && a.offset == b.offset)
+    }
+}
+
+impl<V: VertexFormat + Pod> Mesh<V> {
+    /// Encodes the meshes as one `.resamesh` file, every mesh becomes a submesh.
+    pub fn to_binary(meshes: &[Mesh<V>]) -> Vec<u8> {
+        let attributes = V::get_attributes();
+        let stride = V::get_stride() as usize;
+        let vertex_count: usize = meshes.iter().map(|mesh| mesh.vertices.len()).sum();
+        let index_count: usize = meshes.iter().map(|mesh| mesh.indices.len()).sum();
+
+        let mut names: Vec<u8> = Vec::new();
+        let mut submeshes: Vec<SubmeshRecord> = Vec::with_capacity(meshes.len());
+        let (mut first_index, mut first_vertex) = (0, 0);
+        for mesh in meshes.iter() {
+            let name = mesh.material.as_ref().map(|material| material.name.as_str()).unwrap_or_default();
+            let bounds = get_bounds(bytemuck::cast_slice(&mesh.vertices), stride, &attributes);
+            submeshes.push(SubmeshRecord {
+                first_index,
+                index_count: mesh.indices.len() as u32,
+                first_vertex,
+                vertex_count: mesh.vertices.len() as u32,
+                name_offset: names.len() as u32,
+                name_size: name.len() as u32,
+                bounds_min: bounds.min,
+                bounds_max: bounds.max,
+            });
+            names.extend_from_slice(name.as_bytes());
+            first_index += mesh.indices.len() as u32;
+            first_vertex += mesh.vertices.len() as u32;
+        }
+        let bounds = submeshes.iter()
+            .filter(|submesh| submesh.vertex_count > 0)
+            .map(|submesh| Aabb { min: submesh.bounds_min, max: submesh.bounds_max })
+            .reduce(|a, b| a.union(&b))
+            .unwrap_or(Aabb { min: [0.0; 3], max: [0.0; 3] });
+
+        let names_end = mem::size_of::<FileHeader>() + attributes.len() * mem::size_of::<AttributeRecord>()
+            + submeshes.len() * mem::size_of::<SubmeshRecord>() + names.len();
+        let vertex_data_offset = align(names_end);
+        let index_data_offset = align(vertex_data_offset + vertex_count * stride);
+        let header = FileHeader {
+            magic: BINARY_MESH_MAGIC,
+            version: BINARY_MESH_VERSION,
+            checksum: 0,
+            vertex_stride: stride as u32,
+            attribute_count: attributes.len() as u32,
+            submesh_count: submeshes.len() as u32,
+            vertex_count: vertex_count as u32,
+            index_count: index_count as u32,
+            names_size: names.len() as u32,
+            bounds_min: bounds.min,
+            bounds_max: bounds.max,
+            vertex_data_offset: vertex_data_offset as u64,
+            index_data_offset: index_data_offset as u64,
+        };
+
+        let mut bytes: Vec<u8> = Vec::with_capacity(index_data_offset + index_count * mem::size_of::<u32>());
+        bytes.extend_from_slice(bytemuck::bytes_of(&header));
+        for attribute in attributes.iter() {
+            let record = AttributeRecord { location: attribute.location, format: format_to_u32(attribute.format), offset: attribute.offset, reserved: 0 };
+            bytes.extend_from_slice(bytemuck::bytes_of(&record));
+        }
+        bytes.extend_from_slice(bytemuck::cast_slice(&submeshes));
+        bytes.extend_from_slice(&names);
+        bytes.resize(vertex_data_offset, 0);
+        for mesh in meshes.iter() {
+            bytes.extend_from_slice(bytemuck::cast_slice(&mesh.vertices));
+        }
+        bytes.resize(index_data_offset, 0);
+        for mesh in meshes.iter() {
+            bytes.extend_from_slice(bytemuck::cast_slice(&mesh.indices));
+        }
+
+        let checksum = get_checksum(&bytes);
+        let checksum_offset = mem::size_of::<[u8; 8]>() + mem::size_of::<u32>();
+        bytes[checksum_offset..checksum_offset + mem::size_of::<u32>()].copy_from_slice(&checksum.to_ne_bytes());
+        bytes
+    }
+
+    /// Splits the view into one mesh per submesh. Meshes get no material, only its name is stored.
+    pub fn from_binary(view: &BinaryMeshView) -> Result<Vec<Mesh<V>>, BinaryMeshError> {
+        if !view.matches_format::<V>() {
+            return Err(BinaryMeshError::Layout);
+        }
+
+        // The view may point into a memory map without any alignment, so the data is copied instead of cast.
+        let vertices: Vec<V> = bytemuck::pod_collect_to_vec(view.vertex_data);
+        let indices: Vec<u32> = bytemuck::pod_collect_to_vec(view.index_data);
+        view.submeshes.iter()
+            .map(|submesh| {
+                let first_vertex = submesh.first_vertex as usize;
+                let first_index = submesh.first_index as usize;
+                let submesh_indices = &indices[first_index..first_index + submesh.index_count as usize];
+                if submesh_indices.iter().any(|&index| index >= submesh.vertex_count) {
+                    return Err(corrupt("an index lies outside of its submesh"));
+                }
+                Ok(Mesh {
+                    vertices: vertices[first_vertex..first_vertex + submesh.vertex_count as usize].into(),
+                    indices: submesh_indices.into(),
+                    faces: Box::new([]),
+                    material: None,
+                    lods: None,
+                })
+            })
+            .collect()
+    }
+
+    pub fn write_binary(path: &Path, meshes: &[Mesh<V>]) -> Result<(), BinaryMeshError> {
+        fs::write(path, Mesh::to_binary(meshes)).map_err(|error| BinaryMeshError::Io { path: path.to_path_buf(), error })
+    }
+
+    /// Memory maps the file and reads its meshes.
+    pub fn read_binary(path: &Path) -> Result<Vec<Mesh<V>>, BinaryMeshError> {
+        let file = File::open(path).map_err(|error| BinaryMeshError::Io { path: path.to_path_buf(), error })?;
+        // The map is only read while the file is open here. Changes by other processes in that time are caught by the checksum.
+        let map = unsafe { Mmap::map(&file) }.map_err(|error| BinaryMeshError::Io { path: path.to_path_buf(), error })?;
+        Mesh::from_binary(&BinaryMeshView::parse(&map)?)
+    }
+}
+
+/// CRC-32 of the file with the checksum field counted as zero.
+fn get_checksum(bytes: &[u8]) -> u32 {
+    let checksum_offset = mem::size_of::<[u8; 8]>() + mem::size_of::<u32>();
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(&bytes[..checksum_offset]);
+    hasher.update(&[0; mem::size_of::<u32>()]);
+    hasher.update(&bytes[checksum_offset + mem::size_of::<u32>()..]);
+    hasher.finalize()
+}
+
+/// Box around the positions at location 0. Vertex types without a position have empty bounds.
+fn get_bounds(vertex_data: &[u8], stride: usize, attributes: &[VertexAttribute]) -> Aabb {
+    let empty = Aabb { min: [0.0; 3], max: [0.0; 3] };
+    let offset = match attributes.iter().find(|attribute| attribute.location == 0 && attribute.format == AttributeFormat::Float3) {
+        Some(attribute) => attribute.offset as usize,
+        None => return empty,
+    };
+
+    let mut positions = vertex_data.chunks_exact(stride)
+        .map(|vertex| bytemuck::pod_read_unaligned::<[f32; 3]>(&vertex[offset..offset + mem::size_of::<[f32; 3]>()]));
+    let first = match positions.next() {
+        Some(position) => position,
+        None => return empty,
+    };
+    let mut aabb = Aabb { min: first, max: first };
+    positions.for_each(|position| aabb.add_point(position));
+    aabb
+}
+
+fn align(offset: usize) -> usize {
+    offset.div_ceil(DATA_ALIGNMENT) * DATA_ALIGNMENT
+}
+
+fn corrupt(message: &str) -> BinaryMeshError {
+    BinaryMeshError::Corrupt { message: message.to_string() }
+}
+
+fn format_to_u32(format: AttributeFormat) -> u32 {
+    match format {
+        AttributeFormat::Float => 1,
+        AttributeFormat::Float2 => 2,
+        AttributeFormat::Float3 => 3,
+        AttributeFormat::Float4 => 4,
+        AttributeFormat::UInt4 => 5,
+    }
+}
+
+fn format_from_u32(format: u32) -> Option<AttributeFormat> {
+    match format {
+        1 => Some(AttributeFormat::Float),
+        2 => Some(AttributeFormat::Float2),
+        3 => Some(AttributeFormat::Float3),
+        4 => Some(AttributeFormat::Float4),
+        5 => Some(AttributeFormat::UInt4),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::rendering::mesh::vertex::{SkinnedVertex, Vertex};
+
+    fn get_meshes() -> Vec<Mesh> {
+        vec![Mesh::cube(1.0, 1), Mesh::plane(2.0, 4.0, 2, 2)]
+    }
+
+    #[test]
+    fn meshes_survive_a_round_trip() {
+        let meshes = get_meshes();
+        let bytes = Mesh::to_binary(&meshes);
+        let view = BinaryMeshView::parse(&bytes).unwrap();
+        assert_eq!(view.submeshes.len(), 2);
+        assert_eq!(view.submeshes[1].first_vertex as usize, meshes[0].vertices.len());
+        assert_eq!(view.bounds, Aabb { min: [-1.0, -0.5, -2.0], max: [1.0, 0.5, 2.0] });
+
+        let loaded: Vec<Mesh> = Mesh::from_binary(&view).unwrap();
+        for (mesh, loaded) in meshes.iter().zip(loaded.iter()) {
+            assert_eq!(mesh.indices, loaded.indices);
+            assert!(mesh.vertices.iter().zip(loaded.vertices.iter())
+                .all(|(a, b)| a.position == b.position && a.normal == b.normal && a.uv == b.uv));
+        }
+    }
+
+    #[test]
+    fn data_is_aligned_for_staging_buffers() {
+        let bytes = Mesh::to_binary(&get_meshes());
+        let view = BinaryMeshView::parse(&bytes).unwrap();
+        for data in [view.vertex_data, view.index_data] {
+            assert_eq!((data.as_ptr() as usize - bytes.as_ptr() as usize) % DATA_ALIGNMENT, 0);
+        }
+    }
+
+    #[test]
+    fn mismatches_are_rejected() {
+        let mut bytes = Mesh::to_binary(&get_meshes());
+        assert!(matches!(Mesh::<SkinnedVertex>::from_binary(&BinaryMeshView::parse(&bytes).unwrap()), Err(BinaryMeshError::Layout)));
+
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        assert!(matches!(BinaryMeshView::parse(&bytes), Err(BinaryMeshError::Checksum { .. })));
+
+        bytes[8] = 2;
+        assert!(matches!(BinaryMeshView::parse(&bytes), Err(BinaryMeshError::Version { version: 2 })));
+        assert!(matches!(BinaryMeshView::parse(b"RESA"), Err(BinaryMeshError::NoMeshFile)));
+    }
+
+    /// Changes the header and fixes the checksum, so only the changed values can be rejected.
+    fn with_header(bytes: &[u8], change: impl FnOnce(&mut FileHeader)) -> Vec<u8> {
+        let header_size = mem::size_of::<FileHeader>();
+        let mut header: FileHeader = bytemuck::pod_read_unaligned(&bytes[..header_size]);
+        change(&mut header);
+        let mut bytes = [bytemuck::bytes_of(&header), &bytes[header_size..]].concat();
+        header.checksum = get_checksum(&bytes);
+        bytes[..header_size].copy_from_slice(bytemuck::bytes_of(&header));
+        bytes
+    }
+
+    #[test]
+    fn overflowing_sections_are_rejected() {
+        let bytes = Mesh::to_binary(&get_meshes());
+        let corrupt_headers = [
+            with_header(&bytes, |header| header.vertex_data_offset = u64::MAX),
+            with_header(&bytes, |header| header.index_data_offset = u64::MAX - 3),
+            with_header(&bytes, |header| (header.vertex_count, header.vertex_stride) = (u32::MAX, u32::MAX)),
+            with_header(&bytes, |header| header.vertex_count += 1),
+        ];
+        for bytes in corrupt_headers.iter() {
+            assert!(matches!(BinaryMeshView::parse(bytes), Err(BinaryMeshError::Corrupt { .. })));
+        }
+    }
+
+    #[test]
+    fn files_are_memory_mapped() {
+        let path = std::env::temp_dir().join(format!("resa_binary_test_{}.{}", std::process::id(), BINARY_MESH_EXTENSION));
+        Mesh::write_binary(&path, &get_meshes()).unwrap();
+        let loaded = Mesh::<Vertex>::read_binary(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap().len(), 2);
+    }
+}
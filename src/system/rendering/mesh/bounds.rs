@@ -0,0 +1,133 @@
+use crate::system::rendering::mesh::mesh::Mesh;
+use crate::system::rendering::mesh::vertex::Vertex;
+
+/// Axis aligned bounding box.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Aabb {
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+}
+
+impl Aabb {
+    /// Smallest box around all vertices, None if there are none.
+    pub fn from_vertices(vertices: &[Vertex]) -> Option<Self> {
+        let first = vertices.first()?;
+        let mut aabb = Aabb { min: first.position, max: first.position };
+        for vertex in vertices.iter().skip(1) {
+            aabb.add_point(vertex.position);
+        }
+        Some(aabb)
+    }
+
+    pub fn add_point(&mut self, point: [f32; 3]) {
+        for (axis, value) in point.iter().enumerate() {
+            self.min[axis] = self.min[axis].min(*value);
+            self.max[axis] = self.max[axis].max(*value);
+        }
+    }
+
+    /// Smallest box containing both boxes.
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        let mut union = *self;
+        union.add_point(other.min);
+        union.add_point(other.max);
+        union
+    }
+
+    pub fn get_center(&self) -> [f32; 3] {
+        [(self.min[0] + self.max[0]) * 0.5, (self.min[1] + self.max[1]) * 0.5, (self.min[2] + self.max[2]) * 0.5]
+    }
+
+    /// Half the size of the box along every axis.
+    pub fn get_extents(&self) -> [f32; 3] {
+        [(self.max[0] - self.min[0]) * 0.5, (self.max[1] - self.min[1]) * 0.5, (self.max[2] - self.min[2]) * 0.5]
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct BoundingSphere {
+    pub center: [f32; 3],
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    /// Sphere around all vertices, centered in their bounding box. It is not the minimal sphere, but cheap to compute.
+    /// None if there are no vertices.
+    pub fn from_vertices(vertices: &[Vertex]) -> Option<Self> {
+        let center = Aabb::from_vertices(vertices)?.get_center();
+        let radius_squared = vertices.iter()
+            .map(|vertex| distance_squared(vertex.position, center))
+            .fold(0.0, f32::max);
+        Some(BoundingSphere { center, radius: radius_squared.sqrt() })
+    }
+
+    /// Sphere around the bounding box of all meshes. None if they have no vertices.
+    pub fn from_meshes(meshes: &[Mesh]) -> Option<Self> {
+        meshes.iter()
+            .filter_map(|mesh| Aabb::from_vertices(&mesh.vertices))
+            .reduce(|a, b| a.union(&b))
+            .map(|aabb| BoundingSphere::from_aabb(&aabb))
+    }
+
+    /// Sphere around the whole box.
+    pub fn from_aabb(aabb: &Aabb) -> Self {
+        let extents = aabb.get_extents();
+        BoundingSphere {
+            center: aabb.get_center(),
+            radius: (extents[0] * extents[0] + extents[1] * extents[1] + extents[2] * extents[2]).sqrt(),
+        }
+    }
+}
+
+fn distance_squared(a: [f32; 3], b: [f32; 3]) -> f32 {
+    (a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vertex(position: [f32; 3]) -> Vertex {
+        Vertex { position, ..Default::default() }
+    }
+
+    #[test]
+    fn aabb_contains_all_vertices() {
+        let vertices = [vertex([1.0, -2.0, 3.0]), vertex([-1.0, 4.0, 0.0]), vertex([0.0, 0.0, -5.0])];
+        let aabb = Aabb::from_vertices(&vertices).unwrap();
+        assert_eq!(aabb.min, [-1.0, -2.0, -5.0]);
+        assert_eq!(aabb.max, [1.0, 4.0, 3.0]);
+        assert_eq!(aabb.get_center(), [0.0, 1.0, -1.0]);
+        assert_eq!(aabb.get_extents(), [1.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn aabb_of_no_vertices_is_none() {
+        assert!(Aabb::from_vertices(&[]).is_none());
+        assert!(BoundingSphere::from_vertices(&[]).is_none());
+    }
+
+    #[test]
+    fn aabb_union_covers_both() {
+        let a = Aabb { min: [0.0, 0.0, 0.0], max: [1.0, 1.0, 1.0] };
+        let b = Aabb { min: [-1.0, 0.5, 2.0], max: [0.5, 3.0, 4.0] };
+        assert_eq!(a.union(&b), Aabb { min: [-1.0, 0.0, 0.0], max: [1.0, 3.0, 4.0] });
+    }
+
+    #[test]
+    fn sphere_contains_all_vertices() {
+        let vertices = [vertex([1.0, 0.0, 0.0]), vertex([-1.0, 0.0, 0.0]), vertex([0.0, 0.5, 0.0]), vertex([0.3, -0.2, 0.9])];
+        let sphere = BoundingSphere::from_vertices(&vertices).unwrap();
+        for v in vertices.iter() {
+            assert!(distance_squared(v.position, sphere.center).sqrt() <= sphere.radius + 1e-6);
+        }
+    }
+
+    #[test]
+    fn sphere_of_aabb_touches_corners() {
+        let aabb = Aabb { min: [-1.0, -2.0, -2.0], max: [1.0, 2.0, 2.0] };
+        let sphere = BoundingSphere::from_aabb(&aabb);
+        assert_eq!(sphere.center, [0.0, 0.0, 0.0]);
+        assert!((sphere.radius - 3.0).abs() < 1e-6);
+    }
+}
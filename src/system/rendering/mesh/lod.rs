@@ -0,0 +1,113 @@
+use crate::system::math::matrix::Mat4;
+use crate::system::math::vector::Vec3;
+use crate::system::rendering::camera_uniform::CameraUniform;
+use crate::system::rendering::mesh::bounds::BoundingSphere;
+use crate::system::rendering::mesh::mesh::Mesh;
+use crate::system::rendering::mesh::simplify;
+
+/// Most levels of detail generated below the full mesh.
+pub const MAX_LOD_LEVELS: usize = 4;
+/// Meshes with fewer triangles are cheap enough to always draw in full.
+pub const MIN_LOD_TRIANGLES: usize = 256;
+/// Largest error of a generated level, relative to the radius of the mesh.
+const MAX_RELATIVE_LOD_ERROR: f32 = 0.25;
+
+/// Simplified index buffer over the unchanged vertices of a mesh.
+#[derive(Clone)]
+pub struct MeshLod {
+    pub indices: Box<[u32]>,
+    /// Largest distance the simplified surface may be away from the full mesh, in world units.
+    pub error: f32,
+}
+
+/// Levels of detail of a mesh, from the finest to the coarsest.
+#[derive(Clone)]
+pub struct LodChain {
+    /// Bounds of the full mesh, used to estimate how large the mesh is on screen.
+    pub bounds: BoundingSphere,
+    pub levels: Vec<MeshLod>,
+}
+
+impl Mesh {
+    /// Builds the level of detail chain of the mesh, if it has at least `MIN_LOD_TRIANGLES` indexed triangles.
+    /// Every level has about half the triangles of the previous one. Returns the number of levels.
+    pub fn generate_lods(&mut self) -> usize {
+        self.lods = None;
+        if self.indices.len() / 3 < MIN_LOD_TRIANGLES {
+            return 0;
+        }
+        let bounds = match BoundingSphere::from_vertices(&self.vertices) {
+            Some(bounds) => bounds,
+            None => return 0,
+        };
+
+        let levels = simplify::build_lod_levels(&self.vertices, &self.indices, MAX_LOD_LEVELS, bounds.radius * MAX_RELATIVE_LOD_ERROR);
+        let level_count = levels.len();
+        if level_count > 0 {
+            self.lods = Some(LodChain { bounds, levels });
+        }
+        level_count
+    }
+
+    /// Indices of the coarsest level whose error, projected onto a viewport `viewport_height` pixels high,
+    /// stays within `pixel_error` pixels. The full indices if there is no such level or the camera is inside the bounds.
+    /// The transform places the mesh in world space, its scale scales the error as well.
+    pub fn select_lod(&self, camera: &CameraUniform, transform: &Mat4, viewport_height: u32, pixel_error: f32) -> &[u32] {
+        let lods = match &self.lods {
+            Some(lods) => lods,
+            None => return &self.indices,
+        };
+
+        let scale = transform.get_max_scale();
+        let center = transform.transform_point(Vec3::from(lods.bounds.center));
+        let distance = camera.get_distance(center.into()) - lods.bounds.radius * scale;
+        if distance <= 0.0 {
+            return &self.indices;
+        }
+        let pixels_per_unit = camera.get_pixels_per_unit(viewport_height) / distance;
+        lods.levels.iter().rev()
+            .find(|level| level.error * scale * pixels_per_unit <= pixel_error)
+            .map(|level| &*level.indices)
+            .unwrap_or(&self.indices)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flat_grids_collapse_without_error() {
+        let mut plane = Mesh::plane(2.0, 2.0, 32, 32);
+        let triangle_count = plane.indices.len() / 3;
+        assert!(plane.generate_lods() > 0);
+
+        let levels = &plane.lods.as_ref().unwrap().levels;
+        assert!(levels[0].indices.len() / 3 <= triangle_count / 2);
+        assert!(levels.windows(2).all(|pair| pair[1].indices.len() < pair[0].indices.len()));
+        assert!(levels.iter().all(|level| level.error < 1e-4));
+        assert!(levels.iter().flat_map(|level| level.indices.iter()).all(|&index| (index as usize) < plane.vertices.len()));
+    }
+
+    #[test]
+    fn far_meshes_use_coarser_levels() {
+        let mut sphere = Mesh::uv_sphere(1.0, 48, 32);
+        assert!(sphere.generate_lods() > 0);
+
+        let near = CameraUniform::look_at([0.0, 0.0, 3.0], [0.0; 3], [0.0, 1.0, 0.0], 1.0, 1.0, 0.1, 100.0);
+        let far = CameraUniform::look_at([0.0, 0.0, 300.0], [0.0; 3], [0.0, 1.0, 0.0], 1.0, 1.0, 0.1, 1000.0);
+        let inside = CameraUniform::look_at([0.0; 3], [0.0, 0.0, -1.0], [0.0, 1.0, 0.0], 1.0, 1.0, 0.1, 100.0);
+        assert_eq!(sphere.select_lod(&near, &Mat4::IDENTITY, 1080, 1.0).len(), sphere.indices.len());
+        assert!(sphere.select_lod(&far, &Mat4::IDENTITY, 1080, 1.0).len() < sphere.indices.len());
+        assert_eq!(sphere.select_lod(&inside, &Mat4::IDENTITY, 1080, 1.0).len(), sphere.indices.len());
+        // Scaled up a hundred times the far sphere is as close as the near one.
+        assert_eq!(sphere.select_lod(&far, &Mat4::from_scale(Vec3::splat(100.0)), 1080, 1.0).len(), sphere.indices.len());
+    }
+
+    #[test]
+    fn small_meshes_have_no_levels() {
+        let mut cube = Mesh::cube(1.0, 1);
+        assert_eq!(cube.generate_lods(), 0);
+        assert!(cube.lods.is_none());
+    }
+}
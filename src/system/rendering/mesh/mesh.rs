@@ -1,9 +1,16 @@
-use crate::system::rendering::mesh::vertex::Vertex;
+use std::sync::Arc;
+use crate::system::rendering::material::MaterialInstance;
+use crate::system::rendering::mesh::lod::LodChain;
+use crate::system::rendering::mesh::vertex::{Vertex, VertexFormat};
 
-pub struct Mesh{
-    pub vertices: Box<[Vertex]>,
+/// Indexed triangle list. The vertex type defaults to the position, normal and uv vertex.
+/// Meshes without a material are drawn with `Material::fallback`.
+#[derive(Clone)]
+pub struct Mesh<V: VertexFormat = Vertex>{
+    pub vertices: Box<[V]>,
     pub indices: Box<[u32]>,
     pub faces: Box<[u16]>,
-    // material:
+    pub material: Option<Arc<MaterialInstance>>,
+    /// Simplified versions of the indices, built by `generate_lods`.
+    pub lods: Option<LodChain>,
 }
-
@@ -1,2 +1,8 @@
 pub mod mesh;
-pub mod vertex;
\ No newline at end of file
+pub mod vertex;
+pub mod bounds;
+pub mod processing;
+pub mod primitives;
+pub mod binary;
+pub mod simplify;
+pub mod lod;
@@ -0,0 +1,371 @@
+use std::collections::HashMap;
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+use crate::system::math::vector::Vec3;
+use crate::system::rendering::mesh::mesh::Mesh;
+use crate::system::rendering::mesh::processing::weld_vertices;
+use crate::system::rendering::mesh::vertex::Vertex;
+
+/// Procedural meshes, centered at the origin with y up. Triangles are wound counter clockwise seen from the outside,
+/// normals point outwards and uvs have their origin in the top left corner of the texture.
+impl Mesh {
+    /// Cube with the given edge length. Every face is split into subdivisions x subdivisions quads and uses the whole texture.
+    pub fn cube(size: f32, subdivisions: u32) -> Mesh {
+        let half = size * 0.5;
+        // Normal, right and down axis of every face, as seen from the outside.
+        let faces = [
+            (Vec3::X, -Vec3::Z, -Vec3::Y),
+            (-Vec3::X, Vec3::Z, -Vec3::Y),
+            (Vec3::Y, Vec3::X, Vec3::Z),
+            (-Vec3::Y, Vec3::X, -Vec3::Z),
+            (Vec3::Z, Vec3::X, -Vec3::Y),
+            (-Vec3::Z, -Vec3::X, -Vec3::Y),
+        ];
+
+        let mut builder = MeshBuilder::default();
+        for (normal, right, down) in faces {
+            builder.add_grid(normal * half, right * size, down * size, normal, subdivisions, subdivisions);
+        }
+        builder.build()
+    }
+
+    /// Plane in the xz plane facing +y, split into a grid of columns x rows quads. Columns run along x, rows along z.
+    pub fn plane(width: f32, depth: f32, columns: u32, rows: u32) -> Mesh {
+        let mut builder = MeshBuilder::default();
+        builder.add_grid(Vec3::ZERO, Vec3::new(width, 0.0, 0.0), Vec3::new(0.0, 0.0, depth), Vec3::Y, columns, rows);
+        builder.build()
+    }
+
+    /// Sphere made of `segments` slices around the y axis and `rings` stacks from pole to pole.
+    /// u runs around the equator, v from the north to the south pole.
+    pub fn uv_sphere(radius: f32, segments: u32, rings: u32) -> Mesh {
+        let rings = rings.max(2);
+        let profile: Vec<ProfilePoint> = (0..=rings)
+            .map(|ring| {
+                let polar = PI * ring as f32 / rings as f32;
+                get_sphere_point(radius, 0.0, polar, ring as f32 / rings as f32)
+            })
+            .collect();
+
+        let mut builder = MeshBuilder::default();
+        builder.add_lathe(&profile, segments);
+        builder.build()
+    }
+
+    /// Sphere made of evenly sized triangles, created by splitting the faces of an icosahedron `subdivisions` times.
+    /// The uvs use the same mapping as the uv sphere. Triangles crossing the seam get u values above one,
+    /// so textures on it need a repeating sampler.
+    pub fn icosphere(radius: f32, subdivisions: u32) -> Mesh {
+        let t = (1.0 + 5.0f32.sqrt()) * 0.5;
+        let mut positions: Vec<Vec3> = [
+            [-1.0, t, 0.0], [1.0, t, 0.0], [-1.0, -t, 0.0], [1.0, -t, 0.0],
+            [0.0, -1.0, t], [0.0, 1.0, t], [0.0, -1.0, -t], [0.0, 1.0, -t],
+            [t, 0.0, -1.0], [t, 0.0, 1.0], [-t, 0.0, -1.0], [-t, 0.0, 1.0],
+        ].iter().map(|&position| Vec3::from(position).normalize()).collect();
+        let mut triangles: Vec<[u32; 3]> = vec![
+            [0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+            [1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+            [3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+            [4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1],
+        ];
+
+        for _ in 0..subdivisions {
+            let mut midpoints: HashMap<(u32, u32), u32> = HashMap::new();
+            let mut midpoint = |a: u32, b: u32, positions: &mut Vec<Vec3>| {
+                *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                    positions.push(((positions[a as usize] + positions[b as usize]) * 0.5).normalize());
+                    positions.len() as u32 - 1
+                })
+            };
+            triangles = triangles.iter()
+                .flat_map(|&[a, b, c]| {
+                    let ab = midpoint(a, b, &mut positions);
+                    let bc = midpoint(b, c, &mut positions);
+                    let ca = midpoint(c, a, &mut positions);
+                    [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+                })
+                .collect();
+        }
+
+        // Every corner gets its own vertex first, so triangles on the seam can use u values above one. Welding merges the rest again.
+        let mut vertices = Vec::with_capacity(triangles.len() * 3);
+        for triangle in triangles.iter() {
+            let directions = triangle.map(|index| positions[index as usize]);
+            let mut uvs = directions.map(get_sphere_uv);
+            let us = uvs.map(|uv| uv[0]);
+            if us.iter().cloned().fold(f32::MIN, f32::max) - us.iter().cloned().fold(f32::MAX, f32::min) > 0.5 {
+                uvs.iter_mut().filter(|uv| uv[0] < 0.5).for_each(|uv| uv[0] += 1.0);
+            }
+            // The u of a pole is undefined, the middle of the opposite edge avoids a twisted texture.
+            for corner in 0..3 {
+                if directions[corner].y.abs() > 0.9999 {
+                    uvs[corner][0] = (uvs[(corner + 1) % 3][0] + uvs[(corner + 2) % 3][0]) * 0.5;
+                }
+            }
+            for corner in 0..3 {
+                vertices.push(Vertex { position: (directions[corner] * radius).into(), normal: directions[corner].into(), uv: uvs[corner] });
+            }
+        }
+
+        let mesh = Mesh {
+            vertices: vertices.into_boxed_slice(),
+            indices: Box::new([]),
+            faces: Box::new([]),
+            material: None,
+            lods: None,
+        };
+        weld_vertices(&mesh, 0.0)
+    }
+
+    /// Capped cylinder around the y axis. The side is split into `height_segments` rings, the caps are flat.
+    pub fn cylinder(radius: f32, height: f32, segments: u32, height_segments: u32) -> Mesh {
+        let half = height * 0.5;
+        let height_segments = height_segments.max(1);
+        let side: Vec<ProfilePoint> = (0..=height_segments)
+            .map(|ring| {
+                let v = ring as f32 / height_segments as f32;
+                ProfilePoint { radius, height: half - height * v, normal: [1.0, 0.0], v }
+            })
+            .collect();
+
+        let mut builder = MeshBuilder::default();
+        builder.add_lathe(&side, segments);
+        builder.add_lathe(&get_cap(radius, half, true), segments);
+        builder.add_lathe(&get_cap(radius, -half, false), segments);
+        builder.build()
+    }
+
+    /// Cone around the y axis with the tip at the top and a flat base.
+    pub fn cone(radius: f32, height: f32, segments: u32) -> Mesh {
+        let half = height * 0.5;
+        let slant = (radius * radius + height * height).sqrt();
+        let normal = [height / slant, radius / slant];
+        let side = [
+            ProfilePoint { radius: 0.0, height: half, normal, v: 0.0 },
+            ProfilePoint { radius, height: -half, normal, v: 1.0 },
+        ];
+
+        let mut builder = MeshBuilder::default();
+        builder.add_lathe(&side, segments);
+        builder.add_lathe(&get_cap(radius, -half, false), segments);
+        builder.build()
+    }
+
+    /// Cylinder with hemispheres instead of caps. `height` is the length of the straight part, so the capsule is
+    /// height + 2 * radius tall. Each hemisphere has `rings` stacks, v runs over the whole length.
+    pub fn capsule(radius: f32, height: f32, segments: u32, rings: u32) -> Mesh {
+        let half = height * 0.5;
+        let rings = rings.max(1);
+        let mut profile: Vec<ProfilePoint> = Vec::new();
+        for (center, start_angle) in [(half, 0.0), (-half, FRAC_PI_2)] {
+            for ring in 0..=rings {
+                let polar = start_angle + FRAC_PI_2 * ring as f32 / rings as f32;
+                profile.push(get_sphere_point(radius, center, polar, 0.0));
+            }
+        }
+
+        // v follows the length of the profile, so the texture is not stretched along the straight part.
+        let total_length = height + PI * radius;
+        let mut length = 0.0;
+        for i in 1..profile.len() {
+            length += ((profile[i].radius - profile[i - 1].radius).powi(2) + (profile[i].height - profile[i - 1].height).powi(2)).sqrt();
+            profile[i].v = length / total_length;
+        }
+
+        let mut builder = MeshBuilder::default();
+        builder.add_lathe(&profile, segments);
+        builder.build()
+    }
+
+    /// Ring around the y axis. `segments` slices go around the ring, `sides` around the tube.
+    pub fn torus(major_radius: f32, minor_radius: f32, segments: u32, sides: u32) -> Mesh {
+        let sides = sides.max(3);
+        // The tube profile starts at the top and runs over the outside first.
+        let profile: Vec<ProfilePoint> = (0..=sides)
+            .map(|side| {
+                let angle = TAU * side as f32 / sides as f32;
+                ProfilePoint {
+                    radius: major_radius + minor_radius * angle.sin(),
+                    height: minor_radius * angle.cos(),
+                    normal: [angle.sin(), angle.cos()],
+                    v: side as f32 / sides as f32,
+                }
+            })
+            .collect();
+
+        let mut builder = MeshBuilder::default();
+        builder.add_lathe(&profile, segments);
+        builder.build()
+    }
+}
+
+/// Point of a profile that is rotated around the y axis. The normal is given as radial and vertical component.
+#[derive(Clone, Copy)]
+struct ProfilePoint {
+    radius: f32,
+    height: f32,
+    normal: [f32; 2],
+    v: f32,
+}
+
+/// Point on a sphere around the given height, `polar` is the angle from the north pole.
+fn get_sphere_point(radius: f32, center: f32, polar: f32, v: f32) -> ProfilePoint {
+    // sin(PI) is not exactly zero, but the south pole has to end up on the axis.
+    let sin = if polar >= PI { 0.0 } else { polar.sin() };
+    ProfilePoint { radius: radius * sin, height: center + radius * polar.cos(), normal: [sin, polar.cos()], v }
+}
+
+/// Flat disc at the given height, facing up or down.
+fn get_cap(radius: f32, height: f32, facing_up: bool) -> [ProfilePoint; 2] {
+    let normal = if facing_up { [0.0, 1.0] } else { [0.0, -1.0] };
+    let center = ProfilePoint { radius: 0.0, height, normal, v: 0.0 };
+    let rim = ProfilePoint { radius, height, normal, v: 1.0 };
+    // Profiles run from the top of the surface to its bottom, as seen from the outside.
+    if facing_up { [center, rim] } else { [rim, center] }
+}
+
+#[derive(Default)]
+struct MeshBuilder {
+    vertices: Vec<Vertex>,
+    indices: Vec<u32>,
+}
+
+impl MeshBuilder {
+    /// Adds a grid of quads centered at `center` and spanned by `right` and `down`, which also give the uv directions.
+    fn add_grid(&mut self, center: Vec3, right: Vec3, down: Vec3, normal: Vec3, columns: u32, rows: u32) {
+        let (columns, rows) = (columns.max(1), rows.max(1));
+        let first = self.vertices.len() as u32;
+        for row in 0..=rows {
+            for column in 0..=columns {
+                let u = column as f32 / columns as f32;
+                let v = row as f32 / rows as f32;
+                let position = center + right * (u - 0.5) + down * (v - 0.5);
+                self.vertices.push(Vertex { position: position.into(), normal: normal.into(), uv: [u, v] });
+            }
+        }
+
+        let stride = columns + 1;
+        for row in 0..rows {
+            for column in 0..columns {
+                let top_left = first + row * stride + column;
+                let bottom_left = top_left + stride;
+                self.indices.extend_from_slice(&[top_left, bottom_left, bottom_left + 1, top_left, bottom_left + 1, top_left + 1]);
+            }
+        }
+    }
+
+    /// Rotates the profile around the y axis. The first and last slice share their position but not their u,
+    /// so the texture wraps without a visible seam. Points on the axis turn the quads next to them into triangles.
+    fn add_lathe(&mut self, profile: &[ProfilePoint], segments: u32) {
+        let segments = segments.max(3);
+        let mut ring_starts = Vec::with_capacity(profile.len());
+        for point in profile.iter() {
+            ring_starts.push(self.vertices.len() as u32);
+            // Points on the axis get one vertex per slice, with their u in its middle.
+            let on_axis = point.radius == 0.0;
+            let (count, offset) = if on_axis { (segments, 0.5) } else { (segments + 1, 0.0) };
+            for segment in 0..count {
+                let u = (segment as f32 + offset) / segments as f32;
+                let (sin, cos) = (TAU * u).sin_cos();
+                self.vertices.push(Vertex {
+                    position: [point.radius * cos, point.height, -point.radius * sin],
+                    normal: [point.normal[0] * cos, point.normal[1], -point.normal[0] * sin],
+                    uv: [u, point.v],
+                });
+            }
+        }
+
+        for (ring, points) in profile.windows(2).enumerate() {
+            for segment in 0..segments {
+                let top_left = ring_starts[ring] + segment;
+                let bottom_left = ring_starts[ring + 1] + segment;
+                if points[0].radius == 0.0 {
+                    self.indices.extend_from_slice(&[top_left, bottom_left, bottom_left + 1]);
+                } else if points[1].radius == 0.0 {
+                    self.indices.extend_from_slice(&[top_left, bottom_left, top_left + 1]);
+                } else {
+                    self.indices.extend_from_slice(&[top_left, bottom_left, bottom_left + 1, top_left, bottom_left + 1, top_left + 1]);
+                }
+            }
+        }
+    }
+
+    fn build(self) -> Mesh {
+        Mesh {
+            vertices: self.vertices.into_boxed_slice(),
+            indices: self.indices.into_boxed_slice(),
+            faces: Box::new([]),
+            material: None,
+            lods: None,
+        }
+    }
+}
+
+/// Same mapping as the uv sphere: u is the angle around the y axis, v the angle from the north pole.
+fn get_sphere_uv(direction: Vec3) -> [f32; 2] {
+    let u = (-direction.z).atan2(direction.x) / TAU;
+    [if u < 0.0 { u + 1.0 } else { u }, direction.y.clamp(-1.0, 1.0).acos() / PI]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_primitives() -> Vec<(&'static str, Mesh)> {
+        vec![
+            ("cube", Mesh::cube(2.0, 3)),
+            ("plane", Mesh::plane(2.0, 1.0, 4, 2)),
+            ("uv sphere", Mesh::uv_sphere(1.0, 16, 8)),
+            ("icosphere", Mesh::icosphere(1.0, 2)),
+            ("cylinder", Mesh::cylinder(0.5, 2.0, 12, 3)),
+            ("cone", Mesh::cone(0.5, 1.0, 12)),
+            ("capsule", Mesh::capsule(0.5, 1.0, 12, 4)),
+            ("torus", Mesh::torus(1.0, 0.25, 16, 8)),
+        ]
+    }
+
+    #[test]
+    fn winding_matches_normals() {
+        for (name, mesh) in get_primitives() {
+            assert!(!mesh.indices.is_empty(), "{} has no triangles", name);
+            for triangle in mesh.indices.chunks(3) {
+                let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(mesh.vertices[triangle[i] as usize].position));
+                let face_normal = (b - a).cross(c - a);
+                assert!(face_normal.length_squared() > 0.0, "{} has a degenerate triangle", name);
+                for index in triangle {
+                    assert!(face_normal.dot(Vec3::from(mesh.vertices[*index as usize].normal)) > 0.0, "{} has a triangle facing inwards", name);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn normals_are_unit_length_and_uvs_in_range() {
+        for (name, mesh) in get_primitives() {
+            for vertex in mesh.vertices.iter() {
+                assert!((Vec3::from(vertex.normal).length_squared() - 1.0).abs() < 1e-4, "{} has a normal that is not normalized", name);
+                // The icosphere wraps its seam triangles past u = 1.
+                let max_u = if name == "icosphere" { 1.5 } else { 1.0 + 1e-6 };
+                assert!((-1e-6..=max_u).contains(&vertex.uv[0]) && (-1e-6..=1.0 + 1e-6).contains(&vertex.uv[1]), "{} has uv {:?}", name, vertex.uv);
+            }
+        }
+    }
+
+    #[test]
+    fn closed_surfaces_point_outwards() {
+        for (name, mesh) in get_primitives().into_iter().filter(|(name, _)| *name != "plane" && *name != "torus") {
+            for vertex in mesh.vertices.iter() {
+                assert!(Vec3::from(vertex.position).dot(Vec3::from(vertex.normal)) > 0.0, "{} has a normal pointing towards its center", name);
+            }
+        }
+    }
+
+    #[test]
+    fn icosphere_shares_vertices() {
+        let mesh = Mesh::icosphere(2.0, 1);
+        assert_eq!(mesh.indices.len(), 80 * 3);
+        assert!(mesh.vertices.len() < 80);
+        for vertex in mesh.vertices.iter() {
+            assert!((Vec3::from(vertex.position).length() - 2.0).abs() < 1e-5);
+        }
+    }
+}
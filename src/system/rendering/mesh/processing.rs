@@ -0,0 +1,437 @@
+use std::collections::HashMap;
+use crate::system::math::vector::Vec3;
+use crate::system::rendering::mesh::mesh::Mesh;
+use crate::system::rendering::mesh::vertex::Vertex;
+
+/// Replaces the normals of the mesh with the area weighted average of the surrounding face normals.
+/// Vertices at the same position share their normal, so uv seams don't show up as creases.
+pub fn compute_smooth_normals(mesh: &mut Mesh) {
+    let triangles = get_triangles(mesh);
+    let mut position_normals: HashMap<[u32; 3], Vec3> = HashMap::new();
+
+    for triangle in triangles.iter() {
+        // The cross product is twice the triangle area long, which weights larger faces stronger.
+        let face_normal = get_face_normal(mesh, triangle);
+        for index in triangle.iter() {
+            *position_normals.entry(position_key(&mesh.vertices[*index as usize])).or_insert(Vec3::ZERO) += face_normal;
+        }
+    }
+
+    for vertex in mesh.vertices.iter_mut() {
+        let normal = position_normals.get(&position_key(vertex)).copied().unwrap_or(Vec3::ZERO);
+        vertex.normal = normal.normalize_or_zero().into();
+    }
+}
+
+/// Returns an unindexed copy of the mesh where every triangle has its own vertices with the face normal.
+pub fn compute_flat_normals(mesh: &Mesh) -> Mesh {
+    let mut vertices = Vec::new();
+    for triangle in get_triangles(mesh).iter() {
+        let normal = get_face_normal(mesh, triangle).normalize_or_zero().into();
+        for index in triangle.iter() {
+            vertices.push(Vertex { normal, ..mesh.vertices[*index as usize] });
+        }
+    }
+
+    Mesh {
+        indices: (0..vertices.len() as u32).collect(),
+        vertices: vertices.into_boxed_slice(),
+        faces: Box::new([]),
+        material: mesh.material.clone(),
+        lods: None,
+    }
+}
+
+/// Computes a tangent per vertex, following the MikkTSpace conventions: the tangent points along +u,
+/// is orthogonal to the normal and its w component is the sign of the bitangent, cross(normal, tangent.xyz) * w.
+/// Unlike the reference implementation, vertices are not split where the tangent frames of the faces disagree.
+pub fn compute_tangents(mesh: &Mesh) -> Vec<[f32; 4]> {
+    let mut tangents = vec![Vec3::ZERO; mesh.vertices.len()];
+    let mut bitangents = vec![Vec3::ZERO; mesh.vertices.len()];
+
+    for triangle in get_triangles(mesh).iter() {
+        let [v0, v1, v2] = triangle.map(|index| &mesh.vertices[index as usize]);
+        let edge1 = Vec3::from(v1.position) - Vec3::from(v0.position);
+        let edge2 = Vec3::from(v2.position) - Vec3::from(v0.position);
+        let (du1, dv1) = (v1.uv[0] - v0.uv[0], v1.uv[1] - v0.uv[1]);
+        let (du2, dv2) = (v2.uv[0] - v0.uv[0], v2.uv[1] - v0.uv[1]);
+
+        let determinant = du1 * dv2 - du2 * dv1;
+        if determinant.abs() <= f32::EPSILON {
+            continue;
+        }
+        let r = 1.0 / determinant;
+        let tangent = (edge1 * dv2 - edge2 * dv1) * r;
+        let bitangent = (edge2 * du1 - edge1 * du2) * r;
+
+        for index in triangle.iter() {
+            tangents[*index as usize] += tangent;
+            bitangents[*index as usize] += bitangent;
+        }
+    }
+
+    mesh.vertices.iter()
+        .zip(tangents.iter().zip(bitangents.iter()))
+        .map(|(vertex, (tangent, bitangent))| {
+            let normal = Vec3::from(vertex.normal);
+            // Gram-Schmidt: remove the part of the tangent that points along the normal.
+            let mut orthogonal = (*tangent - normal * normal.dot(*tangent)).normalize_or_zero();
+            if orthogonal == Vec3::ZERO {
+                orthogonal = get_any_orthogonal(normal);
+            }
+            let handedness = if normal.cross(orthogonal).dot(*bitangent) < 0.0 { -1.0 } else { 1.0 };
+            orthogonal.extend(handedness).to_array()
+        })
+        .collect()
+}
+
+/// Merges vertices whose attributes differ by at most `epsilon` and rebuilds the index buffer.
+/// Unindexed meshes are treated as triangle lists. Only referenced vertices are kept.
+pub fn weld_vertices(mesh: &Mesh, epsilon: f32) -> Mesh {
+    let quantize = |value: f32| if epsilon > 0.0 { (value / epsilon).round().to_bits() } else { value.to_bits() };
+    let mut lookup: HashMap<[u32; 8], u32> = HashMap::new();
+    let mut vertices: Vec<Vertex> = Vec::new();
+    let mut indices: Vec<u32> = Vec::new();
+
+    for triangle in get_triangles(mesh).iter() {
+        for index in triangle.iter() {
+            let vertex = mesh.vertices[*index as usize];
+            let [px, py, pz] = vertex.position;
+            let [nx, ny, nz] = vertex.normal;
+            let [u, v] = vertex.uv;
+            let key = [px, py, pz, nx, ny, nz, u, v].map(quantize);
+            let welded_index = *lookup.entry(key).or_insert_with(|| {
+                vertices.push(vertex);
+                vertices.len() as u32 - 1
+            });
+            indices.push(welded_index);
+        }
+    }
+
+    Mesh {
+        vertices: vertices.into_boxed_slice(),
+        indices: indices.into_boxed_slice(),
+        faces: mesh.faces.clone(),
+        material: mesh.material.clone(),
+        lods: None,
+    }
+}
+
+/// Converts a triangle list into a single strip. Neighbouring triangles are chained greedily,
+/// separate strips are joined with degenerate triangles. Degenerate input triangles are dropped.
+pub fn triangle_list_to_strip(indices: &[u32]) -> Vec<u32> {
+    let triangles: Vec<[u32; 3]> = indices.chunks_exact(3)
+        .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+        .filter(|[a, b, c]| a != b && b != c && a != c)
+        .collect();
+
+    // Every directed edge of a triangle, in winding order, points to the triangle and its remaining corner.
+    let mut edges: HashMap<(u32, u32), Vec<(usize, u32)>> = HashMap::new();
+    for (i, [a, b, c]) in triangles.iter().copied().enumerate() {
+        edges.entry((a, b)).or_default().push((i, c));
+        edges.entry((b, c)).or_default().push((i, a));
+        edges.entry((c, a)).or_default().push((i, b));
+    }
+
+    let mut used = vec![false; triangles.len()];
+    let mut strip: Vec<u32> = Vec::new();
+    for start in 0..triangles.len() {
+        if used[start] {
+            continue;
+        }
+        used[start] = true;
+
+        // The strip continues over the edge between the second and third corner, so start with the rotation that has a free neighbour there.
+        let [a, b, c] = triangles[start];
+        let first_triangle = [[a, b, c], [b, c, a], [c, a, b]].into_iter()
+            .find(|[_, b, c]| edges.get(&(*c, *b)).is_some_and(|candidates| candidates.iter().any(|(triangle, _)| !used[*triangle])))
+            .unwrap_or([a, b, c]);
+
+        if let Some(&last) = strip.last() {
+            let first = first_triangle[0];
+            strip.extend_from_slice(&[last, first]);
+            // A strip flips the winding of every odd triangle, so the new strip has to start at an even position.
+            if strip.len() % 2 == 1 {
+                strip.push(first);
+            }
+        }
+        strip.extend_from_slice(&first_triangle);
+
+        loop {
+            let n = strip.len();
+            let (previous, current) = (strip[n - 2], strip[n - 1]);
+            // The next triangle starts at n - 2. Odd triangles are wound the other way around.
+            let edge = if (n - 2).is_multiple_of(2) { (previous, current) } else { (current, previous) };
+            let next = edges.get(&edge)
+                .and_then(|candidates| candidates.iter().find(|(triangle, _)| !used[*triangle]).copied());
+            match next {
+                Some((triangle, corner)) => {
+                    used[triangle] = true;
+                    strip.push(corner);
+                }
+                None => break,
+            }
+        }
+    }
+    strip
+}
+
+/// Converts a strip into a triangle list with the winding of the first triangle. Degenerate triangles are skipped.
+pub fn triangle_strip_to_list(strip: &[u32]) -> Vec<u32> {
+    let mut list = Vec::new();
+    for i in 0..strip.len().saturating_sub(2) {
+        let triangle = if i % 2 == 0 { [strip[i], strip[i + 1], strip[i + 2]] } else { [strip[i + 1], strip[i], strip[i + 2]] };
+        if triangle[0] != triangle[1] && triangle[1] != triangle[2] && triangle[0] != triangle[2] {
+            list.extend_from_slice(&triangle);
+        }
+    }
+    list
+}
+
+/// Converts a fan around its first index into a triangle list.
+pub fn triangle_fan_to_list(fan: &[u32]) -> Vec<u32> {
+    let mut list = Vec::new();
+    for i in 1..fan.len().saturating_sub(1) {
+        list.extend_from_slice(&[fan[0], fan[i], fan[i + 1]]);
+    }
+    list
+}
+
+/// The triangles of the mesh, for unindexed meshes every three vertices form one. Out of range triangles are skipped.
+fn get_triangles(mesh: &Mesh) -> Vec<[u32; 3]> {
+    let vertex_count = mesh.vertices.len() as u32;
+    if mesh.indices.is_empty() {
+        return (0..vertex_count / 3).map(|i| [i * 3, i * 3 + 1, i * 3 + 2]).collect();
+    }
+    mesh.indices.chunks_exact(3)
+        .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+        .filter(|triangle| triangle.iter().all(|index| *index < vertex_count))
+        .collect()
+}
+
+/// Not normalized, the length is twice the triangle area.
+fn get_face_normal(mesh: &Mesh, triangle: &[u32; 3]) -> Vec3 {
+    let [p0, p1, p2] = triangle.map(|index| Vec3::from(mesh.vertices[index as usize].position));
+    (p1 - p0).cross(p2 - p0)
+}
+
+fn position_key(vertex: &Vertex) -> [u32; 3] {
+    // Adding zero turns -0.0 into 0.0, so both end up with the same key.
+    vertex.position.map(|component| (component + 0.0).to_bits())
+}
+
+fn get_any_orthogonal(normal: Vec3) -> Vec3 {
+    let axis = if normal.x.abs() < 0.9 { Vec3::X } else { Vec3::Y };
+    normal.cross(axis).normalize_or_zero()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 1e-5;
+
+    fn vertex(position: [f32; 3], uv: [f32; 2]) -> Vertex {
+        Vertex { position, uv, ..Default::default() }
+    }
+
+    /// Unit quad in the xy plane, facing +z, with the uv origin in the top left corner.
+    fn quad() -> Mesh {
+        Mesh {
+            vertices: Box::new([
+                vertex([0.0, 0.0, 0.0], [0.0, 1.0]),
+                vertex([1.0, 0.0, 0.0], [1.0, 1.0]),
+                vertex([1.0, 1.0, 0.0], [1.0, 0.0]),
+                vertex([0.0, 1.0, 0.0], [0.0, 0.0]),
+            ]),
+            indices: Box::new([0, 1, 2, 0, 2, 3]),
+            faces: Box::new([]),
+            material: None,
+            lods: None,
+        }
+    }
+
+    /// Triangles rotated so the smallest index comes first, which keeps the winding but ignores the start corner.
+    fn canonical_triangles(list: &[u32]) -> Vec<[u32; 3]> {
+        let mut triangles: Vec<[u32; 3]> = list.chunks_exact(3)
+            .map(|t| {
+                let mut triangle = [t[0], t[1], t[2]];
+                while triangle[0] != *t.iter().min().unwrap() {
+                    triangle.rotate_left(1);
+                }
+                triangle
+            })
+            .collect();
+        triangles.sort();
+        triangles
+    }
+
+    fn assert_near(actual: &[f32], expected: &[f32]) {
+        for (a, e) in actual.iter().zip(expected.iter()) {
+            assert!((a - e).abs() < EPSILON, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn smooth_normals_of_a_plane_point_up() {
+        let mut mesh = quad();
+        compute_smooth_normals(&mut mesh);
+        for vertex in mesh.vertices.iter() {
+            assert_near(&vertex.normal, &[0.0, 0.0, 1.0]);
+        }
+    }
+
+    #[test]
+    fn smooth_normals_average_across_an_edge() {
+        // One triangle facing +z and one facing +y, sharing the edge along x.
+        let mut mesh = Mesh {
+            vertices: Box::new([
+                vertex([0.0, 0.0, 0.0], [0.0, 0.0]),
+                vertex([1.0, 0.0, 0.0], [0.0, 0.0]),
+                vertex([0.0, 1.0, 0.0], [0.0, 0.0]),
+                vertex([0.0, 0.0, -1.0], [0.0, 0.0]),
+            ]),
+            indices: Box::new([0, 1, 2, 0, 1, 3]),
+            faces: Box::new([]),
+            material: None,
+            lods: None,
+        };
+        compute_smooth_normals(&mut mesh);
+        let diagonal = 0.5f32.sqrt();
+        assert_near(&mesh.vertices[0].normal, &[0.0, diagonal, diagonal]);
+        assert_near(&mesh.vertices[1].normal, &[0.0, diagonal, diagonal]);
+        assert_near(&mesh.vertices[2].normal, &[0.0, 0.0, 1.0]);
+        assert_near(&mesh.vertices[3].normal, &[0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn smooth_normals_are_shared_across_uv_seams() {
+        let mut mesh = Mesh {
+            vertices: Box::new([
+                vertex([0.0, 0.0, 0.0], [0.0, 0.0]),
+                vertex([1.0, 0.0, 0.0], [0.0, 0.0]),
+                vertex([0.0, 1.0, 0.0], [0.0, 0.0]),
+                vertex([1.0, 0.0, 0.0], [0.5, 0.5]),
+                vertex([0.0, 0.0, 0.0], [0.5, 0.5]),
+                vertex([0.0, 0.0, -1.0], [0.5, 0.5]),
+            ]),
+            indices: Box::new([0, 1, 2, 3, 4, 5]),
+            faces: Box::new([]),
+            material: None,
+            lods: None,
+        };
+        compute_smooth_normals(&mut mesh);
+        assert_near(&mesh.vertices[0].normal, &mesh.vertices[4].normal);
+        assert_near(&mesh.vertices[1].normal, &mesh.vertices[3].normal);
+    }
+
+    #[test]
+    fn flat_normals_split_every_triangle() {
+        let mesh = compute_flat_normals(&quad());
+        assert_eq!(mesh.vertices.len(), 6);
+        assert_eq!(&mesh.indices[..], &[0, 1, 2, 3, 4, 5]);
+        for vertex in mesh.vertices.iter() {
+            assert_near(&vertex.normal, &[0.0, 0.0, 1.0]);
+        }
+        assert_eq!(mesh.vertices[4].uv, [1.0, 0.0]);
+    }
+
+    #[test]
+    fn tangents_follow_u_and_store_the_handedness() {
+        let mut mesh = quad();
+        compute_smooth_normals(&mut mesh);
+        for tangent in compute_tangents(&mesh).iter() {
+            // v grows downwards while y grows upwards, so the bitangent points along -y = -cross(normal, tangent).
+            assert_near(tangent, &[1.0, 0.0, 0.0, -1.0]);
+        }
+
+        for vertex in mesh.vertices.iter_mut() {
+            vertex.uv[0] = 1.0 - vertex.uv[0];
+        }
+        for tangent in compute_tangents(&mesh).iter() {
+            assert_near(tangent, &[-1.0, 0.0, 0.0, 1.0]);
+        }
+    }
+
+    #[test]
+    fn tangents_without_uvs_are_still_orthogonal() {
+        let mut mesh = quad();
+        compute_smooth_normals(&mut mesh);
+        mesh.vertices.iter_mut().for_each(|vertex| vertex.uv = [0.0, 0.0]);
+        for (tangent, vertex) in compute_tangents(&mesh).iter().zip(mesh.vertices.iter()) {
+            let xyz = Vec3::new(tangent[0], tangent[1], tangent[2]);
+            assert!(xyz.dot(Vec3::from(vertex.normal)).abs() < EPSILON);
+            assert!((xyz.length() - 1.0).abs() < EPSILON);
+        }
+    }
+
+    #[test]
+    fn welding_restores_shared_vertices() {
+        let mesh = weld_vertices(&compute_flat_normals(&quad()), 0.0);
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(canonical_triangles(&mesh.indices), canonical_triangles(&[0, 1, 2, 0, 2, 3]));
+    }
+
+    #[test]
+    fn welding_merges_within_epsilon_only() {
+        let mesh = Mesh {
+            vertices: Box::new([
+                vertex([0.0, 0.0, 0.0], [0.0, 0.0]),
+                vertex([1.0, 0.0, 0.0], [0.0, 0.0]),
+                vertex([0.0, 1.0, 0.0], [0.0, 0.0]),
+                vertex([0.0, 0.000_01, 0.0], [0.0, 0.0]),
+                vertex([1.0, 0.0, 0.0], [0.0, 0.0]),
+                vertex([1.0, 1.0, 0.0], [0.0, 0.0]),
+            ]),
+            indices: Box::new([]),
+            faces: Box::new([]),
+            material: None,
+            lods: None,
+        };
+        assert_eq!(weld_vertices(&mesh, 0.001).vertices.len(), 4);
+        assert_eq!(weld_vertices(&mesh, 0.0).vertices.len(), 5);
+    }
+
+    #[test]
+    fn strip_of_a_quad_has_four_indices() {
+        let strip = triangle_list_to_strip(&[0, 1, 2, 0, 2, 3]);
+        assert_eq!(strip.len(), 4);
+        assert_eq!(canonical_triangles(&triangle_strip_to_list(&strip)), canonical_triangles(&[0, 1, 2, 0, 2, 3]));
+    }
+
+    #[test]
+    fn strips_keep_every_triangle_and_its_winding() {
+        // 4x4 grid of quads, plus a separate triangle and a degenerate one that is dropped.
+        let mut list = Vec::new();
+        for y in 0..4u32 {
+            for x in 0..4u32 {
+                let i = y * 5 + x;
+                list.extend_from_slice(&[i, i + 1, i + 6, i, i + 6, i + 5]);
+            }
+        }
+        list.extend_from_slice(&[100, 101, 102, 7, 7, 8]);
+
+        let strip = triangle_list_to_strip(&list);
+        assert!(strip.len() < list.len());
+        let expected = canonical_triangles(&list[..list.len() - 3]);
+        assert_eq!(canonical_triangles(&triangle_strip_to_list(&strip)), expected);
+    }
+
+    #[test]
+    fn strip_to_list_flips_odd_triangles() {
+        assert_eq!(triangle_strip_to_list(&[0, 1, 2, 3, 4]), vec![0, 1, 2, 2, 1, 3, 2, 3, 4]);
+        assert_eq!(triangle_strip_to_list(&[0, 1]), Vec::<u32>::new());
+    }
+
+    #[test]
+    fn fan_to_list_shares_the_first_index() {
+        assert_eq!(triangle_fan_to_list(&[0, 1, 2, 3, 4]), vec![0, 1, 2, 0, 2, 3, 0, 3, 4]);
+    }
+
+    #[test]
+    fn out_of_range_triangles_are_ignored() {
+        let mut mesh = quad();
+        mesh.indices = Box::new([0, 1, 2, 0, 2, 9]);
+        assert_eq!(compute_flat_normals(&mesh).vertices.len(), 3);
+    }
+}
@@ -0,0 +1,337 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use crate::system::math::vector::Vec3;
+use crate::system::rendering::mesh::lod::MeshLod;
+use crate::system::rendering::mesh::mesh::Mesh;
+use crate::system::rendering::mesh::vertex::Vertex;
+
+/// Symmetric 4x4 matrix that sums the squared distances of a point to a set of planes, stored as its upper triangle.
+/// The sums of many planes lose too much precision in f32, so it is kept in f64.
+#[derive(Clone, Copy, Default)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    fn from_plane(normal: Vec3, distance: f32) -> Self {
+        let [a, b, c] = normal.to_array().map(f64::from);
+        let d = distance as f64;
+        Quadric([a * a, a * b, a * c, a * d, b * b, b * c, b * d, c * c, c * d, d * d])
+    }
+
+    fn add(&mut self, other: &Quadric) {
+        for (value, other_value) in self.0.iter_mut().zip(other.0.iter()) {
+            *value += other_value;
+        }
+    }
+
+    fn evaluate(&self, point: Vec3) -> f64 {
+        let q = &self.0;
+        let [x, y, z] = point.to_array().map(f64::from);
+        q[0] * x * x + q[4] * y * y + q[7] * z * z + q[9]
+            + 2.0 * (q[1] * x * y + q[2] * x * z + q[5] * y * z + q[3] * x + q[6] * y + q[8] * z)
+    }
+}
+
+/// Candidate collapse of the vertex `from` into the vertex `to`, ordered so the binary heap pops the cheapest first.
+struct Collapse {
+    cost: f64,
+    from: u32,
+    to: u32,
+    /// Versions of both vertices when the cost was computed. The candidate is stale once either changed.
+    from_version: u32,
+    to_version: u32,
+}
+
+impl PartialEq for Collapse {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Collapse {}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Collapse {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Ties are broken by the vertices, so the result does not depend on the order the candidates were found in.
+        other.cost.total_cmp(&self.cost)
+            .then_with(|| other.from.cmp(&self.from))
+            .then_with(|| other.to.cmp(&self.to))
+    }
+}
+
+/// Edge collapse simplifier with quadric error metrics. A vertex is always collapsed into one of its neighbours,
+/// so the result only needs new indices and keeps the uvs and normals of the original vertices.
+/// Vertices on open borders, on uv or normal seams and on non manifold edges are never moved, so the mesh does not crack.
+struct Simplifier {
+    positions: Vec<Vec3>,
+    triangles: Vec<[u32; 3]>,
+    removed_triangles: Vec<bool>,
+    /// Triangles around every vertex. Removed triangles are only filtered out lazily.
+    vertex_triangles: Vec<Vec<usize>>,
+    quadrics: Vec<Quadric>,
+    locked: Vec<bool>,
+    removed: Vec<bool>,
+    versions: Vec<u32>,
+    heap: BinaryHeap<Collapse>,
+    triangle_count: usize,
+    /// Largest squared error of all collapses so far.
+    error: f64,
+}
+
+impl Simplifier {
+    fn new(vertices: &[Vertex], indices: &[u32]) -> Self {
+        let positions: Vec<Vec3> = vertices.iter().map(|vertex| Vec3::from(vertex.position)).collect();
+        let triangles: Vec<[u32; 3]> = indices.chunks_exact(3)
+            .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+            .filter(|triangle| triangle.iter().all(|&index| (index as usize) < vertices.len()))
+            .filter(|triangle| triangle[0] != triangle[1] && triangle[1] != triangle[2] && triangle[0] != triangle[2])
+            .collect();
+
+        let mut vertex_triangles = vec![Vec::new(); vertices.len()];
+        let mut quadrics = vec![Quadric::default(); vertices.len()];
+        let mut edge_use: HashMap<(u32, u32), u32> = HashMap::new();
+        for (t, triangle) in triangles.iter().enumerate() {
+            let [a, b, c] = triangle.map(|index| positions[index as usize]);
+            let normal = (b - a).cross(c - a).normalize_or_zero();
+            let plane = Quadric::from_plane(normal, -normal.dot(a));
+            for (corner, &index) in triangle.iter().enumerate() {
+                vertex_triangles[index as usize].push(t);
+                quadrics[index as usize].add(&plane);
+                let next = triangle[(corner + 1) % 3];
+                *edge_use.entry((index.min(next), index.max(next))).or_insert(0) += 1;
+            }
+        }
+
+        let mut locked = vec![false; vertices.len()];
+        for (&(a, b), &count) in edge_use.iter() {
+            if count != 2 {
+                locked[a as usize] = true;
+                locked[b as usize] = true;
+            }
+        }
+
+        let triangle_count = triangles.len();
+        let mut simplifier = Simplifier {
+            positions,
+            removed_triangles: vec![false; triangle_count],
+            triangles,
+            vertex_triangles,
+            quadrics,
+            locked,
+            removed: vec![false; vertices.len()],
+            versions: vec![0; vertices.len()],
+            heap: BinaryHeap::new(),
+            triangle_count,
+            error: 0.0,
+        };
+        for &(a, b) in edge_use.keys() {
+            simplifier.push_collapse(a, b);
+            simplifier.push_collapse(b, a);
+        }
+        simplifier
+    }
+
+    /// Collapses edges until at most `target_triangle_count` triangles are left or the next collapse would move
+    /// the surface further than `max_error`.
+    fn run(&mut self, target_triangle_count: usize, max_error: f32) {
+        let max_cost = (max_error as f64) * (max_error as f64);
+        while self.triangle_count > target_triangle_count {
+            let collapse = match self.heap.pop() {
+                Some(collapse) => collapse,
+                None => return,
+            };
+            let (from, to) = (collapse.from as usize, collapse.to as usize);
+            if self.removed[from] || self.removed[to]
+                || collapse.from_version != self.versions[from] || collapse.to_version != self.versions[to] {
+                continue;
+            }
+            if collapse.cost > max_cost {
+                // Candidates are popped cheapest first, so every other collapse is too expensive as well.
+                self.heap.push(collapse);
+                return;
+            }
+            if self.is_valid(from, to) {
+                self.collapse(from, to, collapse.cost);
+            }
+        }
+    }
+
+    /// Geometric error of the simplified surface, in the units of the vertex positions.
+    fn get_error(&self) -> f32 {
+        self.error.sqrt() as f32
+    }
+
+    fn get_indices(&self) -> Box<[u32]> {
+        self.triangles.iter().zip(self.removed_triangles.iter())
+            .filter(|(_, removed)| !**removed)
+            .flat_map(|(triangle, _)| triangle.iter().copied())
+            .collect()
+    }
+
+    fn push_collapse(&mut self, from: u32, to: u32) {
+        if self.locked[from as usize] {
+            return;
+        }
+        let mut quadric = self.quadrics[from as usize];
+        quadric.add(&self.quadrics[to as usize]);
+        self.heap.push(Collapse {
+            cost: quadric.evaluate(self.positions[to as usize]).max(0.0),
+            from,
+            to,
+            from_version: self.versions[from as usize],
+            to_version: self.versions[to as usize],
+        });
+    }
+
+    fn get_live_triangles(&self, vertex: usize) -> impl Iterator<Item = usize> + '_ {
+        self.vertex_triangles[vertex].iter().copied().filter(|&t| !self.removed_triangles[t])
+    }
+
+    fn get_neighbours(&self, vertex: usize) -> Vec<u32> {
+        let mut neighbours: Vec<u32> = self.get_live_triangles(vertex)
+            .flat_map(|t| self.triangles[t])
+            .filter(|&index| index as usize != vertex)
+            .collect();
+        neighbours.sort_unstable();
+        neighbours.dedup();
+        neighbours
+    }
+
+    /// A collapse is valid if it keeps the mesh manifold and does not flip or degenerate any remaining triangle.
+    fn is_valid(&self, from: usize, to: usize) -> bool {
+        // Link condition: the only neighbours both vertices share are the opposite corners of the triangles on the edge.
+        let edge_triangle_count = self.get_live_triangles(from)
+            .filter(|&t| self.triangles[t].contains(&(to as u32)))
+            .count();
+        let to_neighbours = self.get_neighbours(to);
+        let shared_neighbours = self.get_neighbours(from).iter()
+            .filter(|neighbour| to_neighbours.binary_search(neighbour).is_ok())
+            .count();
+        if edge_triangle_count == 0 || shared_neighbours != edge_triangle_count {
+            return false;
+        }
+
+        self.get_live_triangles(from)
+            .filter(|&t| !self.triangles[t].contains(&(to as u32)))
+            .all(|t| {
+                let corners = self.triangles[t].map(|index| self.positions[index as usize]);
+                let moved = self.triangles[t].map(|index| if index as usize == from { self.positions[to] } else { self.positions[index as usize] });
+                let old_normal = (corners[1] - corners[0]).cross(corners[2] - corners[0]);
+                let new_normal = (moved[1] - moved[0]).cross(moved[2] - moved[0]);
+                new_normal.length_squared() > f32::EPSILON * old_normal.length_squared() && old_normal.dot(new_normal) > 0.0
+            })
+    }
+
+    fn collapse(&mut self, from: usize, to: usize, cost: f64) {
+        let from_triangles: Vec<usize> = self.get_live_triangles(from).collect();
+        for t in from_triangles {
+            if self.triangles[t].contains(&(to as u32)) {
+                self.removed_triangles[t] = true;
+                self.triangle_count -= 1;
+            } else {
+                for index in self.triangles[t].iter_mut().filter(|index| **index as usize == from) {
+                    *index = to as u32;
+                }
+                self.vertex_triangles[to].push(t);
+            }
+        }
+
+        let from_quadric = self.quadrics[from];
+        self.quadrics[to].add(&from_quadric);
+        self.removed[from] = true;
+        self.vertex_triangles[from].clear();
+        let removed_triangles = &self.removed_triangles;
+        self.vertex_triangles[to].retain(|&t| !removed_triangles[t]);
+        self.versions[to] += 1;
+        self.error = self.error.max(cost);
+
+        for neighbour in self.get_neighbours(to) {
+            self.push_collapse(neighbour, to as u32);
+            self.push_collapse(to as u32, neighbour);
+        }
+    }
+}
+
+/// Simplifies the indexed triangles until at most `target_triangle_count` are left or the surface would move further than `max_error`.
+/// The returned indices refer to the unchanged vertices.
+pub fn simplify_indices(vertices: &[Vertex], indices: &[u32], target_triangle_count: usize, max_error: f32) -> MeshLod {
+    let mut simplifier = Simplifier::new(vertices, indices);
+    simplifier.run(target_triangle_count, max_error);
+    MeshLod { indices: simplifier.get_indices(), error: simplifier.get_error() }
+}
+
+/// Reduced copy of the mesh with only the vertices the remaining triangles use. The material is kept, faces and levels of detail are dropped.
+pub fn simplify(mesh: &Mesh, target_triangle_count: usize, max_error: f32) -> Mesh {
+    let lod = simplify_indices(&mesh.vertices, &mesh.indices, target_triangle_count, max_error);
+
+    let mut remap: Vec<Option<u32>> = vec![None; mesh.vertices.len()];
+    let mut vertices = Vec::new();
+    let indices: Vec<u32> = lod.indices.iter()
+        .map(|&index| *remap[index as usize].get_or_insert_with(|| {
+            vertices.push(mesh.vertices[index as usize]);
+            (vertices.len() - 1) as u32
+        }))
+        .collect();
+
+    Mesh {
+        vertices: vertices.into_boxed_slice(),
+        indices: indices.into_boxed_slice(),
+        faces: Box::new([]),
+        material: mesh.material.clone(),
+        lods: None,
+    }
+}
+
+/// Simplifies the triangles level by level, every level aiming for half the triangles of the previous one.
+/// Stops after `level_count` levels, once a level would exceed `max_error` or removes less than a tenth of the triangles.
+pub fn build_lod_levels(vertices: &[Vertex], indices: &[u32], level_count: usize, max_error: f32) -> Vec<MeshLod> {
+    let mut simplifier = Simplifier::new(vertices, indices);
+    let mut levels = Vec::new();
+    while levels.len() < level_count {
+        let previous_count = simplifier.triangle_count;
+        simplifier.run(previous_count / 2, max_error);
+        if simplifier.triangle_count * 10 > previous_count * 9 {
+            break;
+        }
+        levels.push(MeshLod { indices: simplifier.get_indices(), error: simplifier.get_error() });
+    }
+    levels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::rendering::mesh::bounds::Aabb;
+
+    #[test]
+    fn simplified_meshes_reach_the_target() {
+        let sphere = Mesh::uv_sphere(1.0, 48, 32);
+        let simplified = simplify(&sphere, 500, f32::INFINITY);
+        assert!(simplified.indices.len() / 3 <= 500);
+        assert!(simplified.vertices.len() < sphere.vertices.len());
+        assert!(simplified.indices.iter().all(|&index| (index as usize) < simplified.vertices.len()));
+    }
+
+    #[test]
+    fn error_limit_stops_the_simplification() {
+        let sphere = Mesh::uv_sphere(1.0, 48, 32);
+        let coarse = simplify_indices(&sphere.vertices, &sphere.indices, 0, 0.1);
+        let fine = simplify_indices(&sphere.vertices, &sphere.indices, 0, 0.01);
+        assert!(coarse.error <= 0.1 && fine.error <= 0.01);
+        assert!(coarse.indices.len() < fine.indices.len());
+        assert!(!fine.indices.is_empty());
+    }
+
+    #[test]
+    fn borders_keep_their_place() {
+        let plane = Mesh::plane(2.0, 1.0, 16, 8);
+        let simplified = simplify(&plane, 0, f32::INFINITY);
+        assert!(simplified.indices.len() < plane.indices.len());
+        assert_eq!(Aabb::from_vertices(&simplified.vertices), Aabb::from_vertices(&plane.vertices));
+    }
+}
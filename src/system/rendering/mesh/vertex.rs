@@ -1,5 +1,56 @@
+use std::mem;
+use bytemuck::{offset_of, Pod, Zeroable};
+
+/// Numeric type and component count of a single vertex attribute, independent of the graphics api.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AttributeFormat {
+    Float,
+    Float2,
+    Float3,
+    Float4,
+    UInt4,
+}
+
+/// One attribute of a vertex type: the shader input location it feeds and where it lies inside the vertex.
+#[derive(Clone, Copy)]
+pub struct VertexAttribute {
+    pub location: u32,
+    pub format: AttributeFormat,
+    pub offset: u32,
+}
+
+/// Describes the memory layout of a vertex type, so pipelines can derive their vertex input from it.
+/// Attribute locations follow one convention for every format:
+/// 0 position, 1 uv, 2 normal, 3 joint indices, 4 joint weights.
+pub trait VertexFormat: Copy + 'static {
+    fn get_attributes() -> Vec<VertexAttribute>;
+
+    fn get_stride() -> u32 {
+        mem::size_of::<Self>() as u32
+    }
+}
+
+/// Vertex with only a position, e.g. for depth only passes and debug lines.
+#[repr(C)]
+#[derive(Clone, Copy, Default, Pod, Zeroable)]
+pub struct PositionVertex {
+    pub position: [f32; 3],
+}
+
+impl VertexFormat for PositionVertex {
+    fn get_attributes() -> Vec<VertexAttribute> {
+        vec![
+            VertexAttribute { location: 0, format: AttributeFormat::Float3, offset: offset_of!(PositionVertex, position) as u32 },
+        ]
+    }
+}
+
+/// The default vertex with position, normal and texture coordinates.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
 pub struct Vertex{
-    pub position: [f64; 3] ,
+    pub position: [f32; 3] ,
+    pub normal: [f32; 3],
     pub uv: [f32; 2],
     // color: [u8]
 }
@@ -8,7 +59,41 @@ impl Default for Vertex{
     fn default() -> Self {
         Vertex{
             position: [0.0, 0.0, 0.0],
+            normal: [0.0, 0.0, 0.0],
             uv: [0.0, 0.0],
         }
     }
-}
\ No newline at end of file
+}
+
+impl VertexFormat for Vertex {
+    fn get_attributes() -> Vec<VertexAttribute> {
+        vec![
+            VertexAttribute { location: 0, format: AttributeFormat::Float3, offset: offset_of!(Vertex, position) as u32 },
+            VertexAttribute { location: 1, format: AttributeFormat::Float2, offset: offset_of!(Vertex, uv) as u32 },
+            VertexAttribute { location: 2, format: AttributeFormat::Float3, offset: offset_of!(Vertex, normal) as u32 },
+        ]
+    }
+}
+
+/// Vertex influenced by up to four joints. The weights of a vertex add up to one.
+#[repr(C)]
+#[derive(Clone, Copy, Default, Pod, Zeroable)]
+pub struct SkinnedVertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub uv: [f32; 2],
+    pub joints: [u32; 4],
+    pub weights: [f32; 4],
+}
+
+impl VertexFormat for SkinnedVertex {
+    fn get_attributes() -> Vec<VertexAttribute> {
+        vec![
+            VertexAttribute { location: 0, format: AttributeFormat::Float3, offset: offset_of!(SkinnedVertex, position) as u32 },
+            VertexAttribute { location: 1, format: AttributeFormat::Float2, offset: offset_of!(SkinnedVertex, uv) as u32 },
+            VertexAttribute { location: 2, format: AttributeFormat::Float3, offset: offset_of!(SkinnedVertex, normal) as u32 },
+            VertexAttribute { location: 3, format: AttributeFormat::UInt4, offset: offset_of!(SkinnedVertex, joints) as u32 },
+            VertexAttribute { location: 4, format: AttributeFormat::Float4, offset: offset_of!(SkinnedVertex, weights) as u32 },
+        ]
+    }
+}
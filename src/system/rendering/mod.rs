@@ -1,14 +1,38 @@
 pub mod vulkan;
+pub mod software;
 pub mod mesh;
+pub mod render_image;
+pub mod camera_uniform;
+pub mod camera;
+pub mod camera_controller;
+pub mod material;
+pub mod render_list;
+pub mod frustum;
 
+use std::sync::Arc;
 use winit::window::{Window};
-use crate::system::rendering::mesh::mesh::Mesh;
+use crate::system::assets::manager::AssetManager;
+use crate::system::ini::RenderingIniData;
+use crate::system::rendering::camera_uniform::CameraUniform;
+use crate::system::rendering::render_image::RenderImage;
+use crate::system::rendering::render_list::{CullStats, RenderList};
 
 
 pub trait IRenderer {
-    fn new(window: &Window) -> Self where Self: Sized;
+    /// Creates a renderer that presents to the window. Textures of materials are requested from the asset manager.
+    fn new(window: &Window, settings: &RenderingIniData, assets: Arc<AssetManager>) -> Self where Self: Sized;
+    /// Creates a renderer without a window, that renders into an offscreen image of the given size.
+    fn new_headless(width: u32, height: u32, settings: &RenderingIniData, assets: Arc<AssetManager>) -> Self where Self: Sized;
     fn get_gpu_name(&self) -> &str;
-    fn render(&self, meshes: &[Mesh]);
-    fn recreate_pipelines(&self, window_width: u32, window_height: u32);
-    fn dispose(&self);
+    /// Draws the items of the render list as seen by the camera. The transform of every item moves its mesh into world space.
+    /// Items outside of the camera's view are culled before any draw is recorded.
+    /// Textures that are still loading are drawn white, so the frame never waits for the disk.
+    fn render(&mut self, camera: &CameraUniform, render_list: &RenderList);
+    /// Visible and culled items of the last rendered frame.
+    fn get_cull_stats(&self) -> CullStats;
+    /// Returns the last rendered frame. Only available for headless renderers.
+    fn read_frame(&self) -> Option<RenderImage>;
+    /// Rebuilds all size dependent resources. Called whenever the window got resized, a size of zero means it got minimized.
+    fn recreate_pipelines(&mut self, window_width: u32, window_height: u32);
+    fn dispose(&mut self);
 }
\ No newline at end of file
@@ -0,0 +1,22 @@
+use std::fs::File;
+use std::io::{Error, Write};
+
+/// Cpu side copy of a rendered frame. Pixels are stored row by row as RGBA with 8 bits per channel.
+#[derive(Clone)]
+pub struct RenderImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl RenderImage {
+    /// Writes the image as binary PPM. Alpha is dropped, since the format does not support it.
+    pub fn save_as_ppm(&self, path: &str) -> Result<(), Error> {
+        let mut file = File::create(path)?;
+        write!(file, "P6\n{} {}\n255\n", self.width, self.height)?;
+
+        let rgb: Vec<u8> = self.pixels.chunks_exact(4).flat_map(|pixel| [pixel[0], pixel[1], pixel[2]]).collect();
+        file.write_all(&rgb)?;
+        Ok(())
+    }
+}
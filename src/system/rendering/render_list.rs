@@ -0,0 +1,89 @@
+use std::sync::Arc;
+use crate::system::math::matrix::Mat4;
+use crate::system::rendering::camera_uniform::CameraUniform;
+use crate::system::rendering::frustum::Frustum;
+use crate::system::rendering::material::MaterialInstance;
+use crate::system::rendering::mesh::bounds::Aabb;
+use crate::system::rendering::mesh::mesh::Mesh;
+
+/// One mesh to draw with its material and its model matrix, which moves it from its own space into world space.
+#[derive(Clone)]
+pub struct RenderItem {
+    pub mesh: Arc<Mesh>,
+    /// Material of the item, None draws it with `Material::fallback`.
+    pub material: Option<Arc<MaterialInstance>>,
+    pub transform: Mat4,
+    /// Bounds of the mesh in its own space, None if it has no vertices.
+    pub bounds: Option<Aabb>,
+}
+
+/// How many items of a render list a frame drew and how many were outside of the camera's view.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct CullStats {
+    pub visible: usize,
+    pub culled: usize,
+}
+
+/// Everything a renderer draws in one frame, extracted from the scene.
+#[derive(Clone, Default)]
+pub struct RenderList {
+    pub items: Vec<RenderItem>,
+}
+
+impl RenderList {
+    pub fn new() -> Self {
+        RenderList { items: Vec::new() }
+    }
+
+    /// Adds the mesh with its own material. Its bounds are computed from the vertices, pass known bounds to `push_with_bounds` instead.
+    pub fn push(&mut self, mesh: Arc<Mesh>, transform: Mat4) {
+        let bounds = Aabb::from_vertices(&mesh.vertices);
+        self.push_with_bounds(mesh, bounds, transform);
+    }
+
+    pub fn push_with_bounds(&mut self, mesh: Arc<Mesh>, bounds: Option<Aabb>, transform: Mat4) {
+        let material = mesh.material.clone();
+        self.items.push(RenderItem { mesh, material, transform, bounds });
+    }
+
+    /// Items whose bounds intersect the view of the camera, in their order in the list. Items without vertices count as culled.
+    pub fn cull(&self, camera: &CameraUniform) -> (Vec<&RenderItem>, CullStats) {
+        let frustum = Frustum::from_camera(camera);
+        let visible: Vec<&RenderItem> = self.items.iter()
+            .filter(|item| item.bounds.map(|bounds| frustum.intersects_aabb(&bounds, &item.transform)).unwrap_or(false))
+            .collect();
+        let stats = CullStats { visible: visible.len(), culled: self.items.len() - visible.len() };
+        (visible, stats)
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+    use super::*;
+    use crate::system::math::vector::Vec3;
+
+    #[test]
+    fn culling_skips_items_outside_of_the_view() {
+        let camera = CameraUniform::look_at([0.0; 3], [0.0, 0.0, -1.0], [0.0, 1.0, 0.0], FRAC_PI_2, 1.0, 0.1, 100.0);
+        let cube = Arc::new(Mesh::cube(1.0, 1));
+        let mut render_list = RenderList::new();
+        render_list.push(cube.clone(), Mat4::from_translation(Vec3::new(0.0, 0.0, -5.0)));
+        render_list.push(cube.clone(), Mat4::from_translation(Vec3::new(0.0, 0.0, 5.0)));
+        render_list.push(cube, Mat4::from_translation(Vec3::new(0.0, 50.0, -5.0)));
+        let empty = Mesh { vertices: Box::new([]), indices: Box::new([]), faces: Box::new([]), material: None, lods: None };
+        render_list.push(Arc::new(empty), Mat4::IDENTITY);
+
+        let (visible, stats) = render_list.cull(&camera);
+        assert_eq!(stats, CullStats { visible: 1, culled: 3 });
+        assert_eq!(visible[0].transform.get_translation(), Vec3::new(0.0, 0.0, -5.0));
+    }
+}
@@ -0,0 +1,2 @@
+pub mod renderer;
+mod rasterizer;
@@ -0,0 +1,220 @@
+use std::sync::Arc;
+use crate::system::assets::texture::TextureImage;
+use crate::system::ini::{TextureFilter, TextureWrap};
+use crate::system::math::matrix::Mat4;
+use crate::system::rendering::camera_uniform::CameraUniform;
+use crate::system::rendering::material::MaterialInstance;
+use crate::system::rendering::mesh::mesh::Mesh;
+use crate::system::rendering::render_image::RenderImage;
+
+/// Vertex after the camera transform, before the perspective divide.
+#[derive(Clone, Copy)]
+struct ClipVertex {
+    position: [f32; 4],
+    uv: [f32; 2],
+}
+
+/// Vertex after the viewport transform. x and y are in pixels, z is the depth in [0, 1].
+/// The uv is stored divided by w, so it can be interpolated perspective correct.
+#[derive(Clone, Copy)]
+struct ScreenVertex {
+    x: f32,
+    y: f32,
+    z: f32,
+    inverse_w: f32,
+    uv_over_w: [f32; 2],
+}
+
+/// Rasterizes triangles into a cpu framebuffer with the same conventions as the vulkan pipeline:
+/// positions are transformed by the camera, y points down in clip space, triangles are clipped at the near plane,
+/// attributes are interpolated perspective correct, depth is tested with LESS_OR_EQUAL and nothing is culled.
+pub struct Rasterizer {
+    color: RenderImage,
+    depth: Vec<f32>,
+    /// Base color of the mesh that is drawn.
+    base_color: [f32; 4],
+    /// First texture of the material of the mesh that is drawn.
+    texture: Arc<TextureImage>,
+    texture_filter: TextureFilter,
+    texture_wrap: TextureWrap,
+}
+
+impl Rasterizer {
+    pub fn new(width: u32, height: u32, texture_filter: TextureFilter, texture_wrap: TextureWrap) -> Rasterizer {
+        let pixel_count = (width * height) as usize;
+        Rasterizer {
+            color: RenderImage {
+                width,
+                height,
+                pixels: vec![0; pixel_count * 4],
+            },
+            depth: vec![1.0; pixel_count],
+            base_color: [1.0; 4],
+            texture: Arc::new(TextureImage::white()),
+            texture_filter,
+            texture_wrap,
+        }
+    }
+
+    pub fn get_color(&self) -> &RenderImage {
+        &self.color
+    }
+
+    pub fn clear(&mut self, color: [u8; 4], depth: f32) {
+        for pixel in self.color.pixels.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&color);
+        }
+        self.depth.iter_mut().for_each(|value| *value = depth);
+    }
+
+    /// Draws the mesh, moved into world space by the transform, with the given material, which is the material of the render item or a fallback for items without one.
+    /// The texture is the decoded first texture slot of the material, or a white texture.
+    /// The indices are those of the mesh or of one of its levels of detail, meshes without indices are drawn as a triangle list.
+    pub fn draw_mesh(&mut self, camera: &CameraUniform, mesh: &Mesh, transform: &Mat4, indices: &[u32], material: &MaterialInstance, texture: Arc<TextureImage>) {
+        self.base_color = material.get_uniform_data().first().copied().unwrap_or([1.0; 4]);
+        self.texture = texture;
+
+        let clip_vertices: Vec<ClipVertex> = mesh.vertices.iter()
+            .map(|vertex| ClipVertex { position: camera.project(transform.transform_point(vertex.position.into()).into()), uv: vertex.uv })
+            .collect();
+
+        if indices.is_empty() {
+            for triangle in clip_vertices.chunks_exact(3) {
+                self.clip_and_draw_triangle(&[triangle[0], triangle[1], triangle[2]]);
+            }
+            return;
+        }
+
+        for triangle in indices.chunks_exact(3) {
+            let vertex_count = clip_vertices.len();
+            if triangle.iter().any(|&index| index as usize >= vertex_count) {
+                continue;
+            }
+            self.clip_and_draw_triangle(&[clip_vertices[triangle[0] as usize],
+                                          clip_vertices[triangle[1] as usize],
+                                          clip_vertices[triangle[2] as usize]]);
+        }
+    }
+
+    /// Clips the triangle against the near plane z = 0. The remaining polygon has at most four corners and is drawn as a fan.
+    fn clip_and_draw_triangle(&mut self, triangle: &[ClipVertex; 3]) {
+        let mut polygon: Vec<ClipVertex> = Vec::with_capacity(4);
+        for (i, current) in triangle.iter().enumerate() {
+            let next = &triangle[(i + 1) % 3];
+            let current_inside = current.position[2] >= 0.0;
+            let next_inside = next.position[2] >= 0.0;
+
+            if current_inside {
+                polygon.push(*current);
+            }
+            if current_inside != next_inside {
+                let t = current.position[2] / (current.position[2] - next.position[2]);
+                let lerp = |a: f32, b: f32| a + (b - a) * t;
+                polygon.push(ClipVertex {
+                    position: [0, 1, 2, 3].map(|c| lerp(current.position[c], next.position[c])),
+                    uv: [lerp(current.uv[0], next.uv[0]), lerp(current.uv[1], next.uv[1])],
+                });
+            }
+        }
+
+        for i in 1..polygon.len().saturating_sub(1) {
+            self.draw_triangle(&polygon[0], &polygon[i], &polygon[i + 1]);
+        }
+    }
+
+    fn draw_triangle(&mut self, v0: &ClipVertex, v1: &ClipVertex, v2: &ClipVertex) {
+        let s0 = self.to_screen(v0);
+        let s1 = self.to_screen(v1);
+        let s2 = self.to_screen(v2);
+
+        let area = Rasterizer::edge(&s0, &s1, s2.x, s2.y);
+        if area == 0.0 {
+            return;
+        }
+
+        let min_x = s0.x.min(s1.x).min(s2.x).floor().max(0.0) as u32;
+        let min_y = s0.y.min(s1.y).min(s2.y).floor().max(0.0) as u32;
+        let max_x = (s0.x.max(s1.x).max(s2.x).ceil() as i64).clamp(0, self.color.width as i64) as u32;
+        let max_y = (s0.y.max(s1.y).max(s2.y).ceil() as i64).clamp(0, self.color.height as i64) as u32;
+
+        // Orient the edges so the inside of the triangle is always positive, independent of the winding order.
+        let sign = area.signum();
+        let top_left0 = Rasterizer::is_top_left_edge(&s1, &s2, sign);
+        let top_left1 = Rasterizer::is_top_left_edge(&s2, &s0, sign);
+        let top_left2 = Rasterizer::is_top_left_edge(&s0, &s1, sign);
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let sample_x = x as f32 + 0.5;
+                let sample_y = y as f32 + 0.5;
+
+                let w0 = Rasterizer::edge(&s1, &s2, sample_x, sample_y) * sign;
+                let w1 = Rasterizer::edge(&s2, &s0, sample_x, sample_y) * sign;
+                let w2 = Rasterizer::edge(&s0, &s1, sample_x, sample_y) * sign;
+                if !Rasterizer::is_covered(w0, top_left0) || !Rasterizer::is_covered(w1, top_left1) || !Rasterizer::is_covered(w2, top_left2) {
+                    continue;
+                }
+
+                let total = w0 + w1 + w2;
+                let b0 = w0 / total;
+                let b1 = w1 / total;
+                let b2 = w2 / total;
+
+                let depth = b0 * s0.z + b1 * s1.z + b2 * s2.z;
+                if !(0.0..=1.0).contains(&depth) {
+                    continue;
+                }
+
+                let pixel_index = (y * self.color.width + x) as usize;
+                if depth > self.depth[pixel_index] {
+                    continue;
+                }
+                self.depth[pixel_index] = depth;
+
+                let inverse_w = b0 * s0.inverse_w + b1 * s1.inverse_w + b2 * s2.inverse_w;
+                let u = (b0 * s0.uv_over_w[0] + b1 * s1.uv_over_w[0] + b2 * s2.uv_over_w[0]) / inverse_w;
+                let v = (b0 * s0.uv_over_w[1] + b1 * s1.uv_over_w[1] + b2 * s2.uv_over_w[1]) / inverse_w;
+                let color = self.shade(u, v);
+                self.color.pixels[pixel_index * 4..pixel_index * 4 + 4].copy_from_slice(&color);
+            }
+        }
+    }
+
+    /// Cpu version of the default fragment shader, which multiplies the uv color with the first texture and the first parameter of the material.
+    /// Textures are sampled without mip levels. Blending is not emulated, so the alpha is always opaque.
+    fn shade(&self, u: f32, v: f32) -> [u8; 4] {
+        let to_byte = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+        let [red, green, blue, _] = self.base_color;
+        let [texture_red, texture_green, texture_blue, _] = self.texture.sample(u, v, self.texture_filter, self.texture_wrap);
+        [to_byte(u * texture_red * red), to_byte(v * texture_green * green), to_byte(0.5 * texture_blue * blue), 255]
+    }
+
+    fn to_screen(&self, vertex: &ClipVertex) -> ScreenVertex {
+        let inverse_w = 1.0 / vertex.position[3];
+        ScreenVertex {
+            x: (vertex.position[0] * inverse_w + 1.0) * 0.5 * self.color.width as f32,
+            y: (vertex.position[1] * inverse_w + 1.0) * 0.5 * self.color.height as f32,
+            z: vertex.position[2] * inverse_w,
+            inverse_w,
+            uv_over_w: [vertex.uv[0] * inverse_w, vertex.uv[1] * inverse_w],
+        }
+    }
+
+    fn edge(a: &ScreenVertex, b: &ScreenVertex, x: f32, y: f32) -> f32 {
+        (b.x - a.x) * (y - a.y) - (b.y - a.y) * (x - a.x)
+    }
+
+    /// Top-left fill rule: samples exactly on a top or left edge are covered, samples on the other edges are not.
+    /// This keeps triangles sharing an edge from drawing a pixel twice.
+    fn is_covered(edge_value: f32, is_top_left: bool) -> bool {
+        edge_value > 0.0 || (edge_value == 0.0 && is_top_left)
+    }
+
+    fn is_top_left_edge(a: &ScreenVertex, b: &ScreenVertex, sign: f32) -> bool {
+        let edge_x = (b.x - a.x) * sign;
+        let edge_y = (b.y - a.y) * sign;
+        let is_top_edge = edge_y == 0.0 && edge_x > 0.0;
+        let is_left_edge = edge_y < 0.0;
+        is_top_edge || is_left_edge
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use raw_window_handle::{HasRawWindowHandle, RawWindowHandle};
+use softbuffer::GraphicsContext;
+use winit::window::Window;
+use crate::system::assets::manager::{AssetManager, AssetState, Handle};
+use crate::system::assets::texture::TextureImage;
+use crate::system::ini::{RenderingIniData, TextureFilter, TextureWrap};
+use crate::system::log::Log;
+use crate::system::rendering::IRenderer;
+use crate::system::rendering::camera_uniform::CameraUniform;
+use crate::system::rendering::material::MaterialInstance;
+use crate::system::rendering::render_image::RenderImage;
+use crate::system::rendering::render_list::{CullStats, RenderList};
+use crate::system::rendering::software::rasterizer::Rasterizer;
+
+const CLEAR_COLOR: [u8; 4] = [0, 0, 0, 255];
+
+/// Copy of the raw handle of the window the renderer presents to.
+/// The window is owned by ResaWindow and outlives the renderer.
+struct WindowHandle(RawWindowHandle);
+
+unsafe impl HasRawWindowHandle for WindowHandle {
+    fn raw_window_handle(&self) -> RawWindowHandle {
+        self.0
+    }
+}
+
+/// Texture of the first material slot, decoded by the asset manager.
+enum TextureEntry {
+    Loading(Handle<TextureImage>),
+    Ready(Arc<TextureImage>),
+    Failed,
+}
+
+/// Pure cpu reference renderer. It is used if no vulkan loader is available
+/// and produces deterministic images to compare the vulkan output against.
+pub struct SoftwareRenderer {
+    rasterizer: Rasterizer,
+    surface: Option<GraphicsContext<WindowHandle>>,
+    /// Used for items without a material.
+    default_material: Arc<MaterialInstance>,
+    textures: HashMap<PathBuf, TextureEntry>,
+    /// Drawn for empty slots and for textures that are loading or failed to load.
+    white_texture: Arc<TextureImage>,
+    texture_filter: TextureFilter,
+    texture_wrap: TextureWrap,
+    lod_pixel_error: f32,
+    cull_stats: CullStats,
+    assets: Arc<AssetManager>,
+}
+
+impl IRenderer for SoftwareRenderer {
+    fn new(window: &Window, settings: &RenderingIniData, assets: Arc<AssetManager>) -> Self where Self: Sized {
+        Log::get().write("Create new software renderer");
+        let size = window.inner_size();
+        let surface = match unsafe { GraphicsContext::new(WindowHandle(window.raw_window_handle())) } {
+            Ok(context) => Some(context),
+            Err(_) => {
+                Log::get().write_error("Creating the presentation surface of the software renderer failed!");
+                None
+            }
+        };
+
+        SoftwareRenderer::create(size.width, size.height, surface, settings, assets)
+    }
+
+    fn new_headless(width: u32, height: u32, settings: &RenderingIniData, assets: Arc<AssetManager>) -> Self where Self: Sized {
+        Log::get().write("Create new headless software renderer");
+        SoftwareRenderer::create(width, height, None, settings, assets)
+    }
+
+    fn get_gpu_name(&self) -> &str {
+        "Software Rasterizer"
+    }
+
+    fn render(&mut self, camera: &CameraUniform, render_list: &RenderList) {
+        self.rasterizer.clear(CLEAR_COLOR, 1.0);
+        let viewport_height = self.rasterizer.get_color().height;
+        let (visible_items, cull_stats) = render_list.cull(camera);
+        self.cull_stats = cull_stats;
+        for item in visible_items {
+            let material = item.material.clone().unwrap_or_else(|| self.default_material.clone());
+            let texture = self.get_texture(&material);
+            let indices = item.mesh.select_lod(camera, &item.transform, viewport_height, self.lod_pixel_error);
+            self.rasterizer.draw_mesh(camera, &item.mesh, &item.transform, indices, &material, texture);
+        }
+
+        let image = self.rasterizer.get_color();
+        if image.width == 0 || image.height == 0 {
+            // The window is minimized, there is nothing to present.
+            return;
+        }
+
+        if let Some(surface) = &mut self.surface {
+            let buffer: Vec<u32> = image.pixels.chunks_exact(4)
+                .map(|pixel| (pixel[0] as u32) << 16 | (pixel[1] as u32) << 8 | pixel[2] as u32)
+                .collect();
+            surface.set_buffer(&buffer, image.width as u16, image.height as u16);
+        }
+    }
+
+    fn get_cull_stats(&self) -> CullStats {
+        self.cull_stats
+    }
+
+    fn read_frame(&self) -> Option<RenderImage> {
+        if self.surface.is_some() {
+            return None;
+        }
+        Some(self.rasterizer.get_color().clone())
+    }
+
+    fn recreate_pipelines(&mut self, window_width: u32, window_height: u32) {
+        self.rasterizer = Rasterizer::new(window_width, window_height, self.texture_filter, self.texture_wrap);
+    }
+
+    fn dispose(&mut self) {
+        Log::get().write("Disposing software renderer");
+    }
+}
+
+impl SoftwareRenderer {
+    fn create(width: u32, height: u32, surface: Option<GraphicsContext<WindowHandle>>, settings: &RenderingIniData, assets: Arc<AssetManager>) -> Self {
+        SoftwareRenderer {
+            rasterizer: Rasterizer::new(width, height, settings.texture_filter, settings.texture_wrap),
+            surface,
+            default_material: Arc::new(MaterialInstance::fallback()),
+            textures: HashMap::new(),
+            white_texture: Arc::new(TextureImage::white()),
+            texture_filter: settings.texture_filter,
+            texture_wrap: settings.texture_wrap,
+            lod_pixel_error: settings.lod_pixel_error,
+            cull_stats: CullStats::default(),
+            assets,
+        }
+    }
+
+    /// Returns the first texture of the material, the only one the default shaders sample. It is requested from the asset manager on first use.
+    /// Empty slots and textures that are loading or failed to load are white.
+    fn get_texture(&mut self, material: &MaterialInstance) -> Arc<TextureImage> {
+        let path = match material.get_textures().first() {
+            Some(Some(path)) => path,
+            _ => return self.white_texture.clone(),
+        };
+
+        let assets = &self.assets;
+        let entry = self.textures.entry(path.clone()).or_insert_with(|| TextureEntry::Loading(assets.load(path)));
+        if let TextureEntry::Loading(handle) = entry {
+            match handle.get_state() {
+                AssetState::Loading => (),
+                AssetState::Ready(texture) => *entry = TextureEntry::Ready(texture),
+                AssetState::Failed(message) => {
+                    Log::get().write_error(format!("Loading texture failed, it is drawn white: {}", message).as_str());
+                    *entry = TextureEntry::Failed;
+                }
+            }
+        }
+
+        match entry {
+            TextureEntry::Ready(texture) => texture.clone(),
+            _ => self.white_texture.clone(),
+        }
+    }
+}
@@ -0,0 +1,398 @@
+use std::ffi::c_void;
+use ash::Device;
+use ash::vk::{DeviceMemory, DeviceSize, MemoryAllocateInfo, MemoryMapFlags, MemoryPropertyFlags, MemoryRequirements, WHOLE_SIZE};
+use crate::system::log::Log;
+use crate::system::rendering::vulkan::global::VkPhysicalDevice;
+
+/// Size of a regular memory block. Bigger resources get a block of their own.
+const DEFAULT_BLOCK_SIZE: DeviceSize = 64 * 1024 * 1024;
+/// Small heaps, e.g. the 256 MiB device local and host visible heap, get smaller blocks so they are not used up by a few of them.
+const HEAP_BLOCK_FRACTION: DeviceSize = 8;
+
+/// How allocations are placed inside a block.
+#[derive(Clone, Copy, PartialEq)]
+pub enum AllocationStrategy {
+    /// First fit into the gaps between the live allocations. Allocations can be freed in any order.
+    FreeList,
+    /// Allocations are appended at the end of the block. Freed space is only reused once the whole block is empty,
+    /// which makes it a good fit for resources that are created and released together, like staging or per frame data.
+    Linear,
+}
+
+/// Buffers and linear images may not share a bufferImageGranularity sized page with optimal images.
+#[derive(Clone, Copy, PartialEq)]
+pub enum ResourceKind {
+    Linear,
+    Optimal,
+}
+
+/// A piece of a memory block, bound to exactly one buffer or image.
+pub struct Allocation {
+    pub memory: DeviceMemory,
+    pub offset: DeviceSize,
+    /// Pointer to the start of the allocation, if the memory is host visible. The block stays mapped for its whole lifetime.
+    pub mapped_ptr: Option<*mut u8>,
+    memory_type_index: u32,
+    strategy: AllocationStrategy,
+    block_id: u64,
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct MemoryStatistics {
+    pub block_count: u32,
+    pub allocation_count: u32,
+    /// Bytes allocated from the driver.
+    pub reserved_bytes: DeviceSize,
+    /// Bytes handed out to resources, including alignment padding.
+    pub used_bytes: DeviceSize,
+}
+
+impl MemoryStatistics {
+    fn add(&mut self, other: &MemoryStatistics) {
+        self.block_count += other.block_count;
+        self.allocation_count += other.allocation_count;
+        self.reserved_bytes += other.reserved_bytes;
+        self.used_bytes += other.used_bytes;
+    }
+}
+
+/// Everything needed to place an allocation inside a block.
+struct PlacementRequest {
+    size: DeviceSize,
+    alignment: DeviceSize,
+    kind: ResourceKind,
+    granularity: DeviceSize,
+}
+
+struct BlockAllocation {
+    offset: DeviceSize,
+    size: DeviceSize,
+    kind: ResourceKind,
+}
+
+struct MemoryBlock {
+    id: u64,
+    memory: DeviceMemory,
+    size: DeviceSize,
+    mapped_ptr: Option<*mut u8>,
+    strategy: AllocationStrategy,
+    /// Live allocations, sorted by offset.
+    allocations: Vec<BlockAllocation>,
+    /// End of the last allocation of a linear block.
+    linear_offset: DeviceSize,
+}
+
+impl MemoryBlock {
+    /// Finds an offset for the allocation and reserves the range, or returns None if the block is too full.
+    fn allocate(&mut self, request: &PlacementRequest) -> Option<DeviceSize> {
+        let (index, offset) = match self.strategy {
+            AllocationStrategy::Linear => {
+                let offset = MemoryBlock::place(self.allocations.last(), None, self.linear_offset, self.size, request)?;
+                (self.allocations.len(), offset)
+            }
+            AllocationStrategy::FreeList => {
+                let mut found = None;
+                for index in 0..=self.allocations.len() {
+                    let previous = if index == 0 { None } else { self.allocations.get(index - 1) };
+                    let next = self.allocations.get(index);
+                    let gap_start = previous.map_or(0, |allocation| allocation.offset + allocation.size);
+                    let gap_end = next.map_or(self.size, |allocation| allocation.offset);
+                    if let Some(offset) = MemoryBlock::place(previous, next, gap_start, gap_end, request) {
+                        found = Some((index, offset));
+                        break;
+                    }
+                }
+                found?
+            }
+        };
+
+        self.allocations.insert(index, BlockAllocation { offset, size: request.size, kind: request.kind });
+        self.linear_offset = self.linear_offset.max(offset + request.size);
+        Some(offset)
+    }
+
+    fn free(&mut self, offset: DeviceSize) {
+        self.allocations.retain(|allocation| allocation.offset != offset);
+        if self.allocations.is_empty() {
+            self.linear_offset = 0;
+        }
+    }
+
+    /// Places an allocation into the gap between two neighbours. If a neighbour is a different kind of resource,
+    /// the allocation is moved to the next page of bufferImageGranularity, so they never share one.
+    fn place(previous: Option<&BlockAllocation>, next: Option<&BlockAllocation>, gap_start: DeviceSize, gap_end: DeviceSize,
+             request: &PlacementRequest) -> Option<DeviceSize> {
+        let mut offset = align_up(gap_start, request.alignment);
+        if let Some(previous) = previous {
+            if previous.kind != request.kind && is_on_same_page(previous.offset + previous.size - 1, offset, request.granularity) {
+                offset = align_up(offset, request.granularity);
+            }
+        }
+
+        let end = offset.checked_add(request.size)?;
+        if end > gap_end {
+            return None;
+        }
+        if let Some(next) = next {
+            if next.kind != request.kind && is_on_same_page(end - 1, next.offset, request.granularity) {
+                return None;
+            }
+        }
+        Some(offset)
+    }
+
+    fn get_used_bytes(&self) -> DeviceSize {
+        self.allocations.iter().map(|allocation| allocation.size).sum()
+    }
+}
+
+/// Sub-allocates buffer and image memory from a few big blocks instead of calling vkAllocateMemory for every resource.
+/// Every memory type and strategy has its own list of blocks.
+pub struct VkAllocator {
+    physical_device: VkPhysicalDevice,
+    blocks: Vec<Vec<MemoryBlock>>,
+    next_block_id: u64,
+}
+
+impl VkAllocator {
+    pub fn new(physical_device: &VkPhysicalDevice) -> Self {
+        let memory_type_count = physical_device.memory_properties.memory_type_count as usize;
+        VkAllocator {
+            physical_device: physical_device.clone(),
+            blocks: (0..memory_type_count).map(|_| Vec::new()).collect(),
+            next_block_id: 0,
+        }
+    }
+
+    pub fn allocate(&mut self, device: &Device, requirements: &MemoryRequirements, flags: MemoryPropertyFlags,
+                    kind: ResourceKind, strategy: AllocationStrategy) -> Allocation {
+        let memory_type_index = self.physical_device.find_memory_type_index(requirements, flags)
+            .expect("Unable to find suitable memory type!");
+        let request = PlacementRequest {
+            size: requirements.size,
+            alignment: requirements.alignment.max(1),
+            kind,
+            granularity: self.physical_device.buffer_image_granularity.max(1),
+        };
+
+        let blocks = &mut self.blocks[memory_type_index as usize];
+        for block in blocks.iter_mut().filter(|block| block.strategy == strategy) {
+            if let Some(offset) = block.allocate(&request) {
+                return VkAllocator::create_allocation(block, offset, memory_type_index);
+            }
+        }
+
+        let block_size = self.get_block_size(memory_type_index).max(requirements.size);
+        let mut block = VkAllocator::create_block(device, &self.physical_device, self.next_block_id, memory_type_index, block_size, strategy);
+        self.next_block_id += 1;
+        let offset = block.allocate(&request).expect("Allocation does not fit into a new block!");
+        let allocation = VkAllocator::create_allocation(&block, offset, memory_type_index);
+        self.blocks[memory_type_index as usize].push(block);
+        allocation
+    }
+
+    /// Returns the memory of the allocation to its block. Empty blocks are released, except for the last one of each kind.
+    pub fn free(&mut self, device: &Device, allocation: &Allocation) {
+        let blocks = &mut self.blocks[allocation.memory_type_index as usize];
+        let block_index = match blocks.iter().position(|block| block.id == allocation.block_id) {
+            Some(index) => index,
+            None => {
+                Log::get().write_error("Freed an allocation that does not belong to this allocator!");
+                return;
+            }
+        };
+
+        blocks[block_index].free(allocation.offset);
+        let same_strategy_count = blocks.iter().filter(|block| block.strategy == allocation.strategy).count();
+        if blocks[block_index].allocations.is_empty() && same_strategy_count > 1 {
+            let block = blocks.remove(block_index);
+            VkAllocator::destroy_block(device, &block);
+        }
+    }
+
+    pub fn get_statistics(&self) -> MemoryStatistics {
+        let mut statistics = MemoryStatistics::default();
+        for memory_type_index in 0..self.blocks.len() {
+            statistics.add(&self.get_memory_type_statistics(memory_type_index as u32));
+        }
+        statistics
+    }
+
+    pub fn get_memory_type_statistics(&self, memory_type_index: u32) -> MemoryStatistics {
+        let mut statistics = MemoryStatistics::default();
+        for block in self.blocks[memory_type_index as usize].iter() {
+            statistics.block_count += 1;
+            statistics.allocation_count += block.allocations.len() as u32;
+            statistics.reserved_bytes += block.size;
+            statistics.used_bytes += block.get_used_bytes();
+        }
+        statistics
+    }
+
+    pub fn log_statistics(&self) {
+        for memory_type_index in 0..self.blocks.len() as u32 {
+            let statistics = self.get_memory_type_statistics(memory_type_index);
+            if statistics.block_count == 0 {
+                continue;
+            }
+            let flags = self.physical_device.memory_properties.memory_types[memory_type_index as usize].property_flags;
+            Log::get().write(format!("Memory type {} ({:?}): {} blocks, {} allocations, {} of {} bytes used",
+                                     memory_type_index, flags, statistics.block_count, statistics.allocation_count,
+                                     statistics.used_bytes, statistics.reserved_bytes).as_str());
+        }
+        let total = self.get_statistics();
+        Log::get().write(format!("GPU memory: {} blocks, {} allocations, {} of {} bytes used",
+                                 total.block_count, total.allocation_count, total.used_bytes, total.reserved_bytes).as_str());
+    }
+
+    /// Releases every block. All resources using the memory must be destroyed already.
+    pub fn destroy(&mut self, device: &Device) {
+        for block in self.blocks.iter().flatten() {
+            VkAllocator::destroy_block(device, block);
+        }
+        self.blocks.iter_mut().for_each(|blocks| blocks.clear());
+    }
+
+    fn get_block_size(&self, memory_type_index: u32) -> DeviceSize {
+        let memory_properties = &self.physical_device.memory_properties;
+        let heap_index = memory_properties.memory_types[memory_type_index as usize].heap_index;
+        let heap_size = memory_properties.memory_heaps[heap_index as usize].size;
+        DEFAULT_BLOCK_SIZE.min(heap_size / HEAP_BLOCK_FRACTION)
+    }
+
+    fn create_block(device: &Device, physical_device: &VkPhysicalDevice, id: u64, memory_type_index: u32, size: DeviceSize, strategy: AllocationStrategy) -> MemoryBlock {
+        let allocate_info = MemoryAllocateInfo::builder()
+            .allocation_size(size)
+            .memory_type_index(memory_type_index);
+        let memory = unsafe { device.allocate_memory(&allocate_info, None).expect("Unable to allocate memory block!") };
+
+        let property_flags = physical_device.memory_properties.memory_types[memory_type_index as usize].property_flags;
+        let mapped_ptr = if property_flags.contains(MemoryPropertyFlags::HOST_VISIBLE) {
+            let data_ptr: *mut c_void = unsafe { device.map_memory(memory, 0, WHOLE_SIZE, MemoryMapFlags::empty()).expect("Mapping memory block failed!") };
+            Some(data_ptr as *mut u8)
+        } else {
+            None
+        };
+
+        MemoryBlock {
+            id,
+            memory,
+            size,
+            mapped_ptr,
+            strategy,
+            allocations: Vec::new(),
+            linear_offset: 0,
+        }
+    }
+
+    fn destroy_block(device: &Device, block: &MemoryBlock) {
+        unsafe {
+            if block.mapped_ptr.is_some() {
+                device.unmap_memory(block.memory);
+            }
+            device.free_memory(block.memory, None);
+        }
+    }
+
+    fn create_allocation(block: &MemoryBlock, offset: DeviceSize, memory_type_index: u32) -> Allocation {
+        Allocation {
+            memory: block.memory,
+            offset,
+            mapped_ptr: block.mapped_ptr.map(|block_ptr| unsafe { block_ptr.add(offset as usize) }),
+            memory_type_index,
+            strategy: block.strategy,
+            block_id: block.id,
+        }
+    }
+}
+
+fn align_up(value: DeviceSize, alignment: DeviceSize) -> DeviceSize {
+    value.div_ceil(alignment) * alignment
+}
+
+fn is_on_same_page(first: DeviceSize, second: DeviceSize, page_size: DeviceSize) -> bool {
+    first / page_size == second / page_size
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(size: DeviceSize, strategy: AllocationStrategy) -> MemoryBlock {
+        MemoryBlock {
+            id: 0,
+            memory: DeviceMemory::null(),
+            size,
+            mapped_ptr: None,
+            strategy,
+            allocations: Vec::new(),
+            linear_offset: 0,
+        }
+    }
+
+    fn request(size: DeviceSize, alignment: DeviceSize, kind: ResourceKind) -> PlacementRequest {
+        PlacementRequest { size, alignment, kind, granularity: 1024 }
+    }
+
+    #[test]
+    fn offsets_are_aligned() {
+        let mut block = block(4096, AllocationStrategy::FreeList);
+        assert_eq!(block.allocate(&request(10, 1, ResourceKind::Linear)), Some(0));
+        assert_eq!(block.allocate(&request(10, 256, ResourceKind::Linear)), Some(256));
+        assert_eq!(block.allocate(&request(10, 16, ResourceKind::Linear)), Some(16));
+        assert_eq!(block.allocate(&request(4096, 1, ResourceKind::Linear)), None);
+    }
+
+    #[test]
+    fn linear_and_optimal_resources_never_share_a_page() {
+        let mut block = block(4096, AllocationStrategy::FreeList);
+        assert_eq!(block.allocate(&request(100, 4, ResourceKind::Linear)), Some(0));
+        // Moved to the next page of the granularity after the buffer.
+        assert_eq!(block.allocate(&request(100, 4, ResourceKind::Optimal)), Some(1024));
+        // Another buffer fits right behind the first one, but not onto the page of the image.
+        assert_eq!(block.allocate(&request(100, 4, ResourceKind::Linear)), Some(100));
+        assert_eq!(block.allocate(&request(900, 4, ResourceKind::Linear)), Some(2048));
+        // The same kind of resource shares pages.
+        assert_eq!(block.allocate(&request(100, 4, ResourceKind::Optimal)), Some(1124));
+    }
+
+    #[test]
+    fn freed_neighbours_merge_into_one_gap() {
+        let mut block = block(300, AllocationStrategy::FreeList);
+        for offset in [0, 100, 200] {
+            assert_eq!(block.allocate(&request(100, 1, ResourceKind::Linear)), Some(offset));
+        }
+        block.free(0);
+        assert_eq!(block.allocate(&request(200, 1, ResourceKind::Linear)), None);
+        block.free(100);
+        assert_eq!(block.allocate(&request(200, 1, ResourceKind::Linear)), Some(0));
+    }
+
+    #[test]
+    fn linear_blocks_reset_once_empty() {
+        let mut block = block(300, AllocationStrategy::Linear);
+        assert_eq!(block.allocate(&request(100, 1, ResourceKind::Linear)), Some(0));
+        assert_eq!(block.allocate(&request(100, 1, ResourceKind::Linear)), Some(100));
+        // Freed space in front is not reused while the block holds allocations.
+        block.free(0);
+        assert_eq!(block.allocate(&request(150, 1, ResourceKind::Linear)), None);
+        block.free(100);
+        assert_eq!(block.allocate(&request(300, 1, ResourceKind::Linear)), Some(0));
+    }
+
+    #[test]
+    fn used_bytes_count_live_allocations() {
+        let mut block = block(4096, AllocationStrategy::FreeList);
+        block.allocate(&request(100, 1, ResourceKind::Linear));
+        block.allocate(&request(50, 1, ResourceKind::Optimal));
+        assert_eq!((block.allocations.len(), block.get_used_bytes()), (2, 150));
+        block.free(0);
+        assert_eq!((block.allocations.len(), block.get_used_bytes()), (1, 50));
+
+        let mut total = MemoryStatistics::default();
+        let statistics = MemoryStatistics { block_count: 1, allocation_count: 1, reserved_bytes: block.size, used_bytes: block.get_used_bytes() };
+        total.add(&statistics);
+        total.add(&statistics);
+        assert_eq!((total.block_count, total.allocation_count, total.reserved_bytes, total.used_bytes), (2, 2, 8192, 100));
+    }
+}
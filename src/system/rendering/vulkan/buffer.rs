@@ -0,0 +1,58 @@
+use std::mem;
+use ash::Device;
+use ash::util::Align;
+use ash::vk::{Buffer, BufferCreateInfo, BufferUsageFlags, DeviceSize, MemoryPropertyFlags, SharingMode};
+use crate::system::rendering::vulkan::allocator::{Allocation, AllocationStrategy, ResourceKind, VkAllocator};
+
+pub struct VkBuffer {
+    pub buffer: Buffer,
+    allocation: Allocation,
+    pub size: DeviceSize,
+}
+
+impl VkBuffer {
+    pub fn new(allocator: &mut VkAllocator, device: &Device, size: DeviceSize, usage: BufferUsageFlags, memory_flags: MemoryPropertyFlags, strategy: AllocationStrategy) -> Self {
+        let buffer_create_info = BufferCreateInfo::builder()
+            .size(size)
+            .usage(usage)
+            .sharing_mode(SharingMode::EXCLUSIVE);
+
+        let buffer = unsafe { device.create_buffer(&buffer_create_info, None).expect("Buffer creation failed!") };
+        let memory_requirements = unsafe { device.get_buffer_memory_requirements(buffer) };
+        let allocation = allocator.allocate(device, &memory_requirements, memory_flags, ResourceKind::Linear, strategy);
+        unsafe { device.bind_buffer_memory(buffer, allocation.memory, allocation.offset).expect("Unable to bind buffer memory!") };
+
+        VkBuffer {
+            buffer,
+            allocation,
+            size,
+        }
+    }
+
+    /// Copies the data into the buffer. The buffer memory must be host visible and coherent.
+    pub fn upload<T: Copy>(&self, data: &[T]) {
+        if data.is_empty() {
+            return;
+        }
+        let data_size = mem::size_of_val(data) as DeviceSize;
+        assert!(data_size <= self.size, "Data does not fit into the buffer!");
+
+        let data_ptr = self.allocation.mapped_ptr.expect("Buffer memory is not host visible!");
+        let mut slice = unsafe { Align::new(data_ptr as *mut _, mem::align_of::<T>() as DeviceSize, data_size) };
+        slice.copy_from_slice(data);
+    }
+
+    /// Copies the first `count` elements out of the buffer. The buffer memory must be host visible and coherent.
+    pub fn read<T: Copy>(&self, count: usize) -> Vec<T> {
+        let data_size = (mem::size_of::<T>() * count) as DeviceSize;
+        assert!(data_size <= self.size, "Requested more data than the buffer holds!");
+
+        let data_ptr = self.allocation.mapped_ptr.expect("Buffer memory is not host visible!");
+        unsafe { std::slice::from_raw_parts(data_ptr as *const T, count).to_vec() }
+    }
+
+    pub fn destroy(&self, device: &Device, allocator: &mut VkAllocator) {
+        unsafe { device.destroy_buffer(self.buffer, None) };
+        allocator.free(device, &self.allocation);
+    }
+}
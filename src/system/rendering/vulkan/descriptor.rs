@@ -0,0 +1,147 @@
+use ash::Device;
+use ash::vk::{DescriptorBufferInfo, DescriptorImageInfo, DescriptorPool, DescriptorPoolCreateInfo, DescriptorPoolSize, DescriptorSet, DescriptorSetAllocateInfo, DescriptorSetLayout, DescriptorSetLayoutBinding, DescriptorSetLayoutCreateInfo, DescriptorType, ImageLayout, ImageView, Result, Sampler, ShaderStageFlags, WriteDescriptorSet};
+use crate::system::rendering::vulkan::buffer::VkBuffer;
+
+/// Number of sets a single pool of the allocator holds before a new pool is created.
+const SETS_PER_POOL: u32 = 64;
+
+/// One entry of a descriptor set layout.
+#[derive(Clone, Copy)]
+pub struct DescriptorBinding {
+    pub binding: u32,
+    pub descriptor_type: DescriptorType,
+    pub count: u32,
+    pub stages: ShaderStageFlags,
+}
+
+pub struct VkDescriptorSetLayout {
+    pub layout: DescriptorSetLayout,
+    bindings: Vec<DescriptorBinding>,
+}
+
+impl VkDescriptorSetLayout {
+    pub fn new(device: &Device, bindings: &[DescriptorBinding]) -> Self {
+        let layout_bindings: Vec<DescriptorSetLayoutBinding> = bindings.iter()
+            .map(|binding| DescriptorSetLayoutBinding::builder()
+                .binding(binding.binding)
+                .descriptor_type(binding.descriptor_type)
+                .descriptor_count(binding.count)
+                .stage_flags(binding.stages)
+                .build())
+            .collect();
+
+        let create_info = DescriptorSetLayoutCreateInfo::builder().bindings(&layout_bindings);
+        let layout = unsafe { device.create_descriptor_set_layout(&create_info, None).expect("Descriptor set layout creation failed!") };
+
+        VkDescriptorSetLayout {
+            layout,
+            bindings: bindings.to_vec(),
+        }
+    }
+
+    pub fn destroy(&self, device: &Device) {
+        unsafe { device.destroy_descriptor_set_layout(self.layout, None) };
+    }
+}
+
+/// Hands out descriptor sets of any layout. Pools are created on demand, whenever the current one runs out of space.
+#[derive(Default)]
+pub struct VkDescriptorAllocator {
+    pools: Vec<DescriptorPool>,
+}
+
+impl VkDescriptorAllocator {
+    pub fn allocate(&mut self, device: &Device, layout: &VkDescriptorSetLayout) -> DescriptorSet {
+        if let Some(pool) = self.pools.last() {
+            match VkDescriptorAllocator::allocate_from_pool(device, *pool, layout) {
+                Ok(set) => return set,
+                Err(Result::ERROR_OUT_OF_POOL_MEMORY) | Err(Result::ERROR_FRAGMENTED_POOL) => {}
+                Err(e) => panic!("Descriptor set allocation failed: {}", e),
+            }
+        }
+
+        let pool = VkDescriptorAllocator::create_pool(device, layout);
+        self.pools.push(pool);
+        VkDescriptorAllocator::allocate_from_pool(device, pool, layout).expect("Descriptor set allocation failed!")
+    }
+
+    pub fn destroy(&self, device: &Device) {
+        unsafe {
+            for pool in self.pools.iter() {
+                device.destroy_descriptor_pool(*pool, None);
+            }
+        }
+    }
+
+    fn allocate_from_pool(device: &Device, pool: DescriptorPool, layout: &VkDescriptorSetLayout) -> ash::prelude::VkResult<DescriptorSet> {
+        let layouts = [layout.layout];
+        let allocate_info = DescriptorSetAllocateInfo::builder()
+            .descriptor_pool(pool)
+            .set_layouts(&layouts);
+        unsafe { device.allocate_descriptor_sets(&allocate_info).map(|sets| sets[0]) }
+    }
+
+    /// Creates a pool with room for SETS_PER_POOL sets of the given layout.
+    /// Sets of other layouts may fit in as well; if they don't, the next pool is sized for them.
+    fn create_pool(device: &Device, layout: &VkDescriptorSetLayout) -> DescriptorPool {
+        let mut pool_sizes: Vec<DescriptorPoolSize> = Vec::new();
+        for binding in layout.bindings.iter() {
+            match pool_sizes.iter_mut().find(|size| size.ty == binding.descriptor_type) {
+                Some(size) => size.descriptor_count += binding.count * SETS_PER_POOL,
+                None => pool_sizes.push(DescriptorPoolSize { ty: binding.descriptor_type, descriptor_count: binding.count * SETS_PER_POOL }),
+            }
+        }
+
+        let create_info = DescriptorPoolCreateInfo::builder()
+            .max_sets(SETS_PER_POOL)
+            .pool_sizes(&pool_sizes);
+        unsafe { device.create_descriptor_pool(&create_info, None).expect("Descriptor pool creation failed!") }
+    }
+}
+
+/// Points the given binding of the set at the whole buffer.
+pub fn write_uniform_buffer(device: &Device, set: DescriptorSet, binding: u32, buffer: &VkBuffer) {
+    let buffer_infos = [DescriptorBufferInfo {
+        buffer: buffer.buffer,
+        offset: 0,
+        range: buffer.size,
+    }];
+    let write = WriteDescriptorSet::builder()
+        .dst_set(set)
+        .dst_binding(binding)
+        .descriptor_type(DescriptorType::UNIFORM_BUFFER)
+        .buffer_info(&buffer_infos);
+    unsafe { device.update_descriptor_sets(&[write.build()], &[]) };
+}
+
+/// Points the given binding of the set at the sampler.
+pub fn write_sampler(device: &Device, set: DescriptorSet, binding: u32, sampler: Sampler) {
+    let image_infos = [DescriptorImageInfo {
+        sampler,
+        image_view: ImageView::null(),
+        image_layout: ImageLayout::UNDEFINED,
+    }];
+    let write = WriteDescriptorSet::builder()
+        .dst_set(set)
+        .dst_binding(binding)
+        .descriptor_type(DescriptorType::SAMPLER)
+        .image_info(&image_infos);
+    unsafe { device.update_descriptor_sets(&[write.build()], &[]) };
+}
+
+/// Points the elements of a sampled image array at the image views, which have to be in shader read only layout.
+pub fn write_sampled_images(device: &Device, set: DescriptorSet, binding: u32, image_views: &[ImageView]) {
+    let image_infos: Vec<DescriptorImageInfo> = image_views.iter()
+        .map(|image_view| DescriptorImageInfo {
+            sampler: Sampler::null(),
+            image_view: *image_view,
+            image_layout: ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        })
+        .collect();
+    let write = WriteDescriptorSet::builder()
+        .dst_set(set)
+        .dst_binding(binding)
+        .descriptor_type(DescriptorType::SAMPLED_IMAGE)
+        .image_info(&image_infos);
+    unsafe { device.update_descriptor_sets(&[write.build()], &[]) };
+}
@@ -0,0 +1,83 @@
+use std::mem;
+use ash::Device;
+use ash::vk::{BufferUsageFlags, CommandBuffer, CommandBufferAllocateInfo, CommandBufferLevel, CommandPool, DescriptorSet, DeviceSize, Fence, FenceCreateFlags, FenceCreateInfo, MemoryPropertyFlags, Semaphore, SemaphoreCreateInfo};
+use crate::system::rendering::camera_uniform::CameraUniform;
+use crate::system::rendering::vulkan::allocator::{AllocationStrategy, VkAllocator};
+use crate::system::rendering::vulkan::buffer::VkBuffer;
+use crate::system::rendering::vulkan::descriptor::{VkDescriptorAllocator, VkDescriptorSetLayout, write_uniform_buffer};
+
+/// Binding of the camera uniform buffer inside the camera descriptor set.
+pub const CAMERA_BINDING: u32 = 0;
+
+/// Buffers and the material descriptor set of one draw call.
+pub struct MeshBuffers {
+    pub vertex_buffer: VkBuffer,
+    pub index_buffer: VkBuffer,
+    /// Number of indices uploaded this frame, those of the level of detail the mesh is drawn with.
+    pub index_count: u32,
+    pub material_buffer: VkBuffer,
+    pub material_descriptor_set: DescriptorSet,
+}
+
+/// Everything a single frame in flight owns. While the gpu still works on this frame,
+/// the cpu records the next one into a different VkFrame, so none of these resources may be shared between frames.
+pub struct VkFrame {
+    pub command_buffer: CommandBuffer,
+    pub in_flight_fence: Fence,
+    pub image_available_semaphore: Semaphore,
+    pub render_finished_semaphore: Semaphore,
+    pub mesh_buffers: Vec<MeshBuffers>,
+    pub camera_buffer: VkBuffer,
+    pub camera_descriptor_set: DescriptorSet,
+}
+
+impl VkFrame {
+    pub fn new(allocator: &mut VkAllocator, device: &Device, command_pool: &CommandPool,
+               descriptor_allocator: &mut VkDescriptorAllocator, camera_set_layout: &VkDescriptorSetLayout) -> Self {
+        let command_buffer_allocate_info = CommandBufferAllocateInfo::builder()
+            .command_buffer_count(1)
+            .command_pool(*command_pool)
+            .level(CommandBufferLevel::PRIMARY);
+
+        // The fence starts signaled, so waiting for a frame that has never been submitted returns immediately.
+        let fence_create_info = FenceCreateInfo::builder().flags(FenceCreateFlags::SIGNALED);
+        let semaphore_create_info = SemaphoreCreateInfo::default();
+
+        // The camera buffers of all frames are created together and live as long as the renderer, so they are packed linearly.
+        let camera_buffer = VkBuffer::new(allocator, device, mem::size_of::<CameraUniform>() as DeviceSize, BufferUsageFlags::UNIFORM_BUFFER,
+                                          MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT, AllocationStrategy::Linear);
+        let camera_descriptor_set = descriptor_allocator.allocate(device, camera_set_layout);
+        write_uniform_buffer(device, camera_descriptor_set, CAMERA_BINDING, &camera_buffer);
+
+        unsafe {
+            VkFrame {
+                command_buffer: device.allocate_command_buffers(&command_buffer_allocate_info).expect("Command buffer allocation failed!")[0],
+                in_flight_fence: device.create_fence(&fence_create_info, None).expect("Create fence failed!"),
+                image_available_semaphore: device.create_semaphore(&semaphore_create_info, None).expect("Semaphore creation failed!"),
+                render_finished_semaphore: device.create_semaphore(&semaphore_create_info, None).expect("Semaphore creation failed!"),
+                mesh_buffers: Vec::new(),
+                camera_buffer,
+                camera_descriptor_set,
+            }
+        }
+    }
+
+    pub fn wait(&self, device: &Device) {
+        unsafe { device.wait_for_fences(&[self.in_flight_fence], true, u64::MAX).expect("Wait for fence failed!") };
+    }
+
+    /// Destroys all resources of the frame. Command buffer and descriptor sets are freed together with their pools.
+    pub fn destroy(&self, device: &Device, allocator: &mut VkAllocator) {
+        self.camera_buffer.destroy(device, allocator);
+        for buffers in self.mesh_buffers.iter() {
+            buffers.vertex_buffer.destroy(device, allocator);
+            buffers.index_buffer.destroy(device, allocator);
+            buffers.material_buffer.destroy(device, allocator);
+        }
+        unsafe {
+            device.destroy_semaphore(self.image_available_semaphore, None);
+            device.destroy_semaphore(self.render_finished_semaphore, None);
+            device.destroy_fence(self.in_flight_fence, None);
+        }
+    }
+}
@@ -4,39 +4,78 @@ use std::os::raw::c_char;
 use ash::{Entry, Instance, Device};
 use ash::extensions::ext::DebugUtils;
 use ash::extensions::khr::Surface;
-use ash::vk::{API_VERSION_1_3, ApplicationInfo, DeviceCreateInfo, DeviceQueueCreateInfo, ExtensionProperties, InstanceCreateInfo, make_api_version, PhysicalDevice, PhysicalDeviceFeatures, PhysicalDeviceType, Queue, QueueFamilyProperties, QueueFlags, SurfaceKHR, TRUE};
+use ash::vk::{API_VERSION_1_3, ApplicationInfo, DeviceCreateInfo, DeviceQueueCreateInfo, DeviceSize, ExtensionProperties, InstanceCreateInfo, make_api_version, MemoryPropertyFlags, MemoryRequirements, PhysicalDevice, PhysicalDeviceFeatures, PhysicalDeviceMemoryProperties, PhysicalDeviceType, Queue, QueueFamilyProperties, QueueFlags, SurfaceKHR, TRUE, UUID_SIZE};
 use ash_window::{create_surface, enumerate_required_extensions};
 use winit::window::Window;
 use crate::system::log::Log;
 
 pub struct VkInstance {
+    // The entry owns the loaded vulkan library and must outlive the instance.
+    _entry: Entry,
     pub instance: Instance,
-    pub surface_handle: SurfaceKHR,
-    pub surface: Surface,
+    pub surface: Option<VkSurface>,
     pub physical_devices: Vec<VkPhysicalDevice>,
     pub selected_physical_device: VkPhysicalDevice,
 }
 
+pub struct VkSurface {
+    pub loader: Surface,
+    pub handle: SurfaceKHR,
+}
+
 #[derive(Clone)]
 pub struct VkPhysicalDevice {
     pub physical_device: PhysicalDevice,
     pub name: String,
+    pub vendor_id: u32,
+    pub device_id: u32,
+    /// Identifies the driver build, pipeline caches are only valid for the same uuid.
+    pub pipeline_cache_uuid: [u8; UUID_SIZE],
+    /// Granularity in bytes at which buffers and optimal images in the same memory may not alias.
+    pub buffer_image_granularity: DeviceSize,
+    /// Highest anisotropy samplers may use, only relevant if the sampler_anisotropy feature is supported.
+    pub max_sampler_anisotropy: f32,
     device_type: PhysicalDeviceType,
     extensions: Vec<ExtensionProperties>,
-    features: PhysicalDeviceFeatures,
+    pub features: PhysicalDeviceFeatures,
+    pub memory_properties: PhysicalDeviceMemoryProperties,
     pub graphics_queue_family_index: u32,
     pub compute_queue_family_index: u32,
 }
 
+impl VkPhysicalDevice {
+    pub fn find_memory_type_index(&self, memory_requirements: &MemoryRequirements, flags: MemoryPropertyFlags) -> Option<u32> {
+        self.memory_properties.memory_types[..self.memory_properties.memory_type_count as usize]
+            .iter()
+            .enumerate()
+            .find(|(index, memory_type)| {
+                (1 << index) & memory_requirements.memory_type_bits != 0
+                    && memory_type.property_flags & flags == flags
+            })
+            .map(|(index, _memory_type)| index as u32)
+    }
+
+    pub fn supports_extension(&self, extension_name: &CStr) -> bool {
+        self.extensions.iter().any(|extension| {
+            let name = unsafe { CStr::from_ptr(extension.extension_name.as_ptr()) };
+            name == extension_name
+        })
+    }
+}
+
 impl VkInstance {
-    pub fn new(window: &Window, gpu_name: Option<String>) -> Result<Self, Error> {
+    /// Creates the vulkan instance. If no window is given, no surface is created and the instance runs headless.
+    pub fn new(window: Option<&Window>, gpu_name: Option<String>) -> Result<Self, Error> {
         let entry = VkInstance::create_entry();
         let instance = VkInstance::create_instance(&entry, window);
-        let surface_handle = unsafe { create_surface(&entry, &instance, window, None) }.unwrap();
-        let surface = Surface::new(&entry, &instance);
+        let surface = window.map(|window| {
+            let handle = unsafe { create_surface(&entry, &instance, window, None) }.unwrap();
+            let loader = Surface::new(&entry, &instance);
+            VkSurface { loader, handle }
+        });
 
 
-        let physical_devices_result = VkInstance::get_physical_devices(&instance, &surface, surface_handle);
+        let physical_devices_result = VkInstance::get_physical_devices(&instance, surface.as_ref());
         if physical_devices_result.is_err() {
             Err("Creation of vk instance failed!").unwrap()
         }
@@ -50,8 +89,8 @@ impl VkInstance {
         let selected_physical_device = selected.clone();
 
         Ok(VkInstance {
+            _entry: entry,
             instance,
-            surface_handle,
             surface,
             physical_devices,
             selected_physical_device,
@@ -60,26 +99,43 @@ impl VkInstance {
 
     pub fn destroy(&self) {
         unsafe {
-            self.surface.destroy_surface(self.surface_handle, None);
+            if let Some(surface) = &self.surface {
+                surface.loader.destroy_surface(surface.handle, None);
+            }
             self.instance.destroy_instance(None)
         };
     }
 
+    /// Checks if a vulkan loader is installed on this machine.
+    pub fn is_vulkan_available() -> bool {
+        let entry = unsafe { Entry::load() };
+        entry.is_ok()
+    }
+
     fn create_entry() -> Entry {
         let entry = unsafe { Entry::load().expect("Failed to load vulkan dll...") };
         entry
     }
 
-    fn create_instance(entry: &Entry, window: &Window) -> Instance {
+    fn create_instance(entry: &Entry, window: Option<&Window>) -> Instance {
         let mut instance;
         unsafe {
-            let mut required_extensions = enumerate_required_extensions(window).unwrap().to_vec();
-            required_extensions.push(DebugUtils::name().as_ptr());
+            let mut required_extensions = match window {
+                Some(window) => enumerate_required_extensions(window).unwrap().to_vec(),
+                None => Vec::new(),
+            };
+            if VkInstance::is_instance_extension_available(entry, DebugUtils::name()) {
+                required_extensions.push(DebugUtils::name().as_ptr());
+            }
 
-            let layers = [
-                CStr::from_bytes_with_nul_unchecked(b"VK_LAYER_KHRONOS_validation\0"),
-            ];
-            let mut required_layers: Vec<*const c_char> = layers.iter().map(|raw_name| raw_name.as_ptr()).collect();
+            // Validation layers are optional, machines without the vulkan sdk (e.g. ci runners) do not ship them.
+            let validation_layer = CStr::from_bytes_with_nul_unchecked(b"VK_LAYER_KHRONOS_validation\0");
+            let mut required_layers: Vec<*const c_char> = Vec::new();
+            if VkInstance::is_layer_available(entry, validation_layer) {
+                required_layers.push(validation_layer.as_ptr());
+            } else {
+                Log::get().write_warning("Vulkan validation layer is not available!");
+            }
 
             let application_info = ApplicationInfo::builder()
                 .application_name(CStr::from_bytes_with_nul_unchecked(b"RESA"))
@@ -98,7 +154,23 @@ impl VkInstance {
         instance
     }
 
-    fn get_physical_devices(instance: &Instance, presentation_surface: &Surface, surface_handle: SurfaceKHR) -> Result<Vec<VkPhysicalDevice>, Error> {
+    fn is_layer_available(entry: &Entry, layer_name: &CStr) -> bool {
+        let layers = entry.enumerate_instance_layer_properties().unwrap_or_default();
+        layers.iter().any(|layer| {
+            let name = unsafe { CStr::from_ptr(layer.layer_name.as_ptr()) };
+            name == layer_name
+        })
+    }
+
+    fn is_instance_extension_available(entry: &Entry, extension_name: &CStr) -> bool {
+        let extensions = entry.enumerate_instance_extension_properties(None).unwrap_or_default();
+        extensions.iter().any(|extension| {
+            let name = unsafe { CStr::from_ptr(extension.extension_name.as_ptr()) };
+            name == extension_name
+        })
+    }
+
+    fn get_physical_devices(instance: &Instance, surface: Option<&VkSurface>) -> Result<Vec<VkPhysicalDevice>, Error> {
         let physical_devices = unsafe { instance.enumerate_physical_devices().expect("Failed to load physical devices!") };
         if physical_devices.iter().count() == 0 {
             Error::default();
@@ -111,20 +183,32 @@ impl VkInstance {
                 let device_properties = instance.get_physical_device_properties(physical_device);
                 let name = CStr::from_ptr(device_properties.device_name.as_ptr()).to_str().unwrap().to_owned();
                 let device_type = device_properties.device_type;
+                let vendor_id = device_properties.vendor_id;
+                let device_id = device_properties.device_id;
+                let pipeline_cache_uuid = device_properties.pipeline_cache_uuid;
+                let buffer_image_granularity = device_properties.limits.buffer_image_granularity;
+                let max_sampler_anisotropy = device_properties.limits.max_sampler_anisotropy;
                 let extensions = instance.enumerate_device_extension_properties(physical_device).expect("Could not enumerate physical device extensions...");
                 let features = instance.get_physical_device_features(physical_device);
+                let memory_properties = instance.get_physical_device_memory_properties(physical_device);
 
                 let queue_family_properties = instance.get_physical_device_queue_family_properties(physical_device);
-                let graphics_queue_family_index: u32 = VkInstance::find_best_graphics_queue_index(&physical_device, presentation_surface, surface_handle, &queue_family_properties).unwrap();
+                let graphics_queue_family_index: u32 = VkInstance::find_best_graphics_queue_index(&physical_device, surface, &queue_family_properties).unwrap();
                 let compute_queue_family_index = VkInstance::find_best_compute_queue_index(&queue_family_properties).unwrap();
 
                 vk_physical_devices.push(
                     VkPhysicalDevice {
                         physical_device,
                         name,
+                        vendor_id,
+                        device_id,
+                        pipeline_cache_uuid,
+                        buffer_image_granularity,
+                        max_sampler_anisotropy,
                         device_type,
                         extensions,
                         features,
+                        memory_properties,
                         graphics_queue_family_index,
                         compute_queue_family_index,
                     });
@@ -133,14 +217,17 @@ impl VkInstance {
         Ok(vk_physical_devices)
     }
 
-    fn find_best_graphics_queue_index(physical_device: &PhysicalDevice, presentation_surface: &Surface, surface_handle: SurfaceKHR, queue_family_properties: &Vec<QueueFamilyProperties>) -> Result<u32, Error> {
+    fn find_best_graphics_queue_index(physical_device: &PhysicalDevice, surface: Option<&VkSurface>, queue_family_properties: &Vec<QueueFamilyProperties>) -> Result<u32, Error> {
         for (i, queue_family) in queue_family_properties.iter().enumerate() {
             let is_graphics_queue = queue_family.queue_flags.contains(QueueFlags::GRAPHICS);
-            let presentation_surface_supported = unsafe {
-                presentation_surface.get_physical_device_surface_support(
-                    physical_device.clone(),
-                    i as u32,
-                    surface_handle).unwrap()
+            let presentation_surface_supported = match surface {
+                Some(surface) => unsafe {
+                    surface.loader.get_physical_device_surface_support(
+                        *physical_device,
+                        i as u32,
+                        surface.handle).unwrap()
+                },
+                None => true,
             };
 
             if !is_graphics_queue || !presentation_surface_supported {
@@ -189,17 +276,21 @@ impl VkInstance {
                          requested_features: &PhysicalDeviceFeatures,
                          requested_extensions: &Vec<*const c_char>/*[*const c_char; 2]*/) -> Device {
         let priorities = [1.0];
-        let graphics_queue_info = [DeviceQueueCreateInfo::builder()
-            .queue_family_index(self.selected_physical_device.graphics_queue_family_index)
-            .queue_priorities(&priorities).build()];
+        let graphics_queue_family_index = self.selected_physical_device.graphics_queue_family_index;
+        let compute_queue_family_index = self.selected_physical_device.compute_queue_family_index;
 
-        let compute_queue_info = [DeviceQueueCreateInfo::builder()
-            .queue_family_index(self.selected_physical_device.compute_queue_family_index)
+        // Each queue family may only be requested once, so the compute queue is only added if it lives in its own family.
+        let mut queue_infos = vec![DeviceQueueCreateInfo::builder()
+            .queue_family_index(graphics_queue_family_index)
             .queue_priorities(&priorities).build()];
+        if compute_queue_family_index != graphics_queue_family_index {
+            queue_infos.push(DeviceQueueCreateInfo::builder()
+                .queue_family_index(compute_queue_family_index)
+                .queue_priorities(&priorities).build());
+        }
 
         let create_info = DeviceCreateInfo::builder()
-            .queue_create_infos(&graphics_queue_info)
-            .queue_create_infos(&compute_queue_info)
+            .queue_create_infos(&queue_infos)
             .enabled_features(requested_features)
             .enabled_extension_names(requested_extensions);
 
@@ -0,0 +1,61 @@
+use ash::Device;
+use ash::vk::{Extent2D, Format, Image, ImageAspectFlags, ImageCreateInfo, ImageLayout, ImageSubresourceRange, ImageTiling, ImageType, ImageUsageFlags, ImageView, ImageViewCreateInfo, ImageViewType, MemoryPropertyFlags, SampleCountFlags, SharingMode};
+use crate::system::rendering::vulkan::allocator::{Allocation, AllocationStrategy, ResourceKind, VkAllocator};
+
+pub struct VkImage {
+    pub image: Image,
+    allocation: Allocation,
+    pub image_view: ImageView,
+}
+
+impl VkImage {
+    pub fn new(allocator: &mut VkAllocator, device: &Device, extent: Extent2D, format: Format, usage: ImageUsageFlags, aspect: ImageAspectFlags) -> Self {
+        VkImage::new_mipmapped(allocator, device, extent, format, usage, aspect, 1)
+    }
+
+    /// Creates an image with the given number of mip levels, the view covers all of them.
+    pub fn new_mipmapped(allocator: &mut VkAllocator, device: &Device, extent: Extent2D, format: Format, usage: ImageUsageFlags, aspect: ImageAspectFlags,
+                         mip_levels: u32) -> Self {
+        let image_create_info = ImageCreateInfo::builder()
+            .image_type(ImageType::TYPE_2D)
+            .format(format)
+            .extent(extent.into())
+            .mip_levels(mip_levels)
+            .array_layers(1)
+            .samples(SampleCountFlags::TYPE_1)
+            .tiling(ImageTiling::OPTIMAL)
+            .usage(usage)
+            .sharing_mode(SharingMode::EXCLUSIVE)
+            .initial_layout(ImageLayout::UNDEFINED);
+
+        let image = unsafe { device.create_image(&image_create_info, None).expect("Image creation failed!") };
+        let memory_requirements = unsafe { device.get_image_memory_requirements(image) };
+        let allocation = allocator.allocate(device, &memory_requirements, MemoryPropertyFlags::DEVICE_LOCAL, ResourceKind::Optimal, AllocationStrategy::FreeList);
+        unsafe { device.bind_image_memory(image, allocation.memory, allocation.offset).expect("Unable to bind image memory!") };
+
+        let image_view_info = ImageViewCreateInfo::builder()
+            .subresource_range(ImageSubresourceRange::builder()
+                .aspect_mask(aspect)
+                .level_count(mip_levels)
+                .layer_count(1)
+                .build())
+            .image(image)
+            .format(format)
+            .view_type(ImageViewType::TYPE_2D);
+        let image_view = unsafe { device.create_image_view(&image_view_info, None).expect("Image view creation failed!") };
+
+        VkImage {
+            image,
+            allocation,
+            image_view,
+        }
+    }
+
+    pub fn destroy(&self, device: &Device, allocator: &mut VkAllocator) {
+        unsafe {
+            device.destroy_image_view(self.image_view, None);
+            device.destroy_image(self.image, None);
+        }
+        allocator.free(device, &self.allocation);
+    }
+}
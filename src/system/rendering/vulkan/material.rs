@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use ash::Device;
+use ash::vk::{CommandPool, DescriptorSet, DescriptorType, ShaderStageFlags};
+use crate::system::assets::manager::{AssetManager, AssetState, Handle};
+use crate::system::assets::texture::TextureImage;
+use crate::system::ini::RenderingIniData;
+use crate::system::log::Log;
+use crate::system::rendering::material::MaterialInstance;
+use crate::system::rendering::render_list::RenderList;
+use crate::system::rendering::vulkan::allocator::VkAllocator;
+use crate::system::rendering::vulkan::buffer::VkBuffer;
+use crate::system::rendering::vulkan::descriptor::{DescriptorBinding, VkDescriptorSetLayout, write_sampled_images, write_sampler, write_uniform_buffer};
+use crate::system::rendering::vulkan::device::VkLogicalDevice;
+use crate::system::rendering::vulkan::global::VkInstance;
+use crate::system::rendering::vulkan::sampler::VkSampler;
+use crate::system::rendering::vulkan::texture::VkTexture;
+
+/// Index of the material descriptor set, the camera set comes first.
+pub const MATERIAL_SET: u32 = 1;
+/// Binding of the material parameter uniform buffer inside the material descriptor set.
+pub const MATERIAL_PARAMETER_BINDING: u32 = 0;
+/// Binding of the sampler all textures of the material descriptor set are read with.
+pub const MATERIAL_SAMPLER_BINDING: u32 = 1;
+/// Binding of the texture array inside the material descriptor set. Element i holds texture slot i of the material.
+pub const MATERIAL_TEXTURE_BINDING: u32 = 2;
+/// Length of the texture array, further slots of a material are not bound.
+pub const MAX_MATERIAL_TEXTURES: usize = 8;
+
+/// Texture of a material slot. It is decoded by the asset manager and uploaded once it is ready.
+enum TextureEntry {
+    Loading(Handle<TextureImage>),
+    /// The decoded image is released after the upload.
+    Uploaded(VkTexture),
+    Failed,
+}
+
+/// Everything materials need on the gpu: the layout of their descriptor set, the sampler and the textures loaded so far.
+pub struct VkMaterialResources {
+    pub set_layout: VkDescriptorSetLayout,
+    /// Used for meshes without a material.
+    pub default_material: Arc<MaterialInstance>,
+    sampler: VkSampler,
+    /// Bound to empty slots and in place of textures that are loading or failed to load.
+    white_texture: VkTexture,
+    textures: HashMap<PathBuf, TextureEntry>,
+    generate_mipmaps: bool,
+    assets: Arc<AssetManager>,
+}
+
+impl VkMaterialResources {
+    pub fn new(instance: &VkInstance, device: &VkLogicalDevice, allocator: &mut VkAllocator, command_pool: &CommandPool, settings: &RenderingIniData,
+               assets: Arc<AssetManager>) -> Self {
+        let set_layout = VkDescriptorSetLayout::new(&device.device, &[
+            DescriptorBinding {
+                binding: MATERIAL_PARAMETER_BINDING,
+                descriptor_type: DescriptorType::UNIFORM_BUFFER,
+                count: 1,
+                stages: ShaderStageFlags::VERTEX | ShaderStageFlags::FRAGMENT,
+            },
+            DescriptorBinding {
+                binding: MATERIAL_SAMPLER_BINDING,
+                descriptor_type: DescriptorType::SAMPLER,
+                count: 1,
+                stages: ShaderStageFlags::FRAGMENT,
+            },
+            DescriptorBinding {
+                binding: MATERIAL_TEXTURE_BINDING,
+                descriptor_type: DescriptorType::SAMPLED_IMAGE,
+                count: MAX_MATERIAL_TEXTURES as u32,
+                stages: ShaderStageFlags::FRAGMENT,
+            },
+        ]);
+
+        let physical_device = &instance.selected_physical_device;
+        let max_anisotropy = if device.enabled_features.sampler_anisotropy != 0 { physical_device.max_sampler_anisotropy } else { 0.0 };
+        let sampler = VkSampler::new(&device.device, settings, max_anisotropy);
+        let generate_mipmaps = VkTexture::supports_mipmaps(&instance.instance, physical_device.physical_device);
+        if !generate_mipmaps {
+            Log::get().write_warning("The device can not blit textures with a linear filter, textures have no mip levels");
+        }
+        let white_texture = VkTexture::new(allocator, &device.device, command_pool, device.graphics_queue, &TextureImage::white(), false);
+
+        VkMaterialResources {
+            set_layout,
+            default_material: Arc::new(MaterialInstance::fallback()),
+            sampler,
+            white_texture,
+            textures: HashMap::new(),
+            generate_mipmaps,
+            assets,
+        }
+    }
+
+    /// Requests every texture the materials of the render items use for the first time from the asset manager
+    /// and uploads the textures that finished decoding since the last frame. Uploads wait for the gpu.
+    pub fn update_textures(&mut self, allocator: &mut VkAllocator, device: &VkLogicalDevice, command_pool: &CommandPool, render_list: &RenderList) {
+        let paths = render_list.items.iter()
+            .filter_map(|item| item.material.as_ref())
+            .flat_map(|material| material.get_textures().iter().take(MAX_MATERIAL_TEXTURES))
+            .flatten();
+        for path in paths {
+            if !self.textures.contains_key(path) {
+                self.textures.insert(path.clone(), TextureEntry::Loading(self.assets.load(path)));
+            }
+        }
+
+        for (path, entry) in self.textures.iter_mut() {
+            let state = match entry {
+                TextureEntry::Loading(handle) => handle.get_state(),
+                _ => continue,
+            };
+            match state {
+                AssetState::Loading => (),
+                AssetState::Ready(image) => {
+                    let texture = VkTexture::new(allocator, &device.device, command_pool, device.graphics_queue, &image, self.generate_mipmaps);
+                    Log::get().write(format!("Uploaded texture {} with {}x{} pixels and {} mip levels", path.display(), image.width, image.height, texture.mip_levels).as_str());
+                    *entry = TextureEntry::Uploaded(texture);
+                }
+                AssetState::Failed(message) => {
+                    Log::get().write_error(format!("Loading texture failed, it is drawn white: {}", message).as_str());
+                    *entry = TextureEntry::Failed;
+                }
+            }
+        }
+    }
+
+    /// Points the material set at the parameter buffer and at the textures of the material.
+    /// Slots without an uploaded texture are bound to a white texture.
+    pub fn write_descriptor_set(&self, device: &Device, set: DescriptorSet, parameter_buffer: &VkBuffer, material: &MaterialInstance) {
+        write_uniform_buffer(device, set, MATERIAL_PARAMETER_BINDING, parameter_buffer);
+        write_sampler(device, set, MATERIAL_SAMPLER_BINDING, self.sampler.sampler);
+
+        let mut image_views = vec![self.white_texture.image.image_view; MAX_MATERIAL_TEXTURES];
+        for (image_view, path) in image_views.iter_mut().zip(material.get_textures().iter()) {
+            if let Some(TextureEntry::Uploaded(texture)) = path.as_ref().and_then(|path| self.textures.get(path)) {
+                *image_view = texture.image.image_view;
+            }
+        }
+        write_sampled_images(device, set, MATERIAL_TEXTURE_BINDING, &image_views);
+    }
+
+    pub fn destroy(&self, device: &Device, allocator: &mut VkAllocator) {
+        for entry in self.textures.values() {
+            if let TextureEntry::Uploaded(texture) = entry {
+                texture.destroy(device, allocator);
+            }
+        }
+        self.white_texture.destroy(device, allocator);
+        self.sampler.destroy(device);
+        self.set_layout.destroy(device);
+    }
+}
@@ -1,3 +1,17 @@
 pub mod renderer;
 mod global;
-mod device;
\ No newline at end of file
+mod device;
+mod swapchain;
+mod offscreen;
+mod frame;
+mod descriptor;
+mod shader;
+mod pipeline;
+mod pipeline_description;
+mod pipeline_cache;
+mod allocator;
+mod buffer;
+mod image;
+mod texture;
+mod sampler;
+mod material;
\ No newline at end of file
@@ -0,0 +1,83 @@
+use ash::Device;
+use ash::vk::{AccessFlags, BufferImageCopy, BufferMemoryBarrier, BufferUsageFlags, CommandBuffer, DependencyFlags, Extent2D, Format, ImageAspectFlags, ImageLayout, ImageSubresourceLayers, ImageUsageFlags, MemoryPropertyFlags, Offset3D, PipelineStageFlags, WHOLE_SIZE};
+use crate::system::rendering::render_image::RenderImage;
+use crate::system::rendering::vulkan::allocator::{AllocationStrategy, VkAllocator};
+use crate::system::rendering::vulkan::buffer::VkBuffer;
+use crate::system::rendering::vulkan::image::VkImage;
+
+pub const OFFSCREEN_COLOR_FORMAT: Format = Format::R8G8B8A8_UNORM;
+
+/// Render target used instead of a swapchain when the renderer runs without a window.
+/// Every frame is copied into a host visible buffer so it can be read back to the cpu.
+pub struct VkOffscreenTarget {
+    pub color_image: VkImage,
+    readback_buffer: VkBuffer,
+    pub extent: Extent2D,
+}
+
+impl VkOffscreenTarget {
+    pub fn new(allocator: &mut VkAllocator, device: &Device, extent: Extent2D) -> Self {
+        let color_image = VkImage::new(allocator, device, extent, OFFSCREEN_COLOR_FORMAT,
+                                       ImageUsageFlags::COLOR_ATTACHMENT | ImageUsageFlags::TRANSFER_SRC, ImageAspectFlags::COLOR);
+        let buffer_size = (extent.width * extent.height * 4) as u64;
+        let readback_buffer = VkBuffer::new(allocator, device, buffer_size, BufferUsageFlags::TRANSFER_DST,
+                                            MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT, AllocationStrategy::FreeList);
+
+        VkOffscreenTarget {
+            color_image,
+            readback_buffer,
+            extent,
+        }
+    }
+
+    /// Records the copy of the color image into the readback buffer.
+    /// The color image must be in the TRANSFER_SRC_OPTIMAL layout when the copy executes.
+    pub fn record_readback(&self, device: &Device, command_buffer: CommandBuffer) {
+        let region = BufferImageCopy {
+            buffer_offset: 0,
+            buffer_row_length: 0,
+            buffer_image_height: 0,
+            image_subresource: ImageSubresourceLayers {
+                aspect_mask: ImageAspectFlags::COLOR,
+                mip_level: 0,
+                base_array_layer: 0,
+                layer_count: 1,
+            },
+            image_offset: Offset3D { x: 0, y: 0, z: 0 },
+            image_extent: self.extent.into(),
+        };
+
+        let host_read_barrier = BufferMemoryBarrier::builder()
+            .src_access_mask(AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(AccessFlags::HOST_READ)
+            .buffer(self.readback_buffer.buffer)
+            .offset(0)
+            .size(WHOLE_SIZE);
+
+        unsafe {
+            device.cmd_copy_image_to_buffer(command_buffer, self.color_image.image, ImageLayout::TRANSFER_SRC_OPTIMAL,
+                                            self.readback_buffer.buffer, &[region]);
+            device.cmd_pipeline_barrier(command_buffer,
+                                        PipelineStageFlags::TRANSFER,
+                                        PipelineStageFlags::HOST,
+                                        DependencyFlags::empty(),
+                                        &[], &[host_read_barrier.build()], &[]);
+        }
+    }
+
+    /// Reads the last copied frame. The caller has to make sure the copy has finished on the gpu.
+    pub fn read_pixels(&self) -> RenderImage {
+        let pixel_count = (self.extent.width * self.extent.height * 4) as usize;
+        let pixels = self.readback_buffer.read::<u8>(pixel_count);
+        RenderImage {
+            width: self.extent.width,
+            height: self.extent.height,
+            pixels,
+        }
+    }
+
+    pub fn destroy(&self, device: &Device, allocator: &mut VkAllocator) {
+        self.readback_buffer.destroy(device, allocator);
+        self.color_image.destroy(device, allocator);
+    }
+}
@@ -1,128 +1,97 @@
-use std::ffi::CStr;
-use std::io::Cursor;
-use std::mem;
+use std::ffi::{CStr, OsStr};
 use std::default::Default;
+use std::path::Path;
 use ash::{Device};
-use ash::util::read_spv;
-use ash::vk::{BlendFactor, BlendOp, ColorComponentFlags, CompareOp, DescriptorSetLayout, DynamicState, Extent2D, Format, FrontFace, GraphicsPipelineCreateInfo, LogicOp, Pipeline, PipelineCache, PipelineColorBlendAttachmentState, PipelineColorBlendStateCreateInfo, PipelineDepthStencilStateCreateInfo, PipelineDynamicStateCreateInfo, PipelineInputAssemblyStateCreateInfo, PipelineLayout, PipelineLayoutCreateInfo, PipelineMultisampleStateCreateInfo, PipelineRasterizationStateCreateInfo, PipelineShaderStageCreateInfo, PipelineVertexInputStateCreateInfo, PipelineViewportStateCreateInfo, PolygonMode, PrimitiveTopology, RenderPass, SampleCountFlags, ShaderModule, ShaderModuleCreateInfo, ShaderStageFlags, StencilOp, StencilOpState, VertexInputAttributeDescription, VertexInputBindingDescription, VertexInputRate, Viewport};
-use bytemuck::offset_of;
-use crate::system::rendering::mesh::vertex::Vertex;
-
-struct VkGraphicsPipeline {
+use ash::vk::{DescriptorSetLayout, DynamicState, GraphicsPipelineCreateInfo, LogicOp, Pipeline, PipelineCache, PipelineColorBlendStateCreateInfo, PipelineDepthStencilStateCreateInfo, PipelineDynamicStateCreateInfo, PipelineInputAssemblyStateCreateInfo, PipelineLayout, PipelineLayoutCreateInfo, PipelineMultisampleStateCreateInfo, PipelineRasterizationStateCreateInfo, PipelineShaderStageCreateInfo, PipelineVertexInputStateCreateInfo, PipelineViewportStateCreateInfo, RenderPass, SampleCountFlags, ShaderModule, ShaderStageFlags};
+use crate::system::assets::manager::{AssetManager, Handle};
+use crate::system::assets::shader::ShaderCode;
+use crate::system::error::ResaResult;
+use crate::system::log::Log;
+use crate::system::rendering::vulkan::pipeline_description::{ShaderStageDescription, VkPipelineDescription};
+use crate::system::rendering::vulkan::shader::{create_shader_module, get_shader_inputs};
+
+pub struct VkGraphicsPipeline {
     pipelines: Vec<Pipeline>,
     pipeline_layout: PipelineLayout,
-    vertex_shader_mod: ShaderModule,
-    fragment_shader_mod: ShaderModule,
-
+    shader_modules: Vec<ShaderModule>,
+    description: VkPipelineDescription,
 }
 
 impl VkGraphicsPipeline {
-    pub fn new(device: &Device, render_pass: &RenderPass, desc_set_layout: &[DescriptorSetLayout], surface_size: Extent2D) -> Self {
-        let mut vert_shader_cursor = Cursor::new(&include_bytes!("./shader/vert.spv")[..]);
-        let mut frag_shader_cursor = Cursor::new(&include_bytes!("./shader/frag.spv")[..]);
-        let vertex_shader_mod = VkGraphicsPipeline::create_shader_module(device, &mut vert_shader_cursor);
-        let fragment_shader_mod = VkGraphicsPipeline::create_shader_module(device, &mut frag_shader_cursor);
+    /// Starts loading the shaders of the description from the shader directory, one handle per shader stage.
+    pub fn load_shaders(assets: &AssetManager, description: &VkPipelineDescription, shader_path: &Path) -> Vec<Handle<ShaderCode>> {
+        description.shader_stages.iter()
+            .map(|shader_stage| assets.load(&shader_path.join(&shader_stage.file_name)))
+            .collect()
+    }
 
-        let layout_create_info = PipelineLayoutCreateInfo::builder().set_layouts(desc_set_layout);
+    /// Creates the pipeline described by the description from the shaders returned by `load_shaders`, waiting for those still loading.
+    /// Fails without side effects if a shader is missing or invalid.
+    pub fn new(device: &Device, render_pass: &RenderPass, desc_set_layout: &[DescriptorSetLayout], description: &VkPipelineDescription, shaders: &[Handle<ShaderCode>], pipeline_cache: &PipelineCache) -> ResaResult<Self> {
+        let mut shader_modules = Vec::new();
+        for (shader_stage, shader) in description.shader_stages.iter().zip(shaders.iter()) {
+            match VkGraphicsPipeline::load_stage(device, description, shader_stage, shader) {
+                Some(module) => shader_modules.push(module),
+                None => {
+                    Log::get().write_error(format!("Pipeline {} could not be created!", description.name).as_str());
+                    VkGraphicsPipeline::destroy_shader_modules(device, &shader_modules);
+                    return ResaResult::Err();
+                }
+            }
+        }
+
+        let layout_create_info = PipelineLayoutCreateInfo::builder()
+            .set_layouts(desc_set_layout)
+            .push_constant_ranges(&description.push_constant_ranges);
         let pipeline_layout = unsafe { device.create_pipeline_layout(&layout_create_info, None).expect("failed to create pipeline layout") };
 
         let shader_entry_name = unsafe { CStr::from_bytes_with_nul_unchecked(b"main\0") };
-        let shader_stage_create_infos = [
-            PipelineShaderStageCreateInfo {
-                module: vertex_shader_mod,
-                p_name: shader_entry_name.as_ptr(),
-                stage: ShaderStageFlags::VERTEX,
-                ..Default::default()
-            },
-            PipelineShaderStageCreateInfo {
-                module: fragment_shader_mod,
+        let shader_stage_create_infos: Vec<PipelineShaderStageCreateInfo> = description.shader_stages.iter()
+            .zip(shader_modules.iter())
+            .map(|(shader_stage, module)| PipelineShaderStageCreateInfo {
+                module: *module,
                 p_name: shader_entry_name.as_ptr(),
-                stage: ShaderStageFlags::FRAGMENT,
+                stage: shader_stage.stage,
                 ..Default::default()
-            }
-        ];
-
-        let vertex_input_binding = [VertexInputBindingDescription {
-            binding: 0,
-            stride: mem::size_of::<Vertex>() as u32,
-            input_rate: VertexInputRate::VERTEX,
-        }];
-
-        let vertex_input_attribute_descriptions = [
-            VertexInputAttributeDescription {
-                location: 0,
-                binding: 0,
-                format: Format::R32G32B32_SFLOAT,
-                offset: offset_of!(Vertex, position) as u32,
-            },
-            VertexInputAttributeDescription {
-                location: 1,
-                binding: 0,
-                format: Format::R32G32_SFLOAT,
-                offset: offset_of!(Vertex, uv) as u32,
-            },
-        ];
+            })
+            .collect();
 
         let vertex_input_state_info = PipelineVertexInputStateCreateInfo::builder()
-            .vertex_attribute_descriptions(&vertex_input_attribute_descriptions)
-            .vertex_binding_descriptions(&vertex_input_binding);
+            .vertex_attribute_descriptions(&description.vertex_layout.attributes)
+            .vertex_binding_descriptions(&description.vertex_layout.bindings);
 
         let vertex_input_assembly_state_info = PipelineInputAssemblyStateCreateInfo {
-            topology: PrimitiveTopology::TRIANGLE_LIST,
+            topology: description.topology,
             ..Default::default()
         };
 
-        let viewports = [Viewport {
-            x: 0.0,
-            y: 0.0,
-            width: surface_size.width as f32,
-            height: surface_size.height as f32,
-            min_depth: 0.0,
-            max_depth: 1.0,
-        }];
-        let scissors = [surface_size.into()];
+        // Viewport and scissor are dynamic, so the pipeline survives a resize.
         let viewport_state_info = PipelineViewportStateCreateInfo::builder()
-            .scissors(&scissors)
-            .viewports(&viewports);
+            .viewport_count(1)
+            .scissor_count(1);
 
         let rasterization_info = PipelineRasterizationStateCreateInfo {
-            front_face: FrontFace::COUNTER_CLOCKWISE,
-            line_width: 1.0,
-            polygon_mode: PolygonMode::FILL,
+            cull_mode: description.cull_mode,
+            front_face: description.front_face,
+            line_width: description.line_width,
+            polygon_mode: description.polygon_mode,
             ..Default::default()
         };
 
         let multisample_state_info = PipelineMultisampleStateCreateInfo::builder().rasterization_samples(SampleCountFlags::TYPE_1);
 
-        let noop_stencil_state = StencilOpState {
-            fail_op: StencilOp::KEEP,
-            pass_op: StencilOp::KEEP,
-            depth_fail_op: StencilOp::KEEP,
-            compare_op: CompareOp::ALWAYS,
-            ..Default::default()
-        };
-
         let depth_state_info = PipelineDepthStencilStateCreateInfo {
-            depth_test_enable: 1,
-            depth_write_enable: 1,
-            depth_compare_op: CompareOp::LESS_OR_EQUAL,
-            front: noop_stencil_state,
-            back: noop_stencil_state,
+            depth_test_enable: description.depth_test_enable as u32,
+            depth_write_enable: description.depth_write_enable as u32,
+            depth_compare_op: description.depth_compare_op,
+            stencil_test_enable: description.stencil_test_enable as u32,
+            front: description.stencil_front,
+            back: description.stencil_back,
             max_depth_bounds: 1.0,
             ..Default::default()
         };
 
-        let color_blend_attachment_states = [PipelineColorBlendAttachmentState {
-            blend_enable: 0,
-            src_color_blend_factor: BlendFactor::SRC_COLOR,
-            dst_color_blend_factor: BlendFactor::ONE_MINUS_DST_COLOR,
-            color_blend_op: BlendOp::ADD,
-            src_alpha_blend_factor: BlendFactor::ZERO,
-            dst_alpha_blend_factor: BlendFactor::ZERO,
-            alpha_blend_op: BlendOp::ADD,
-            color_write_mask: ColorComponentFlags::RGBA,
-        }];
-
+        let color_blend_attachment_states = [description.blend_mode.get_attachment_state()];
         let color_blend_state = PipelineColorBlendStateCreateInfo::builder()
             .logic_op(LogicOp::CLEAR)
             .attachments(&color_blend_attachment_states);
@@ -144,28 +113,76 @@ impl VkGraphicsPipeline {
             .render_pass(*render_pass);
 
         let pipelines = unsafe {
-            device.create_graphics_pipelines(PipelineCache::null(),
+            device.create_graphics_pipelines(*pipeline_cache,
                                              &[graphics_pipeline_infos.build()],
                                              None)
-                .expect("Failed to create pipelines")
+        };
+        let pipelines = match pipelines {
+            Ok(pipelines) => pipelines,
+            Err((_, e)) => {
+                Log::get().write_error(format!("Failed to create pipeline {}: {}", description.name, e).as_str());
+                unsafe { device.destroy_pipeline_layout(pipeline_layout, None) };
+                VkGraphicsPipeline::destroy_shader_modules(device, &shader_modules);
+                return ResaResult::Err();
+            }
         };
 
-        VkGraphicsPipeline {
-            vertex_shader_mod,
-            fragment_shader_mod,
+        ResaResult::Ok(VkGraphicsPipeline {
+            shader_modules,
             pipeline_layout,
             pipelines,
-        }
+            description: description.clone(),
+        })
     }
 
     pub fn get_primary(&self) -> &Pipeline {
         &self.pipelines[0]
     }
 
-    fn create_shader_module(device: &Device, shader_file: &mut Cursor<&[u8]>) -> ShaderModule {
-        let shader_code = read_spv(shader_file).expect("Failed to read vertex shader");
-        let shader_info = ShaderModuleCreateInfo::builder().code(&shader_code);
-        let shader_module = unsafe { device.create_shader_module(&shader_info, None).expect("failed to create shader module") };
-        shader_module
+    pub fn get_layout(&self) -> &PipelineLayout {
+        &self.pipeline_layout
+    }
+
+    pub fn get_description(&self) -> &VkPipelineDescription {
+        &self.description
+    }
+
+    /// Checks whether the shader file with the given name is part of this pipeline.
+    pub fn uses_shader(&self, file_name: &OsStr) -> bool {
+        self.description.uses_shader(file_name)
+    }
+
+    pub fn destroy(&self, device: &Device) {
+        unsafe {
+            for pipeline in self.pipelines.iter() {
+                device.destroy_pipeline(*pipeline, None);
+            }
+            device.destroy_pipeline_layout(self.pipeline_layout, None);
+        }
+        VkGraphicsPipeline::destroy_shader_modules(device, &self.shader_modules);
+    }
+
+    /// Creates the shader module of one stage. A vertex shader is only accepted if the vertex layout of the pipeline feeds all of its inputs.
+    fn load_stage(device: &Device, description: &VkPipelineDescription, shader_stage: &ShaderStageDescription, shader: &Handle<ShaderCode>) -> Option<ShaderModule> {
+        let shader_code = match shader.wait() {
+            Ok(shader_code) => shader_code,
+            Err(e) => {
+                Log::get().write_error(format!("Unable to load shader {}", e).as_str());
+                return None;
+            }
+        };
+        if shader_stage.stage == ShaderStageFlags::VERTEX
+            && !description.vertex_layout.matches_shader(&get_shader_inputs(&shader_code.code), &shader_stage.file_name) {
+            return None;
+        }
+        create_shader_module(device, shader.get_path(), &shader_code.code)
+    }
+
+    fn destroy_shader_modules(device: &Device, shader_modules: &[ShaderModule]) {
+        unsafe {
+            for shader_module in shader_modules.iter() {
+                device.destroy_shader_module(*shader_module, None);
+            }
+        }
     }
 }
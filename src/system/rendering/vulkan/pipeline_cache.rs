@@ -0,0 +1,148 @@
+use std::convert::TryInto;
+use std::fs;
+use std::path::PathBuf;
+use ash::Device;
+use ash::vk::{PipelineCache, PipelineCacheCreateInfo, PipelineCacheHeaderVersion, UUID_SIZE};
+use directories::ProjectDirs;
+use crate::system::log::Log;
+use crate::system::rendering::vulkan::global::VkPhysicalDevice;
+
+const PIPELINE_CACHE_FILE: &str = "pipeline_cache.bin";
+/// Size of VK_PIPELINE_CACHE_HEADER_VERSION_ONE: header size, header version, vendor id, device id and the cache uuid.
+const HEADER_SIZE: usize = 16 + UUID_SIZE;
+
+/// Pipeline cache that survives restarts. It is read from the user cache directory on creation
+/// and written back by `save`, which makes every pipeline after the first start a lot cheaper to build.
+pub struct VkPipelineCache {
+    pub cache: PipelineCache,
+    path: Option<PathBuf>,
+}
+
+impl VkPipelineCache {
+    pub fn new(physical_device: &VkPhysicalDevice, device: &Device) -> Self {
+        let path = ProjectDirs::from("", "SimpArt", "resa").map(|dirs| dirs.cache_dir().join(PIPELINE_CACHE_FILE));
+        let initial_data = match &path {
+            Some(path) => VkPipelineCache::load_initial_data(physical_device, path),
+            None => {
+                Log::get().write_warning("No user cache directory found, the pipeline cache is not persisted!");
+                Vec::new()
+            }
+        };
+
+        let create_info = PipelineCacheCreateInfo::builder().initial_data(&initial_data);
+        let cache = match unsafe { device.create_pipeline_cache(&create_info, None) } {
+            Ok(cache) => cache,
+            Err(e) => {
+                // The driver may still reject data with a matching header, an empty cache always works.
+                Log::get().write_warning(format!("Pipeline cache rejected by the driver, starting empty: {}", e).as_str());
+                let create_info = PipelineCacheCreateInfo::default();
+                unsafe { device.create_pipeline_cache(&create_info, None).expect("Pipeline cache creation failed!") }
+            }
+        };
+
+        VkPipelineCache {
+            cache,
+            path,
+        }
+    }
+
+    /// Writes the cache to disk. Failing to do so is not critical, the next start simply builds the pipelines from scratch.
+    pub fn save(&self, device: &Device) {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return,
+        };
+
+        let data = match unsafe { device.get_pipeline_cache_data(self.cache) } {
+            Ok(data) => data,
+            Err(e) => {
+                Log::get().write_warning(format!("Reading the pipeline cache failed: {}", e).as_str());
+                return;
+            }
+        };
+
+        let result = match path.parent() {
+            Some(directory) => fs::create_dir_all(directory).and_then(|_| fs::write(path, &data)),
+            None => fs::write(path, &data),
+        };
+        match result {
+            Ok(_) => Log::get().write(format!("Saved pipeline cache with {} bytes to {}", data.len(), path.display()).as_str()),
+            Err(e) => Log::get().write_warning(format!("Saving the pipeline cache to {} failed: {}", path.display(), e).as_str()),
+        }
+    }
+
+    pub fn destroy(&self, device: &Device) {
+        unsafe { device.destroy_pipeline_cache(self.cache, None) };
+    }
+
+    /// Reads the cache file. A missing, damaged or foreign file results in an empty cache.
+    fn load_initial_data(physical_device: &VkPhysicalDevice, path: &PathBuf) -> Vec<u8> {
+        let data = match fs::read(path) {
+            Ok(data) => data,
+            Err(_) => {
+                Log::get().write("No pipeline cache found, starting with an empty cache");
+                return Vec::new();
+            }
+        };
+
+        if !VkPipelineCache::is_header_valid(physical_device.vendor_id, physical_device.device_id, &physical_device.pipeline_cache_uuid, &data) {
+            Log::get().write_warning("Pipeline cache was created by a different device or driver, it is discarded");
+            return Vec::new();
+        }
+        Log::get().write(format!("Loaded pipeline cache with {} bytes", data.len()).as_str());
+        data
+    }
+
+    /// Checks that the cache was written by the device with the ids and the driver build with the uuid. The header fields are little endian.
+    fn is_header_valid(vendor_id: u32, device_id: u32, pipeline_cache_uuid: &[u8; UUID_SIZE], data: &[u8]) -> bool {
+        if data.len() < HEADER_SIZE {
+            return false;
+        }
+        let read_u32 = |offset: usize| u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap());
+
+        let header_size = read_u32(0) as usize;
+        let header_version = read_u32(4);
+        let uuid = &data[16..HEADER_SIZE];
+
+        header_size >= HEADER_SIZE
+            && header_size <= data.len()
+            && header_version == PipelineCacheHeaderVersion::ONE.as_raw() as u32
+            && read_u32(8) == vendor_id
+            && read_u32(12) == device_id
+            && uuid == pipeline_cache_uuid
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VENDOR_ID: u32 = 0x10de;
+    const DEVICE_ID: u32 = 0x2204;
+    const UUID: [u8; UUID_SIZE] = [7; UUID_SIZE];
+
+    fn header(vendor_id: u32, device_id: u32, uuid: &[u8; UUID_SIZE]) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&(HEADER_SIZE as u32).to_le_bytes());
+        data.extend_from_slice(&(PipelineCacheHeaderVersion::ONE.as_raw() as u32).to_le_bytes());
+        data.extend_from_slice(&vendor_id.to_le_bytes());
+        data.extend_from_slice(&device_id.to_le_bytes());
+        data.extend_from_slice(uuid);
+        // Driver specific data follows the header.
+        data.extend_from_slice(&[1, 2, 3, 4]);
+        data
+    }
+
+    #[test]
+    fn header_has_to_match_device_and_driver() {
+        let is_valid = |data: &[u8]| VkPipelineCache::is_header_valid(VENDOR_ID, DEVICE_ID, &UUID, data);
+        assert!(is_valid(&header(VENDOR_ID, DEVICE_ID, &UUID)));
+        assert!(!is_valid(&header(0x1002, DEVICE_ID, &UUID)));
+        assert!(!is_valid(&header(VENDOR_ID, 0x2208, &UUID)));
+        let mut other_uuid = UUID;
+        other_uuid[UUID_SIZE - 1] = 8;
+        assert!(!is_valid(&header(VENDOR_ID, DEVICE_ID, &other_uuid)));
+        assert!(!is_valid(&header(VENDOR_ID, DEVICE_ID, &UUID)[..HEADER_SIZE - 1]));
+        assert!(!is_valid(&[]));
+    }
+}
@@ -0,0 +1,352 @@
+use std::ffi::OsStr;
+use std::mem;
+use ash::vk::{BlendFactor, BlendOp, ColorComponentFlags, CompareOp, CullModeFlags, Format, FrontFace, PipelineColorBlendAttachmentState, PolygonMode, PrimitiveTopology, PushConstantRange, ShaderStageFlags, StencilOp, StencilOpState, VertexInputAttributeDescription, VertexInputBindingDescription, VertexInputRate};
+use crate::system::log::Log;
+use crate::system::math::matrix::Mat4;
+use crate::system::rendering::mesh::vertex::{AttributeFormat, Vertex, VertexFormat};
+use crate::system::rendering::vulkan::shader::{NumericType, ShaderInput};
+
+/// How the fragment output is combined with the color already in the attachment.
+#[derive(Clone, Copy, PartialEq)]
+pub enum BlendMode {
+    Opaque,
+    /// Classic transparency, src * alpha + dst * (1 - alpha).
+    AlphaBlend,
+    Additive,
+}
+
+impl BlendMode {
+    pub fn get_attachment_state(&self) -> PipelineColorBlendAttachmentState {
+        let (src_color, dst_color, src_alpha, dst_alpha) = match self {
+            BlendMode::Opaque => (BlendFactor::ONE, BlendFactor::ZERO, BlendFactor::ONE, BlendFactor::ZERO),
+            BlendMode::AlphaBlend => (BlendFactor::SRC_ALPHA, BlendFactor::ONE_MINUS_SRC_ALPHA, BlendFactor::ONE, BlendFactor::ONE_MINUS_SRC_ALPHA),
+            BlendMode::Additive => (BlendFactor::SRC_ALPHA, BlendFactor::ONE, BlendFactor::ZERO, BlendFactor::ONE),
+        };
+
+        PipelineColorBlendAttachmentState {
+            blend_enable: (*self != BlendMode::Opaque) as u32,
+            src_color_blend_factor: src_color,
+            dst_color_blend_factor: dst_color,
+            color_blend_op: BlendOp::ADD,
+            src_alpha_blend_factor: src_alpha,
+            dst_alpha_blend_factor: dst_alpha,
+            alpha_blend_op: BlendOp::ADD,
+            color_write_mask: ColorComponentFlags::RGBA,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ShaderStageDescription {
+    pub stage: ShaderStageFlags,
+    /// File name of the compiled SPIR-V module inside the shader directory.
+    pub file_name: String,
+}
+
+/// Vertex input state of a pipeline. Usually derived from a vertex type with `VertexLayout::of`.
+#[derive(Clone)]
+pub struct VertexLayout {
+    pub bindings: Vec<VertexInputBindingDescription>,
+    pub attributes: Vec<VertexInputAttributeDescription>,
+}
+
+impl VertexLayout {
+    /// Layout of a single interleaved vertex buffer at binding 0 holding vertices of type V.
+    pub fn of<V: VertexFormat>() -> Self {
+        VertexLayout {
+            bindings: vec![VertexInputBindingDescription {
+                binding: 0,
+                stride: V::get_stride(),
+                input_rate: VertexInputRate::VERTEX,
+            }],
+            attributes: V::get_attributes().iter()
+                .map(|attribute| VertexInputAttributeDescription {
+                    location: attribute.location,
+                    binding: 0,
+                    format: VertexLayout::get_format(attribute.format),
+                    offset: attribute.offset,
+                })
+                .collect(),
+        }
+    }
+
+    /// Checks that every input of the vertex shader is fed by an attribute of the same numeric type.
+    /// The component counts may differ, vulkan fills missing components and drops surplus ones.
+    /// Every mismatch is logged, so a wrong shader is reported before the driver sees it.
+    pub fn matches_shader(&self, inputs: &[ShaderInput], shader_name: &str) -> bool {
+        let mut matches = true;
+        for input in inputs.iter() {
+            let attribute = match self.attributes.iter().find(|attribute| attribute.location == input.location) {
+                Some(attribute) => attribute,
+                None => {
+                    Log::get().write_error(format!("Vertex shader {} reads location {}, which the vertex layout does not provide!", shader_name, input.location).as_str());
+                    matches = false;
+                    continue;
+                }
+            };
+            let attribute_type = VertexLayout::get_numeric_type(attribute.format);
+            if attribute_type != Some(input.numeric_type) {
+                Log::get().write_error(format!("Vertex shader {} reads location {} as {} component {:?}, but the vertex layout provides {:?}!",
+                                               shader_name, input.location, input.components, input.numeric_type, attribute.format).as_str());
+                matches = false;
+            }
+        }
+        matches
+    }
+
+    fn get_format(format: AttributeFormat) -> Format {
+        match format {
+            AttributeFormat::Float => Format::R32_SFLOAT,
+            AttributeFormat::Float2 => Format::R32G32_SFLOAT,
+            AttributeFormat::Float3 => Format::R32G32B32_SFLOAT,
+            AttributeFormat::Float4 => Format::R32G32B32A32_SFLOAT,
+            AttributeFormat::UInt4 => Format::R32G32B32A32_UINT,
+        }
+    }
+
+    fn get_numeric_type(format: Format) -> Option<NumericType> {
+        match format {
+            Format::R32_SFLOAT | Format::R32G32_SFLOAT | Format::R32G32B32_SFLOAT | Format::R32G32B32A32_SFLOAT => Some(NumericType::Float),
+            Format::R32_UINT | Format::R32G32_UINT | Format::R32G32B32_UINT | Format::R32G32B32A32_UINT => Some(NumericType::UInt),
+            Format::R32_SINT | Format::R32G32_SINT | Format::R32G32B32_SINT | Format::R32G32B32A32_SINT => Some(NumericType::SInt),
+            _ => None,
+        }
+    }
+}
+
+/// Every fixed function state of a graphics pipeline. Viewport and scissor are always dynamic and not part of it.
+#[derive(Clone)]
+pub struct VkPipelineDescription {
+    pub name: String,
+    pub topology: PrimitiveTopology,
+    pub cull_mode: CullModeFlags,
+    pub front_face: FrontFace,
+    pub polygon_mode: PolygonMode,
+    pub line_width: f32,
+    pub depth_test_enable: bool,
+    pub depth_write_enable: bool,
+    pub depth_compare_op: CompareOp,
+    pub stencil_test_enable: bool,
+    pub stencil_front: StencilOpState,
+    pub stencil_back: StencilOpState,
+    pub blend_mode: BlendMode,
+    pub shader_stages: Vec<ShaderStageDescription>,
+    pub vertex_layout: VertexLayout,
+    pub push_constant_ranges: Vec<PushConstantRange>,
+}
+
+impl VkPipelineDescription {
+    /// Starts an empty description without shaders or vertex input. Depth testing and blending are disabled.
+    pub fn builder(name: &str) -> VkPipelineBuilder {
+        let noop_stencil_state = StencilOpState {
+            fail_op: StencilOp::KEEP,
+            pass_op: StencilOp::KEEP,
+            depth_fail_op: StencilOp::KEEP,
+            compare_op: CompareOp::ALWAYS,
+            ..Default::default()
+        };
+
+        VkPipelineBuilder {
+            description: VkPipelineDescription {
+                name: name.to_string(),
+                topology: PrimitiveTopology::TRIANGLE_LIST,
+                cull_mode: CullModeFlags::NONE,
+                front_face: FrontFace::COUNTER_CLOCKWISE,
+                polygon_mode: PolygonMode::FILL,
+                line_width: 1.0,
+                depth_test_enable: false,
+                depth_write_enable: false,
+                depth_compare_op: CompareOp::ALWAYS,
+                stencil_test_enable: false,
+                stencil_front: noop_stencil_state,
+                stencil_back: noop_stencil_state,
+                blend_mode: BlendMode::Opaque,
+                shader_stages: Vec::new(),
+                vertex_layout: VertexLayout { bindings: Vec::new(), attributes: Vec::new() },
+                push_constant_ranges: Vec::new(),
+            },
+        }
+    }
+
+    /// Solid, depth tested and depth writing geometry with the default shaders. The other presets start from it.
+    pub fn opaque(name: &str) -> Self {
+        VkPipelineDescription::builder(name)
+            .topology(PrimitiveTopology::TRIANGLE_LIST)
+            .cull_mode(CullModeFlags::NONE, FrontFace::COUNTER_CLOCKWISE)
+            .depth_test(true)
+            .depth_write(true)
+            .depth_compare_op(CompareOp::LESS_OR_EQUAL)
+            .shader(ShaderStageFlags::VERTEX, "vert.spv")
+            .shader(ShaderStageFlags::FRAGMENT, "frag.spv")
+            .vertex_layout(VertexLayout::of::<Vertex>())
+            .push_constant_range(ShaderStageFlags::VERTEX, 0, mem::size_of::<Mat4>() as u32)
+            .build()
+    }
+
+    /// Alpha blended geometry. It is depth tested against the opaque scene but does not write depth itself.
+    pub fn transparent(name: &str) -> Self {
+        VkPipelineDescription::opaque(name).to_builder()
+            .depth_write(false)
+            .blend_mode(BlendMode::AlphaBlend)
+            .build()
+    }
+
+    /// Adds the color on top of the scene, e.g. for particles and glow. Like transparent geometry it does not write depth.
+    pub fn additive(name: &str) -> Self {
+        VkPipelineDescription::opaque(name).to_builder()
+            .depth_write(false)
+            .blend_mode(BlendMode::Additive)
+            .build()
+    }
+
+    /// Draws only the triangle edges. Requires the fillModeNonSolid device feature.
+    pub fn wireframe(name: &str) -> Self {
+        VkPipelineDescription::opaque(name).to_builder()
+            .polygon_mode(PolygonMode::LINE)
+            .line_width(1.0)
+            .build()
+    }
+
+    /// Overlay geometry, drawn in submission order on top of everything else.
+    pub fn ui(name: &str) -> Self {
+        VkPipelineDescription::opaque(name).to_builder()
+            .depth_test(false)
+            .depth_write(false)
+            .blend_mode(BlendMode::AlphaBlend)
+            .build()
+    }
+
+    /// Continues building on a copy of this description.
+    pub fn to_builder(&self) -> VkPipelineBuilder {
+        VkPipelineBuilder { description: self.clone() }
+    }
+
+    pub fn uses_shader(&self, file_name: &OsStr) -> bool {
+        self.shader_stages.iter().any(|stage| OsStr::new(&stage.file_name) == file_name)
+    }
+}
+
+/// Fluent construction of a VkPipelineDescription.
+pub struct VkPipelineBuilder {
+    description: VkPipelineDescription,
+}
+
+impl VkPipelineBuilder {
+    pub fn topology(mut self, topology: PrimitiveTopology) -> Self {
+        self.description.topology = topology;
+        self
+    }
+
+    pub fn cull_mode(mut self, cull_mode: CullModeFlags, front_face: FrontFace) -> Self {
+        self.description.cull_mode = cull_mode;
+        self.description.front_face = front_face;
+        self
+    }
+
+    pub fn polygon_mode(mut self, polygon_mode: PolygonMode) -> Self {
+        self.description.polygon_mode = polygon_mode;
+        self
+    }
+
+    pub fn line_width(mut self, line_width: f32) -> Self {
+        self.description.line_width = line_width;
+        self
+    }
+
+    pub fn depth_test(mut self, enable: bool) -> Self {
+        self.description.depth_test_enable = enable;
+        self
+    }
+
+    pub fn depth_write(mut self, enable: bool) -> Self {
+        self.description.depth_write_enable = enable;
+        self
+    }
+
+    pub fn depth_compare_op(mut self, compare_op: CompareOp) -> Self {
+        self.description.depth_compare_op = compare_op;
+        self
+    }
+
+    pub fn stencil_test(mut self, enable: bool) -> Self {
+        self.description.stencil_test_enable = enable;
+        self
+    }
+
+    /// Stencil operations for front and back facing triangles. They only take effect with the stencil test enabled.
+    pub fn stencil_ops(mut self, front: StencilOpState, back: StencilOpState) -> Self {
+        self.description.stencil_front = front;
+        self.description.stencil_back = back;
+        self
+    }
+
+    pub fn blend_mode(mut self, blend_mode: BlendMode) -> Self {
+        self.description.blend_mode = blend_mode;
+        self
+    }
+
+    /// Replaces the shader of the given stage or adds it, if the stage is not set yet.
+    pub fn shader(mut self, stage: ShaderStageFlags, file_name: &str) -> Self {
+        self.description.shader_stages.retain(|shader| shader.stage != stage);
+        self.description.shader_stages.push(ShaderStageDescription { stage, file_name: file_name.to_string() });
+        self
+    }
+
+    pub fn vertex_layout(mut self, vertex_layout: VertexLayout) -> Self {
+        self.description.vertex_layout = vertex_layout;
+        self
+    }
+
+    /// Adds a range of push constants, e.g. the model matrix the default vertex shader reads at offset 0.
+    pub fn push_constant_range(mut self, stage: ShaderStageFlags, offset: u32, size: u32) -> Self {
+        self.description.push_constant_ranges.push(PushConstantRange { stage_flags: stage, offset, size });
+        self
+    }
+
+    pub fn build(self) -> VkPipelineDescription {
+        self.description
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use super::*;
+    use crate::system::assets::shader::ShaderCode;
+    use crate::system::rendering::vulkan::shader::get_shader_inputs;
+
+    fn default_vertex_shader_inputs() -> Vec<ShaderInput> {
+        get_shader_inputs(&ShaderCode::load(Path::new("assets/shaders/vert.spv")).unwrap().code)
+    }
+
+    #[test]
+    fn builder_keeps_the_stencil_state() {
+        let front = StencilOpState { fail_op: StencilOp::ZERO, pass_op: StencilOp::REPLACE, compare_op: CompareOp::EQUAL, reference: 1, compare_mask: 0xff, write_mask: 0xff, ..Default::default() };
+        let back = StencilOpState { pass_op: StencilOp::INCREMENT_AND_CLAMP, compare_op: CompareOp::ALWAYS, ..Default::default() };
+        let description = VkPipelineDescription::opaque("outline").to_builder()
+            .stencil_test(true)
+            .stencil_ops(front, back)
+            .build();
+
+        assert!(description.stencil_test_enable);
+        assert_eq!((description.stencil_front.fail_op, description.stencil_front.pass_op, description.stencil_front.compare_op), (StencilOp::ZERO, StencilOp::REPLACE, CompareOp::EQUAL));
+        assert_eq!(description.stencil_front.reference, 1);
+        assert_eq!((description.stencil_back.pass_op, description.stencil_back.compare_op), (StencilOp::INCREMENT_AND_CLAMP, CompareOp::ALWAYS));
+        assert!(!VkPipelineDescription::opaque("opaque").stencil_test_enable);
+    }
+
+    #[test]
+    fn vertex_layout_has_to_feed_every_shader_input() {
+        Log::init_for_tests();
+        let inputs = default_vertex_shader_inputs();
+        assert!(VertexLayout::of::<Vertex>().matches_shader(&inputs, "vert.spv"));
+
+        let mut without_uv = VertexLayout::of::<Vertex>();
+        without_uv.attributes.retain(|attribute| attribute.location != 1);
+        assert!(!without_uv.matches_shader(&inputs, "vert.spv"));
+
+        let mut integer_uv = VertexLayout::of::<Vertex>();
+        integer_uv.attributes.iter_mut().filter(|attribute| attribute.location == 1).for_each(|attribute| attribute.format = VertexLayout::get_format(AttributeFormat::UInt4));
+        assert!(!integer_uv.matches_shader(&inputs, "vert.spv"));
+    }
+}
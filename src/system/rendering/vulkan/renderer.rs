@@ -1,55 +1,639 @@
-use ash::vk::Extent2D;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use ash::Device;
+use ash::vk::{AccessFlags, AttachmentDescription, AttachmentLoadOp, AttachmentReference, AttachmentStoreOp, BufferUsageFlags, ClearColorValue, ClearDepthStencilValue, ClearValue, CommandBufferBeginInfo, CommandBufferResetFlags, CommandBufferUsageFlags, CommandPool, CommandPoolCreateFlags, CommandPoolCreateInfo, DescriptorSetLayout, DescriptorType, DeviceSize, Extent2D, Fence, Format, Framebuffer, FramebufferCreateInfo, ImageAspectFlags, ImageLayout, ImageUsageFlags, ImageView, IndexType, MemoryPropertyFlags, PipelineBindPoint, PipelineStageFlags, Rect2D, RenderPass, RenderPassBeginInfo, RenderPassCreateInfo, Result, SampleCountFlags, Semaphore, ShaderStageFlags, SubmitInfo, SUBPASS_EXTERNAL, SubpassContents, SubpassDependency, SubpassDescription, Viewport};
 use winit::window::Window;
+use crate::system::assets::manager::{AssetManager, AssetState, Handle};
+use crate::system::assets::shader::ShaderCode;
+use crate::system::error::ResaResult;
+use crate::system::ini::RenderingIniData;
 use crate::system::log::Log;
 use crate::system::rendering::IRenderer;
-use crate::system::rendering::mesh::mesh::Mesh;
+use crate::system::rendering::camera_uniform::CameraUniform;
+use crate::system::rendering::mesh::vertex::Vertex;
+use crate::system::rendering::render_image::RenderImage;
+use crate::system::rendering::render_list::{CullStats, RenderItem, RenderList};
+use crate::system::rendering::vulkan::allocator::{AllocationStrategy, VkAllocator};
+use crate::system::rendering::vulkan::buffer::VkBuffer;
 use crate::system::rendering::vulkan::device::VkLogicalDevice;
+use crate::system::rendering::vulkan::descriptor::{DescriptorBinding, VkDescriptorAllocator, VkDescriptorSetLayout};
+use crate::system::rendering::vulkan::frame::{CAMERA_BINDING, MeshBuffers, VkFrame};
 use crate::system::rendering::vulkan::global::VkInstance;
+use crate::system::rendering::vulkan::image::VkImage;
+use crate::system::rendering::vulkan::material::{MATERIAL_SET, VkMaterialResources};
+use crate::system::rendering::vulkan::offscreen::{OFFSCREEN_COLOR_FORMAT, VkOffscreenTarget};
+use crate::system::rendering::vulkan::pipeline::VkGraphicsPipeline;
+use crate::system::rendering::vulkan::pipeline_cache::VkPipelineCache;
+use crate::system::rendering::vulkan::pipeline_description::VkPipelineDescription;
+use crate::system::rendering::vulkan::shader::ShaderWatcher;
+
+const DEPTH_FORMAT: Format = Format::D16_UNORM;
+/// Pipeline that is used if a material names an unknown one.
+const DEFAULT_PIPELINE: &str = "opaque";
+
+/// Pipeline that is rebuilt as soon as the asset manager has loaded its changed shaders.
+struct PendingPipeline {
+    /// Index of the pipeline it replaces.
+    index: usize,
+    shaders: Vec<Handle<ShaderCode>>,
+}
 
 pub struct VkRenderer {
     instance: VkInstance,
     gpu_name: String,
     device: VkLogicalDevice,
+    offscreen_target: Option<VkOffscreenTarget>,
+    window_size: Extent2D,
+    extent: Extent2D,
+    color_format: Format,
+    swapchain_outdated: bool,
+    render_pass: RenderPass,
+    depth_image: VkImage,
+    framebuffers: Vec<Framebuffer>,
+    pipelines: Vec<VkGraphicsPipeline>,
+    pipeline_cache: VkPipelineCache,
+    camera_set_layout: VkDescriptorSetLayout,
+    materials: VkMaterialResources,
+    lod_pixel_error: f32,
+    cull_stats: CullStats,
+    descriptor_allocator: VkDescriptorAllocator,
+    allocator: VkAllocator,
+    assets: Arc<AssetManager>,
+    shader_path: PathBuf,
+    shader_watcher: Option<ShaderWatcher>,
+    pending_pipelines: Vec<PendingPipeline>,
+    command_pool: CommandPool,
+    frames: Vec<VkFrame>,
+    frame_index: usize,
+    images_in_flight: Vec<Fence>,
 }
 
 impl IRenderer for VkRenderer {
-    fn new(window: &Window) -> Self where Self: Sized {
+    fn new(window: &Window, settings: &RenderingIniData, assets: Arc<AssetManager>) -> Self where Self: Sized {
         Log::get().write_warning("Create new vulkan renderer! GPU Name is set to none! This must changed to ini variable");
-        let instance = VkInstance::new(window, None).expect("Creation of instance failed!");
+        let instance = VkInstance::new(Some(window), None).expect("Creation of instance failed!");
+        let window_size = Extent2D { width: window.inner_size().width, height: window.inner_size().height };
+        VkRenderer::create(instance, window_size, settings, assets)
+    }
+
+    fn new_headless(width: u32, height: u32, settings: &RenderingIniData, assets: Arc<AssetManager>) -> Self where Self: Sized {
+        Log::get().write("Create new headless vulkan renderer");
+        let instance = VkInstance::new(None, None).expect("Creation of instance failed!");
+        VkRenderer::create(instance, Extent2D { width, height }, settings, assets)
+    }
+
+    fn get_gpu_name(&self) -> &str {
+        let name = self.gpu_name.as_str();
+        name
+    }
+
+    fn render(&mut self, camera: &CameraUniform, render_list: &RenderList) {
+        self.reload_changed_shaders();
+        if self.swapchain_outdated {
+            self.recreate_swapchain();
+            if self.swapchain_outdated {
+                return;
+            }
+        }
+        self.materials.update_textures(&mut self.allocator, &self.device, &self.command_pool, render_list);
+
+        let device = &self.device.device;
+        let frame = &mut self.frames[self.frame_index];
+        frame.wait(device);
+
+        let present_index = match &self.device.swapchain {
+            Some(swapchain) => match swapchain.acquire_next_image(frame.image_available_semaphore) {
+                Ok((index, suboptimal)) => {
+                    // A suboptimal image can still be presented, the swapchain is recreated afterwards.
+                    self.swapchain_outdated = suboptimal;
+                    index
+                }
+                Err(Result::ERROR_OUT_OF_DATE_KHR) => {
+                    self.swapchain_outdated = true;
+                    return;
+                }
+                Err(e) => panic!("Acquire next image failed: {}", e),
+            },
+            None => 0,
+        };
+
+        // An older frame can still render into the acquired image, if the swapchain hands out images out of order.
+        let image_fence = self.images_in_flight[present_index as usize];
+        if image_fence != Fence::null() && image_fence != frame.in_flight_fence {
+            unsafe { device.wait_for_fences(&[image_fence], true, u64::MAX).expect("Wait for fence failed!") };
+        }
+        self.images_in_flight[present_index as usize] = frame.in_flight_fence;
+
+        // The fence is only reset once it is certain that work gets submitted, otherwise the next frame would wait forever.
+        unsafe { device.reset_fences(&[frame.in_flight_fence]).expect("Reset fences failed!") };
+
+        let (items, cull_stats) = render_list.cull(camera);
+        self.cull_stats = cull_stats;
+        let lod_indices: Vec<&[u32]> = items.iter()
+            .map(|item| item.mesh.select_lod(camera, &item.transform, self.extent.height, self.lod_pixel_error))
+            .collect();
+        VkRenderer::upload_meshes(&mut self.allocator, device, &mut self.descriptor_allocator, &self.materials, &mut frame.mesh_buffers, &items, &lod_indices);
+        frame.camera_buffer.upload(&[*camera]);
+
+        let command_buffer = frame.command_buffer;
+        let extent = self.extent;
+        let clear_values = [
+            ClearValue { color: ClearColorValue { float32: [0.0, 0.0, 0.0, 1.0] } },
+            ClearValue { depth_stencil: ClearDepthStencilValue { depth: 1.0, stencil: 0 } },
+        ];
+
+        let render_pass_begin_info = RenderPassBeginInfo::builder()
+            .render_pass(self.render_pass)
+            .framebuffer(self.framebuffers[present_index as usize])
+            .render_area(extent.into())
+            .clear_values(&clear_values);
+
+        let viewports = [Viewport {
+            x: 0.0,
+            y: 0.0,
+            width: extent.width as f32,
+            height: extent.height as f32,
+            min_depth: 0.0,
+            max_depth: 1.0,
+        }];
+        let scissors: [Rect2D; 1] = [extent.into()];
+
+        unsafe {
+            device.reset_command_buffer(command_buffer, CommandBufferResetFlags::RELEASE_RESOURCES).expect("Reset cmd buffer failed!");
+            let command_buffer_begin_info = CommandBufferBeginInfo::builder().flags(CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+            device.begin_command_buffer(command_buffer, &command_buffer_begin_info).expect("Begin cmd buffer failed!");
+
+            device.cmd_begin_render_pass(command_buffer, &render_pass_begin_info, SubpassContents::INLINE);
+            // All pipelines share their set layouts, so the camera set stays bound while the pipelines change.
+            let mut pipeline = VkRenderer::find_pipeline(&self.pipelines, DEFAULT_PIPELINE);
+            device.cmd_bind_pipeline(command_buffer, PipelineBindPoint::GRAPHICS, *pipeline.get_primary());
+            device.cmd_bind_descriptor_sets(command_buffer, PipelineBindPoint::GRAPHICS, *pipeline.get_layout(), 0, &[frame.camera_descriptor_set], &[]);
+            device.cmd_set_viewport(command_buffer, 0, &viewports);
+            device.cmd_set_scissor(command_buffer, 0, &scissors);
+
+            for (item, buffers) in items.iter().zip(frame.mesh_buffers.iter()) {
+                let material = item.material.as_ref().unwrap_or(&self.materials.default_material);
+                let material_pipeline = VkRenderer::find_pipeline(&self.pipelines, material.get_pipeline());
+                if material_pipeline.get_primary() != pipeline.get_primary() {
+                    pipeline = material_pipeline;
+                    device.cmd_bind_pipeline(command_buffer, PipelineBindPoint::GRAPHICS, *pipeline.get_primary());
+                }
+                device.cmd_bind_descriptor_sets(command_buffer, PipelineBindPoint::GRAPHICS, *pipeline.get_layout(), MATERIAL_SET,
+                                                &[buffers.material_descriptor_set], &[]);
+                device.cmd_push_constants(command_buffer, *pipeline.get_layout(), ShaderStageFlags::VERTEX, 0, bytemuck::bytes_of(&item.transform));
+
+                device.cmd_bind_vertex_buffers(command_buffer, 0, &[buffers.vertex_buffer.buffer], &[0]);
+                if buffers.index_count == 0 {
+                    device.cmd_draw(command_buffer, item.mesh.vertices.len() as u32, 1, 0, 0);
+                } else {
+                    device.cmd_bind_index_buffer(command_buffer, buffers.index_buffer.buffer, 0, IndexType::UINT32);
+                    device.cmd_draw_indexed(command_buffer, buffers.index_count, 1, 0, 0, 0);
+                }
+            }
+
+            device.cmd_end_render_pass(command_buffer);
+            if let Some(target) = &self.offscreen_target {
+                target.record_readback(device, command_buffer);
+            }
+            device.end_command_buffer(command_buffer).expect("End cmd buffer failed!");
+
+            // Without a swapchain there is no image to wait for and nothing to present.
+            let has_swapchain = self.device.swapchain.is_some();
+            let wait_semaphores: &[Semaphore] = if has_swapchain { &[frame.image_available_semaphore] } else { &[] };
+            let wait_mask: &[PipelineStageFlags] = if has_swapchain { &[PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT] } else { &[] };
+            let signal_semaphores: &[Semaphore] = if has_swapchain { &[frame.render_finished_semaphore] } else { &[] };
+            let command_buffers = [command_buffer];
+            let submit_info = SubmitInfo::builder()
+                .wait_semaphores(wait_semaphores)
+                .wait_dst_stage_mask(wait_mask)
+                .command_buffers(&command_buffers)
+                .signal_semaphores(signal_semaphores);
+
+            device.queue_submit(self.device.graphics_queue, &[submit_info.build()], frame.in_flight_fence).expect("Queue submit failed!");
+        }
+
+        if let Some(swapchain) = &self.device.swapchain {
+            match swapchain.present(self.device.graphics_queue, present_index, &[frame.render_finished_semaphore]) {
+                Ok(false) => {}
+                Ok(true) | Err(Result::ERROR_OUT_OF_DATE_KHR) => self.swapchain_outdated = true,
+                Err(e) => panic!("Present failed: {}", e),
+            }
+        }
+
+        self.frame_index = (self.frame_index + 1) % self.frames.len();
+    }
+
+    fn get_cull_stats(&self) -> CullStats {
+        self.cull_stats
+    }
+
+    fn read_frame(&self) -> Option<RenderImage> {
+        let target = self.offscreen_target.as_ref()?;
+        let device = &self.device.device;
+        for frame in self.frames.iter() {
+            frame.wait(device);
+        }
+        Some(target.read_pixels())
+    }
+
+    fn recreate_pipelines(&mut self, window_width: u32, window_height: u32) {
+        // The swapchain is recreated lazily with the next frame, so a burst of resize events only recreates it once.
+        self.window_size = Extent2D { width: window_width, height: window_height };
+        self.swapchain_outdated = true;
+    }
+
+    fn dispose(&mut self) {
+        Log::get().write("Disposing renderer");
+        let device = &self.device.device;
+        unsafe {
+            device.device_wait_idle().expect("Wait for device idle failed!");
+
+            for frame in self.frames.iter() {
+                frame.destroy(device, &mut self.allocator);
+            }
+            device.destroy_command_pool(self.command_pool, None);
+            for pipeline in self.pipelines.iter() {
+                pipeline.destroy(device);
+            }
+            self.pipeline_cache.save(device);
+            self.pipeline_cache.destroy(device);
+            self.descriptor_allocator.destroy(device);
+            self.camera_set_layout.destroy(device);
+            self.materials.destroy(device, &mut self.allocator);
+            for framebuffer in self.framebuffers.iter() {
+                device.destroy_framebuffer(*framebuffer, None);
+            }
+            self.depth_image.destroy(device, &mut self.allocator);
+            if let Some(target) = &self.offscreen_target {
+                target.destroy(device, &mut self.allocator);
+            }
+            self.allocator.log_statistics();
+            self.allocator.destroy(device);
+            device.destroy_render_pass(self.render_pass, None);
+        }
+        self.device.destroy();
+        self.instance.destroy();
+    }
+}
+
+impl VkRenderer {
+    pub fn is_available() -> bool {
+        VkInstance::is_vulkan_available()
+    }
+
+    fn create(instance: VkInstance, window_size: Extent2D, settings: &RenderingIniData, assets: Arc<AssetManager>) -> Self {
         let physical_device_name = &instance.selected_physical_device.name;
         let gpu_name = physical_device_name.clone();
 
-
-        let window_size = Extent2D { width: window.inner_size().width, height: window.inner_size().height };
         let device;
         match VkLogicalDevice::new(&instance, &window_size).resolve(){
             Some(logical_device) => device = logical_device,
             None => panic!("Creation failed!")
         }
+
+        let physical_device = &instance.selected_physical_device;
+        let mut allocator = VkAllocator::new(physical_device);
+        let offscreen_target = match device.swapchain {
+            Some(_) => None,
+            None => Some(VkOffscreenTarget::new(&mut allocator, &device.device, window_size)),
+        };
+        let (extent, color_format, color_final_layout, color_image_views) = VkRenderer::get_color_attachments(&device, offscreen_target.as_ref());
+
+        let render_pass = VkRenderer::create_render_pass(&device.device, color_format, color_final_layout);
+        let depth_image = VkImage::new(&mut allocator, &device.device, extent,
+                                       DEPTH_FORMAT, ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT, ImageAspectFlags::DEPTH);
+        let framebuffers = VkRenderer::create_framebuffers(&device.device, &color_image_views, &depth_image.image_view, &render_pass, &extent);
+        let shader_path = PathBuf::from(&settings.shader_path);
+        let camera_set_layout = VkDescriptorSetLayout::new(&device.device, &[DescriptorBinding {
+            binding: CAMERA_BINDING,
+            descriptor_type: DescriptorType::UNIFORM_BUFFER,
+            count: 1,
+            stages: ShaderStageFlags::VERTEX,
+        }]);
+        let mut descriptor_allocator = VkDescriptorAllocator::default();
+        let command_pool = VkRenderer::create_command_pool(&device.device, device.graphics_queue_family_index);
+        let materials = VkMaterialResources::new(&instance, &device, &mut allocator, &command_pool, settings, assets.clone());
+        let pipeline_cache = VkPipelineCache::new(physical_device, &device.device);
+        let set_layouts = [camera_set_layout.layout, materials.set_layout.layout];
+        let pipelines = VkRenderer::create_pipelines(&device, &render_pass, &assets, &shader_path, &pipeline_cache, &set_layouts);
+        let shader_watcher = ShaderWatcher::new(&shader_path);
+
+        let frames_in_flight = settings.frames_in_flight.max(1);
+        Log::get().write(format!("Rendering with {} frames in flight", frames_in_flight).as_str());
+        let frames = (0..frames_in_flight)
+            .map(|_| VkFrame::new(&mut allocator, &device.device, &command_pool, &mut descriptor_allocator, &camera_set_layout))
+            .collect();
+        let images_in_flight = vec![Fence::null(); color_image_views.len()];
+
         VkRenderer {
             instance,
             gpu_name,
             device,
+            offscreen_target,
+            window_size,
+            extent,
+            color_format,
+            swapchain_outdated: false,
+            render_pass,
+            depth_image,
+            framebuffers,
+            pipelines,
+            pipeline_cache,
+            camera_set_layout,
+            materials,
+            lod_pixel_error: settings.lod_pixel_error,
+            cull_stats: CullStats::default(),
+            descriptor_allocator,
+            allocator,
+            assets,
+            shader_path,
+            shader_watcher,
+            pending_pipelines: Vec::new(),
+            command_pool,
+            frames,
+            frame_index: 0,
+            images_in_flight,
         }
     }
 
-    fn get_gpu_name(&self) -> &str {
-        let name = self.gpu_name.as_str();
-        name
+    /// Looks up a pipeline by the name of its description. Falls back to the default pipeline if the name is unknown.
+    fn find_pipeline<'a>(pipelines: &'a [VkGraphicsPipeline], name: &str) -> &'a VkGraphicsPipeline {
+        pipelines.iter()
+            .find(|pipeline| pipeline.get_description().name == name)
+            .or_else(|| pipelines.iter().find(|pipeline| pipeline.get_description().name == DEFAULT_PIPELINE))
+            .unwrap_or(&pipelines[0])
     }
 
-    fn render(&self, meshes: &[Mesh]) {
+    /// All pipelines the renderer offers. Wireframe rendering is skipped if the device does not support it.
+    fn get_pipeline_descriptions(device: &VkLogicalDevice) -> Vec<VkPipelineDescription> {
+        let mut descriptions = vec![
+            VkPipelineDescription::opaque(DEFAULT_PIPELINE),
+            VkPipelineDescription::transparent("transparent"),
+            VkPipelineDescription::additive("additive"),
+            VkPipelineDescription::ui("ui"),
+        ];
+        if device.enabled_features.fill_mode_non_solid != 0 {
+            descriptions.push(VkPipelineDescription::wireframe("wireframe"));
+        }
+        descriptions
+    }
 
-        // println!("Call vulkan render func!");
+    /// Creates every pipeline with the same set layouts: the camera set followed by the material set.
+    /// The shaders of all pipelines are loaded in parallel, the first frame needs all of them.
+    fn create_pipelines(device: &VkLogicalDevice, render_pass: &RenderPass, assets: &AssetManager, shader_path: &Path, pipeline_cache: &VkPipelineCache,
+                        set_layouts: &[DescriptorSetLayout]) -> Vec<VkGraphicsPipeline> {
+        let descriptions = VkRenderer::get_pipeline_descriptions(device);
+        let shaders: Vec<Vec<Handle<ShaderCode>>> = descriptions.iter()
+            .map(|description| VkGraphicsPipeline::load_shaders(assets, description, shader_path))
+            .collect();
+        descriptions.iter().zip(shaders.iter())
+            .map(|(description, shaders)| VkGraphicsPipeline::new(&device.device, render_pass, set_layouts, description, shaders, &pipeline_cache.cache)
+                .resolve().expect("Creation of graphics pipeline failed!"))
+            .collect()
     }
 
-    fn recreate_pipelines(&self, window_width: u32, window_height: u32) {
-        println!("Recreate vulkan pipelines");
+    /// Starts loading the shaders changed on disk since the last frame and rebuilds every pipeline whose shaders have finished loading.
+    /// If a changed shader is broken, the error is logged and the old pipeline stays in use.
+    fn reload_changed_shaders(&mut self) {
+        if let Some(watcher) = &self.shader_watcher {
+            let changed_files = watcher.get_changed_files();
+            for (index, pipeline) in self.pipelines.iter().enumerate() {
+                if !changed_files.iter().any(|file_name| pipeline.uses_shader(file_name)) {
+                    continue;
+                }
+                // The handles of an older change are dropped first, otherwise the asset manager would hand out their code again.
+                self.pending_pipelines.retain(|pending| pending.index != index);
+                let shaders = VkGraphicsPipeline::load_shaders(&self.assets, pipeline.get_description(), &self.shader_path);
+                self.pending_pipelines.push(PendingPipeline { index, shaders });
+            }
+        }
+
+        let (loaded, loading): (Vec<PendingPipeline>, Vec<PendingPipeline>) = self.pending_pipelines.drain(..)
+            .partition(|pending| pending.shaders.iter().all(|shader| !matches!(shader.get_state(), AssetState::Loading)));
+        self.pending_pipelines = loading;
+        if loaded.is_empty() {
+            return;
+        }
+
+        let device = &self.device.device;
+        unsafe { device.device_wait_idle().expect("Wait for device idle failed!") };
+        let set_layouts = [self.camera_set_layout.layout, self.materials.set_layout.layout];
+        for pending in loaded {
+            let pipeline = &mut self.pipelines[pending.index];
+            let description = pipeline.get_description();
+            Log::get().write(format!("Shaders changed, rebuilding pipeline {}", description.name).as_str());
+            match VkGraphicsPipeline::new(device, &self.render_pass, &set_layouts, description, &pending.shaders, &self.pipeline_cache.cache) {
+                ResaResult::Ok(new_pipeline) => {
+                    pipeline.destroy(device);
+                    *pipeline = new_pipeline;
+                }
+                ResaResult::Err() => Log::get().write_error(format!("Reloading the shaders of pipeline {} failed, the previous pipeline is kept!", description.name).as_str()),
+            }
+        }
     }
 
-    fn dispose(&self) {
-        Log::get().write("Disposing renderer");
-        self.device.destroy();
-        self.instance.destroy();
+    /// Returns extent, format, final layout and image views of the color attachments,
+    /// which are either the swapchain images or the offscreen target.
+    fn get_color_attachments(device: &VkLogicalDevice, offscreen_target: Option<&VkOffscreenTarget>) -> (Extent2D, Format, ImageLayout, Vec<ImageView>) {
+        match (&device.swapchain, offscreen_target) {
+            (Some(swapchain), _) => (swapchain.extent, swapchain.surface_format.format, ImageLayout::PRESENT_SRC_KHR, swapchain.image_views.clone()),
+            (None, Some(target)) => (target.extent, OFFSCREEN_COLOR_FORMAT, ImageLayout::TRANSFER_SRC_OPTIMAL, vec![target.color_image.image_view]),
+            (None, None) => panic!("Renderer has neither a swapchain nor an offscreen target!"),
+        }
     }
-}
\ No newline at end of file
+
+    /// Recreates the swapchain (or the offscreen target) together with everything that depends on its size:
+    /// image views, depth buffer and framebuffers. Render pass and pipeline are only rebuilt if the surface format changed.
+    /// While the window is minimized nothing is recreated and the renderer skips its frames.
+    fn recreate_swapchain(&mut self) {
+        let window_size = self.window_size;
+        if window_size.width == 0 || window_size.height == 0 {
+            self.swapchain_outdated = true;
+            return;
+        }
+
+        Log::get().write(format!("Recreate swapchain with size {}x{}", window_size.width, window_size.height).as_str());
+        unsafe { self.device.device.device_wait_idle().expect("Wait for device idle failed!") };
+
+        if let Some(target) = self.offscreen_target.take() {
+            target.destroy(&self.device.device, &mut self.allocator);
+            self.offscreen_target = Some(VkOffscreenTarget::new(&mut self.allocator, &self.device.device, window_size));
+        } else if self.device.recreate_swapchain(&self.instance, &window_size).resolve().is_none() {
+            // Retry with the next frame, the old depth image and framebuffers are cleaned up then.
+            self.swapchain_outdated = true;
+            return;
+        }
+
+        let device = &self.device.device;
+        unsafe {
+            for framebuffer in self.framebuffers.drain(..) {
+                device.destroy_framebuffer(framebuffer, None);
+            }
+        }
+        self.depth_image.destroy(device, &mut self.allocator);
+
+        let (extent, color_format, color_final_layout, color_image_views) = VkRenderer::get_color_attachments(&self.device, self.offscreen_target.as_ref());
+        if color_format != self.color_format {
+            for pipeline in self.pipelines.iter() {
+                pipeline.destroy(device);
+            }
+            unsafe { device.destroy_render_pass(self.render_pass, None) };
+            self.render_pass = VkRenderer::create_render_pass(device, color_format, color_final_layout);
+            let set_layouts = [self.camera_set_layout.layout, self.materials.set_layout.layout];
+            self.pipelines = VkRenderer::create_pipelines(&self.device, &self.render_pass, &self.assets, &self.shader_path, &self.pipeline_cache, &set_layouts);
+            self.color_format = color_format;
+        }
+
+        self.depth_image = VkImage::new(&mut self.allocator, device, extent,
+                                        DEPTH_FORMAT, ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT, ImageAspectFlags::DEPTH);
+        self.framebuffers = VkRenderer::create_framebuffers(device, &color_image_views, &self.depth_image.image_view, &self.render_pass, &extent);
+        self.images_in_flight = vec![Fence::null(); color_image_views.len()];
+        self.extent = extent;
+        self.swapchain_outdated = false;
+    }
+
+    /// Copies the vertices, indices and material parameters of every item into host visible buffers
+    /// and points the material set of every item at its parameters and textures. `lod_indices` holds the indices to draw every item with, those of the level of detail picked for the camera.
+    /// Buffers are kept between frames and only recreated if a mesh no longer fits into them.
+    fn upload_meshes(allocator: &mut VkAllocator, device: &Device, descriptor_allocator: &mut VkDescriptorAllocator, materials: &VkMaterialResources,
+                     mesh_buffers: &mut Vec<MeshBuffers>, items: &[&RenderItem], lod_indices: &[&[u32]]) {
+        for (i, (item, indices)) in items.iter().zip(lod_indices.iter()).enumerate() {
+            let mesh = &item.mesh;
+            let material = item.material.as_ref().unwrap_or(&materials.default_material);
+            let mut material_data = material.get_uniform_data();
+            if material_data.is_empty() {
+                // The default shaders always read a base color, materials without parameters are drawn white.
+                material_data.push([1.0; 4]);
+            }
+
+            let vertex_size = (mem::size_of::<Vertex>() * mesh.vertices.len()) as DeviceSize;
+            let index_size = (mem::size_of::<u32>() * indices.len().max(1)) as DeviceSize;
+            let material_size = mem::size_of_val(material_data.as_slice()) as DeviceSize;
+
+            if i == mesh_buffers.len() {
+                mesh_buffers.push(MeshBuffers {
+                    vertex_buffer: VkRenderer::create_host_buffer(allocator, device, vertex_size, BufferUsageFlags::VERTEX_BUFFER),
+                    index_buffer: VkRenderer::create_host_buffer(allocator, device, index_size, BufferUsageFlags::INDEX_BUFFER),
+                    index_count: 0,
+                    material_buffer: VkRenderer::create_host_buffer(allocator, device, material_size, BufferUsageFlags::UNIFORM_BUFFER),
+                    material_descriptor_set: descriptor_allocator.allocate(device, &materials.set_layout),
+                });
+            }
+
+            let buffers = &mut mesh_buffers[i];
+            if buffers.vertex_buffer.size < vertex_size {
+                buffers.vertex_buffer.destroy(device, allocator);
+                buffers.vertex_buffer = VkRenderer::create_host_buffer(allocator, device, vertex_size, BufferUsageFlags::VERTEX_BUFFER);
+            }
+            if buffers.index_buffer.size < index_size {
+                buffers.index_buffer.destroy(device, allocator);
+                buffers.index_buffer = VkRenderer::create_host_buffer(allocator, device, index_size, BufferUsageFlags::INDEX_BUFFER);
+            }
+
+            if buffers.material_buffer.size < material_size {
+                buffers.material_buffer.destroy(device, allocator);
+                buffers.material_buffer = VkRenderer::create_host_buffer(allocator, device, material_size, BufferUsageFlags::UNIFORM_BUFFER);
+            }
+            // The set is not in use, the frame it belongs to has finished. The item at this index may have another material than last time.
+            materials.write_descriptor_set(device, buffers.material_descriptor_set, &buffers.material_buffer, material);
+
+            buffers.vertex_buffer.upload(&mesh.vertices);
+            buffers.index_buffer.upload(indices);
+            buffers.index_count = indices.len() as u32;
+            buffers.material_buffer.upload(&material_data);
+        }
+    }
+
+    fn create_host_buffer(allocator: &mut VkAllocator, device: &Device, size: DeviceSize, usage: BufferUsageFlags) -> VkBuffer {
+        VkBuffer::new(allocator, device, size, usage, MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT, AllocationStrategy::FreeList)
+    }
+
+    fn create_render_pass(device: &Device, color_format: Format, color_final_layout: ImageLayout) -> RenderPass {
+        let renderpass_attachments = [
+            AttachmentDescription {
+                format: color_format,
+                samples: SampleCountFlags::TYPE_1,
+                load_op: AttachmentLoadOp::CLEAR,
+                store_op: AttachmentStoreOp::STORE,
+                initial_layout: ImageLayout::UNDEFINED,
+                final_layout: color_final_layout,
+                ..Default::default()
+            },
+            AttachmentDescription {
+                format: DEPTH_FORMAT,
+                samples: SampleCountFlags::TYPE_1,
+                load_op: AttachmentLoadOp::CLEAR,
+                initial_layout: ImageLayout::UNDEFINED,
+                final_layout: ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+                ..Default::default()
+            },
+        ];
+
+        let color_attachment_refs = [AttachmentReference {
+            attachment: 0,
+            layout: ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        }];
+
+        let depth_attachment_ref = AttachmentReference { attachment: 1, layout: ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL };
+
+        let dependencies = [
+            // Waits for the attachment writes of the previous frame in flight before clearing them.
+            SubpassDependency {
+                src_subpass: SUBPASS_EXTERNAL,
+                src_stage_mask: PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT | PipelineStageFlags::EARLY_FRAGMENT_TESTS | PipelineStageFlags::LATE_FRAGMENT_TESTS,
+                src_access_mask: AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                dst_access_mask: AccessFlags::COLOR_ATTACHMENT_READ | AccessFlags::COLOR_ATTACHMENT_WRITE | AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                dst_stage_mask: PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT | PipelineStageFlags::EARLY_FRAGMENT_TESTS,
+                ..Default::default()
+            },
+            // Makes the color writes visible to a following readback copy of offscreen targets.
+            SubpassDependency {
+                src_subpass: 0,
+                dst_subpass: SUBPASS_EXTERNAL,
+                src_stage_mask: PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                src_access_mask: AccessFlags::COLOR_ATTACHMENT_WRITE,
+                dst_stage_mask: PipelineStageFlags::TRANSFER,
+                dst_access_mask: AccessFlags::TRANSFER_READ,
+                ..Default::default()
+            },
+        ];
+
+        let subpass = SubpassDescription::builder()
+            .color_attachments(&color_attachment_refs)
+            .depth_stencil_attachment(&depth_attachment_ref)
+            .pipeline_bind_point(PipelineBindPoint::GRAPHICS);
+
+        let renderpass_create_info = RenderPassCreateInfo::builder()
+            .attachments(&renderpass_attachments)
+            .subpasses(std::slice::from_ref(&subpass))
+            .dependencies(&dependencies);
+
+        unsafe { device.create_render_pass(&renderpass_create_info, None).expect("Render pass creation failed!") }
+    }
+
+    fn create_framebuffers(device: &Device, image_views: &[ImageView], depth_image_view: &ImageView, render_pass: &RenderPass, extent: &Extent2D) -> Vec<Framebuffer> {
+        image_views.iter()
+            .map(|&image_view| {
+                let framebuffer_attachments = [image_view, *depth_image_view];
+                let framebuffer_create_info = FramebufferCreateInfo::builder()
+                    .render_pass(*render_pass)
+                    .attachments(&framebuffer_attachments)
+                    .width(extent.width)
+                    .height(extent.height)
+                    .layers(1);
+
+                unsafe { device.create_framebuffer(&framebuffer_create_info, None).expect("Failed to create framebuffer!") }
+            })
+            .collect()
+    }
+
+    fn create_command_pool(device: &Device, queue_family_index: u32) -> CommandPool {
+        let pool_create_info = CommandPoolCreateInfo::builder()
+            .flags(CommandPoolCreateFlags::RESET_COMMAND_BUFFER)
+            .queue_family_index(queue_family_index);
+
+        unsafe { device.create_command_pool(&pool_create_info, None).expect("Creating command pool failed!") }
+    }
+
+}
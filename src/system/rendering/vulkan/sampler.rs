@@ -0,0 +1,50 @@
+use ash::Device;
+use ash::vk::{BorderColor, Filter, LOD_CLAMP_NONE, Sampler, SamplerAddressMode, SamplerCreateInfo, SamplerMipmapMode};
+use crate::system::ini::{RenderingIniData, TextureFilter, TextureWrap};
+use crate::system::log::Log;
+
+pub struct VkSampler {
+    pub sampler: Sampler,
+}
+
+impl VkSampler {
+    /// Creates a sampler with the filter, wrap mode and anisotropy of the settings. It samples every mip level a texture has.
+    /// The anisotropy is clamped to `max_anisotropy`, which is zero if the device does not support anisotropic filtering.
+    pub fn new(device: &Device, settings: &RenderingIniData, max_anisotropy: f32) -> Self {
+        let (filter, mipmap_mode) = match settings.texture_filter {
+            TextureFilter::Nearest => (Filter::NEAREST, SamplerMipmapMode::NEAREST),
+            TextureFilter::Linear => (Filter::LINEAR, SamplerMipmapMode::LINEAR),
+        };
+        let address_mode = match settings.texture_wrap {
+            TextureWrap::Repeat => SamplerAddressMode::REPEAT,
+            TextureWrap::MirroredRepeat => SamplerAddressMode::MIRRORED_REPEAT,
+            TextureWrap::ClampToEdge => SamplerAddressMode::CLAMP_TO_EDGE,
+        };
+        let anisotropy = settings.anisotropy.min(max_anisotropy);
+        if settings.anisotropy > 1.0 && anisotropy < settings.anisotropy {
+            Log::get().write_warning(format!("Anisotropy {} is not supported, using {}", settings.anisotropy, anisotropy.max(1.0)).as_str());
+        }
+
+        let create_info = SamplerCreateInfo::builder()
+            .mag_filter(filter)
+            .min_filter(filter)
+            .mipmap_mode(mipmap_mode)
+            .address_mode_u(address_mode)
+            .address_mode_v(address_mode)
+            .address_mode_w(address_mode)
+            .anisotropy_enable(anisotropy > 1.0)
+            .max_anisotropy(anisotropy.max(1.0))
+            .min_lod(0.0)
+            .max_lod(LOD_CLAMP_NONE)
+            .border_color(BorderColor::INT_OPAQUE_BLACK);
+        let sampler = unsafe { device.create_sampler(&create_info, None).expect("Sampler creation failed!") };
+
+        VkSampler {
+            sampler,
+        }
+    }
+
+    pub fn destroy(&self, device: &Device) {
+        unsafe { device.destroy_sampler(self.sampler, None) };
+    }
+}
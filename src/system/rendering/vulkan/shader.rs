@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
+use std::path::Path;
+use std::sync::mpsc::{channel, Receiver};
+use ash::Device;
+use ash::vk::{ShaderModule, ShaderModuleCreateInfo};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use crate::system::log::Log;
+
+/// Words before the first instruction: magic number, version, generator, id bound and schema.
+const SPIRV_HEADER_SIZE: usize = 5;
+const OP_TYPE_INT: u32 = 21;
+const OP_TYPE_FLOAT: u32 = 22;
+const OP_TYPE_VECTOR: u32 = 23;
+const OP_TYPE_POINTER: u32 = 32;
+const OP_VARIABLE: u32 = 59;
+const OP_DECORATE: u32 = 71;
+const DECORATION_LOCATION: u32 = 30;
+const STORAGE_CLASS_INPUT: u32 = 1;
+
+/// Numeric type of a shader input, as far as the vertex input interface cares.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum NumericType {
+    Float,
+    SInt,
+    UInt,
+}
+
+/// User defined input variable of a shader stage. Built-in inputs are not part of the interface and never reported.
+pub struct ShaderInput {
+    pub location: u32,
+    pub numeric_type: NumericType,
+    pub components: u32,
+}
+
+pub fn create_shader_module(device: &Device, path: &Path, shader_code: &[u32]) -> Option<ShaderModule> {
+    let shader_info = ShaderModuleCreateInfo::builder().code(shader_code);
+    match unsafe { device.create_shader_module(&shader_info, None) } {
+        Ok(shader_module) => Some(shader_module),
+        Err(e) => {
+            Log::get().write_error(format!("Unable to create shader module from {}: {}", path.display(), e).as_str());
+            None
+        }
+    }
+}
+
+/// Collects the scalar and vector input variables with an explicit location from the SPIR-V code.
+/// Inputs of other types, like matrices or structs, are skipped.
+pub fn get_shader_inputs(shader_code: &[u32]) -> Vec<ShaderInput> {
+    let mut locations: HashMap<u32, u32> = HashMap::new();
+    let mut numeric_types: HashMap<u32, (NumericType, u32)> = HashMap::new();
+    let mut input_pointers: HashMap<u32, u32> = HashMap::new();
+    let mut input_variables: Vec<(u32, u32)> = Vec::new();
+
+    let mut position = SPIRV_HEADER_SIZE;
+    while position < shader_code.len() {
+        let word_count = (shader_code[position] >> 16) as usize;
+        let opcode = shader_code[position] & 0xffff;
+        if word_count == 0 || position + word_count > shader_code.len() {
+            break;
+        }
+        let operands = &shader_code[position + 1..position + word_count];
+
+        match (opcode, operands) {
+            (OP_DECORATE, [target, DECORATION_LOCATION, location, ..]) => {
+                locations.insert(*target, *location);
+            }
+            (OP_TYPE_INT, [result, _, signedness, ..]) => {
+                let numeric_type = if *signedness == 1 { NumericType::SInt } else { NumericType::UInt };
+                numeric_types.insert(*result, (numeric_type, 1));
+            }
+            (OP_TYPE_FLOAT, [result, ..]) => {
+                numeric_types.insert(*result, (NumericType::Float, 1));
+            }
+            (OP_TYPE_VECTOR, [result, component_type, component_count, ..]) => {
+                if let Some((numeric_type, _)) = numeric_types.get(component_type).copied() {
+                    numeric_types.insert(*result, (numeric_type, *component_count));
+                }
+            }
+            (OP_TYPE_POINTER, [result, STORAGE_CLASS_INPUT, pointee, ..]) => {
+                input_pointers.insert(*result, *pointee);
+            }
+            (OP_VARIABLE, [result_type, result, STORAGE_CLASS_INPUT, ..]) => {
+                input_variables.push((*result, *result_type));
+            }
+            _ => {}
+        }
+        position += word_count;
+    }
+
+    let mut inputs: Vec<ShaderInput> = input_variables.iter()
+        .filter_map(|(variable, pointer_type)| {
+            let location = *locations.get(variable)?;
+            let (numeric_type, components) = *numeric_types.get(input_pointers.get(pointer_type)?)?;
+            Some(ShaderInput { location, numeric_type, components })
+        })
+        .collect();
+    inputs.sort_by_key(|input| input.location);
+    inputs
+}
+
+/// Watches the shader directory for changed .spv files.
+/// The file system events arrive on a background thread and are collected until the renderer asks for them.
+pub struct ShaderWatcher {
+    _watcher: RecommendedWatcher,
+    receiver: Receiver<OsString>,
+}
+
+impl ShaderWatcher {
+    pub fn new(shader_path: &Path) -> Option<Self> {
+        let (sender, receiver) = channel();
+        let watcher = notify::recommended_watcher(move |result: notify::Result<Event>| {
+            let event = match result {
+                Ok(event) => event,
+                Err(_) => return,
+            };
+            if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                return;
+            }
+            for path in event.paths.iter().filter(|path| path.extension() == Some(OsStr::new("spv"))) {
+                if let Some(file_name) = path.file_name() {
+                    // The receiver is gone once the renderer is disposed, there is nobody left to notify.
+                    let _ = sender.send(file_name.to_os_string());
+                }
+            }
+        });
+
+        let mut watcher = match watcher {
+            Ok(watcher) => watcher,
+            Err(e) => {
+                Log::get().write_warning(format!("Shader hot reload is disabled, creating the file watcher failed: {}", e).as_str());
+                return None;
+            }
+        };
+        if let Err(e) = watcher.watch(shader_path, RecursiveMode::NonRecursive) {
+            Log::get().write_warning(format!("Shader hot reload is disabled, unable to watch {}: {}", shader_path.display(), e).as_str());
+            return None;
+        }
+
+        Some(ShaderWatcher {
+            _watcher: watcher,
+            receiver,
+        })
+    }
+
+    /// Returns the file names of all shaders that changed since the last call. Every name is only reported once.
+    pub fn get_changed_files(&self) -> Vec<OsString> {
+        let mut changed_files: Vec<OsString> = Vec::new();
+        for file_name in self.receiver.try_iter() {
+            if !changed_files.contains(&file_name) {
+                changed_files.push(file_name);
+            }
+        }
+        changed_files
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::assets::shader::ShaderCode;
+
+    #[test]
+    fn inputs_of_the_default_vertex_shader_are_found() {
+        let shader = ShaderCode::load(Path::new("assets/shaders/vert.spv")).unwrap();
+        let inputs: Vec<(u32, NumericType, u32)> = get_shader_inputs(&shader.code).iter()
+            .map(|input| (input.location, input.numeric_type, input.components))
+            .collect();
+        // The push constant block and the camera uniforms are no inputs.
+        assert_eq!(inputs, vec![(0, NumericType::Float, 4), (1, NumericType::Float, 2)]);
+    }
+}
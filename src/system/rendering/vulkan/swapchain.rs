@@ -1,80 +1,206 @@
-use ash::{Instance, Device};
+use ash::Device;
 use ash::extensions::khr::{Surface, Swapchain};
-use ash::vk::{CompositeAlphaFlagsKHR, Extent2D, ImageUsageFlags, PhysicalDevice, PresentModeKHR, SharingMode, SurfaceFormatKHR, SurfaceKHR, SurfaceTransformFlagsKHR, SwapchainCreateInfoKHR, SwapchainKHR};
-use winit::dpi::PhysicalSize;
+use ash::prelude::VkResult;
+use ash::vk::{ColorSpaceKHR, ComponentMapping, ComponentSwizzle, CompositeAlphaFlagsKHR, Extent2D, Fence, Format, Image, ImageAspectFlags, ImageSubresourceRange, ImageUsageFlags, ImageView, ImageViewCreateInfo, ImageViewType, PhysicalDevice, PresentInfoKHR, PresentModeKHR, Queue, Result, Semaphore, SharingMode, SurfaceFormatKHR, SurfaceKHR, SurfaceTransformFlagsKHR, SwapchainCreateInfoKHR, SwapchainKHR};
+use crate::system::rendering::vulkan::global::{VkInstance, VkSurface};
 
-pub struct VkSwapchain{
-    pub vk_swapchain: SwapchainKHR,
-    pub swapchain_loader: Swapchain,
+pub struct VkSwapchain {
+    swapchain_loader: Swapchain,
+    swapchain: SwapchainKHR,
+    pub image_views: Vec<ImageView>,
     pub surface_format: SurfaceFormatKHR,
-    pub surface_resolution: Extent2D,
-
+    pub extent: Extent2D,
 }
 
-impl VkSwapchain{
-    pub fn new(instance: &Instance, pdevice: &PhysicalDevice, device: &Device, surface: &SurfaceKHR, surface_loader: &Surface, window_size: &PhysicalSize<u32>)-> Self{
-        let surface_format = unsafe { surface_loader.get_physical_device_surface_formats(*pdevice, *surface).unwrap()[0] };
+impl VkSwapchain {
+    pub fn new(instance: &VkInstance, surface: &VkSurface, device: &Device, window_size: &Extent2D) -> VkResult<VkSwapchain> {
+        let swapchain_loader = Swapchain::new(&instance.instance, device);
+        let (swapchain, surface_format, extent) = VkSwapchain::create_swapchain(&instance.selected_physical_device.physical_device, &swapchain_loader,
+                                                                                &surface.loader, surface.handle, window_size)?;
 
-        let surface_capabilities = unsafe { surface_loader.get_physical_device_surface_capabilities(*pdevice, *surface).unwrap() };
-        let mut desired_image_count = surface_capabilities.min_image_count + 1;
-        if surface_capabilities.max_image_count > 0 && desired_image_count > surface_capabilities.max_image_count {
-            desired_image_count = surface_capabilities.max_image_count;
-        }
+        let swapchain_images = unsafe { swapchain_loader.get_swapchain_images(swapchain)? };
+        let image_views = VkSwapchain::create_swapchain_image_views(device, &swapchain_images, surface_format.format);
 
-        let surface_resolution = match surface_capabilities.current_extent.width {
-            u32::MAX => Extent2D {
-                width: window_size.width,
-                height: window_size.height,
-            },
-            _ => surface_capabilities.current_extent,
-        };
-
-
-        let mut pre_transform: SurfaceTransformFlagsKHR;
-        if surface_capabilities.supported_transforms.contains(SurfaceTransformFlagsKHR::IDENTITY)
-        {
-            pre_transform = SurfaceTransformFlagsKHR::IDENTITY;
-        } else { pre_transform = surface_capabilities.current_transform };
-
-        let present_modes = unsafe {
-            surface_loader.get_physical_device_surface_present_modes(*pdevice, *surface)
-                .expect("Present modes loading failed!")
-        };
-
-        let present_mode = present_modes
-            .iter()
-            .cloned()
-            .find(|&mode| mode == PresentModeKHR::MAILBOX)
-            .unwrap_or(PresentModeKHR::FIFO);
-
-        let swapchain_loader = Swapchain::new(instance, device);
-        let swapchain_create_info = SwapchainCreateInfoKHR::builder()
-            .surface(*surface)
-            .min_image_count(desired_image_count)
-            .image_color_space(surface_format.color_space)
-            .image_format(surface_format.format)
-            .image_extent(surface_resolution)
-            .image_usage(ImageUsageFlags::COLOR_ATTACHMENT)
-            .image_sharing_mode(SharingMode::EXCLUSIVE)
-            .pre_transform(pre_transform)
-            .composite_alpha(CompositeAlphaFlagsKHR::OPAQUE)
-            .present_mode(present_mode)
-            .clipped(true)
-            .image_array_layers(1);
-
-        let vk_swapchain = unsafe { swapchain_loader.create_swapchain(&swapchain_create_info, None).expect("Swapchain creation failed!") };
-
-        VkSwapchain{
-            vk_swapchain,
+        Ok(VkSwapchain {
             swapchain_loader,
+            swapchain,
+            image_views,
             surface_format,
-            surface_resolution,
+            extent,
+        })
+    }
+
+    pub fn destroy(&self, device: &Device) {
+        unsafe {
+            for image_view in self.image_views.iter() {
+                device.destroy_image_view(*image_view, None);
+            }
+            self.swapchain_loader.destroy_swapchain(self.swapchain, None);
+        }
+    }
+
+    pub fn acquire_next_image(&self, signal_semaphore: Semaphore) -> VkResult<(u32, bool)> {
+        unsafe { self.swapchain_loader.acquire_next_image(self.swapchain, u64::MAX, signal_semaphore, Fence::null()) }
+    }
+
+    pub fn present(&self, queue: Queue, image_index: u32, wait_semaphores: &[Semaphore]) -> VkResult<bool> {
+        let swapchains = [self.swapchain];
+        let image_indices = [image_index];
+        let present_info = PresentInfoKHR::builder()
+            .wait_semaphores(wait_semaphores)
+            .swapchains(&swapchains)
+            .image_indices(&image_indices);
+
+        unsafe { self.swapchain_loader.queue_present(queue, &present_info) }
+    }
+
+    fn create_swapchain_image_views(device: &Device, images: &[Image], format: Format) -> Vec<ImageView> {
+        images.iter()
+            .map(|&image| {
+                let create_view_info = ImageViewCreateInfo::builder()
+                    .view_type(ImageViewType::TYPE_2D)
+                    .format(format)
+                    .components(ComponentMapping {
+                        r: ComponentSwizzle::R,
+                        g: ComponentSwizzle::G,
+                        b: ComponentSwizzle::B,
+                        a: ComponentSwizzle::A,
+                    })
+                    .subresource_range(ImageSubresourceRange {
+                        aspect_mask: ImageAspectFlags::COLOR,
+                        base_mip_level: 0,
+                        level_count: 1,
+                        base_array_layer: 0,
+                        layer_count: 1,
+                    })
+                    .image(image);
+                unsafe { device.create_image_view(&create_view_info, None).expect("Creation of swapchain image view failed!") }
+            })
+            .collect()
+    }
+
+    fn create_swapchain(physical_device: &PhysicalDevice, swapchain_loader: &Swapchain, surface: &Surface, surface_handle: SurfaceKHR, window_size: &Extent2D) -> VkResult<(SwapchainKHR, SurfaceFormatKHR, Extent2D)> {
+        let physical = physical_device.clone();
+        let swapchain: VkResult<(SwapchainKHR, SurfaceFormatKHR, Extent2D)>;
+        unsafe {
+
+            // Get present mode
+            let present_modes =
+                surface.get_physical_device_surface_present_modes(physical,
+                                                                  surface_handle)
+                    .expect("Present modes loading failed!");
+
+            let present_mode = present_modes
+                .iter()
+                .cloned()
+                .find(|&mode| mode == PresentModeKHR::MAILBOX)
+                .unwrap_or(PresentModeKHR::FIFO);
+
+            // load surface capabilities
+            let surface_capabilities = surface
+                .get_physical_device_surface_capabilities(physical, surface_handle)
+                .unwrap();
+
+            let mut number_of_images: u32 = surface_capabilities.min_image_count + 1;
+            if surface_capabilities.max_image_count > 0 && number_of_images > surface_capabilities.max_image_count {
+                number_of_images = surface_capabilities.max_image_count;
+            }
+
+            // Define size of images
+            let mut size_of_images = match surface_capabilities.current_extent.width {
+                u32::MAX => window_size.clone(),
+                _ => surface_capabilities.current_extent,
+            };
+            if size_of_images.width < surface_capabilities.min_image_extent.width {
+                size_of_images.width = surface_capabilities.min_image_extent.width;
+            } else if size_of_images.width > surface_capabilities.max_image_extent.width {
+                size_of_images.width = surface_capabilities.max_image_extent.width;
+            }
+
+            if size_of_images.height < surface_capabilities.min_image_extent.height {
+                size_of_images.height = surface_capabilities.min_image_extent.height;
+            } else if size_of_images.height > surface_capabilities.max_image_extent.height {
+                size_of_images.height = surface_capabilities.max_image_extent.height;
+            }
+
+            // define image usages
+            let desired_usages = ImageUsageFlags::COLOR_ATTACHMENT;
+            let image_usage: ImageUsageFlags = desired_usages & surface_capabilities.supported_usage_flags;
+            let image_usages_available = desired_usages == image_usage;
+
+
+            // get surface transform values
+            let desired_transform = SurfaceTransformFlagsKHR::IDENTITY;
+            let mut surface_transform: SurfaceTransformFlagsKHR;
+            if surface_capabilities.supported_transforms.contains(desired_transform) {
+                surface_transform = desired_transform;
+            } else {
+                surface_transform = surface_capabilities.current_transform
+            }
+
+            // define image format
+            let supported_formats;
+            match surface.get_physical_device_surface_formats(physical, surface_handle) {
+                Ok(formats) => supported_formats = formats,
+                Err(e) => {
+                    println!("Failed to receive surface formats");
+                    return Err(Result::INCOMPLETE);
+                }
+            }
+
+            let desired_surface_format = SurfaceFormatKHR::builder()
+                .format(Format::A8B8G8R8_SRGB_PACK32)
+                .color_space(ColorSpaceKHR::EXTENDED_SRGB_LINEAR_EXT)
+                .build();
+
+            let format_and_color_space = VkSwapchain::get_supported_image_format_and_color_space(&supported_formats, desired_surface_format);
+            let image_format = format_and_color_space.0;
+            let image_color_space = format_and_color_space.1;
+
+            let swapchain_create_info = SwapchainCreateInfoKHR::builder()
+                .surface(surface_handle)
+                .min_image_count(number_of_images)
+                .image_format(image_format)
+                .image_color_space(image_color_space)
+                .image_extent(size_of_images)
+                .image_array_layers(1)
+                .image_usage(image_usage)
+                .image_sharing_mode(SharingMode::EXCLUSIVE)
+                .queue_family_indices(&[0])
+                .pre_transform(surface_transform)
+                .present_mode(present_mode)
+                .clipped(true)
+                .composite_alpha(CompositeAlphaFlagsKHR::OPAQUE);
+
+            let surface_format = SurfaceFormatKHR { format: image_format, color_space: image_color_space };
+            swapchain = swapchain_loader.create_swapchain(&swapchain_create_info, None)
+                .map(|handle| (handle, surface_format, size_of_images));
         }
+        swapchain
     }
+    fn get_supported_image_format_and_color_space(surface_formats: &Vec<SurfaceFormatKHR>, desired_format: SurfaceFormatKHR) -> (Format, ColorSpaceKHR) {
+        let format_and_color_space: (Format, ColorSpaceKHR);
+
+        if surface_formats.len() == 1 && surface_formats[0].format == Format::UNDEFINED {
+            format_and_color_space = (desired_format.format, desired_format.color_space);
+            return format_and_color_space;
+        }
 
-    pub fn destroy(&self){
-        unsafe{
-            self.swapchain_loader.destroy_swapchain(self.vk_swapchain, None);
+        for surface_format in surface_formats {
+            if surface_format.format == desired_format.format && surface_format.color_space == desired_format.color_space {
+                format_and_color_space = (desired_format.format, desired_format.color_space);
+                return format_and_color_space;
+            }
         }
+
+        for surface_format in surface_formats {
+            if surface_format.format == desired_format.format {
+                format_and_color_space = (desired_format.format, surface_format.color_space);
+                return format_and_color_space;
+            }
+        }
+
+        format_and_color_space = (surface_formats[0].format, surface_formats[0].color_space);
+        format_and_color_space
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,144 @@
+use ash::{Device, Instance};
+use ash::vk::{AccessFlags, BufferImageCopy, BufferUsageFlags, CommandBuffer, CommandBufferAllocateInfo, CommandBufferBeginInfo, CommandBufferLevel, CommandBufferUsageFlags, CommandPool, DependencyFlags, DeviceSize, Extent2D, Extent3D, Fence, FenceCreateInfo, Filter, Format, FormatFeatureFlags, ImageAspectFlags, ImageBlit, ImageLayout, ImageMemoryBarrier, ImageSubresourceLayers, ImageSubresourceRange, Image, ImageUsageFlags, MemoryPropertyFlags, Offset3D, PhysicalDevice, PipelineStageFlags, Queue, QUEUE_FAMILY_IGNORED, SubmitInfo};
+use crate::system::assets::texture::TextureImage;
+use crate::system::rendering::vulkan::allocator::{AllocationStrategy, VkAllocator};
+use crate::system::rendering::vulkan::buffer::VkBuffer;
+use crate::system::rendering::vulkan::image::VkImage;
+
+/// Format of every texture. Texels are passed on unchanged, like the unorm offscreen target and the software renderer do.
+pub const TEXTURE_FORMAT: Format = Format::R8G8B8A8_UNORM;
+
+/// Sampled image in device local memory.
+pub struct VkTexture {
+    pub image: VkImage,
+    pub mip_levels: u32,
+}
+
+impl VkTexture {
+    /// Uploads the image through a staging buffer and waits until the upload has finished.
+    /// With `generate_mipmaps` the full mip chain is created on the gpu by blitting every level from the one above.
+    pub fn new(allocator: &mut VkAllocator, device: &Device, command_pool: &CommandPool, queue: Queue, texture: &TextureImage, generate_mipmaps: bool) -> Self {
+        let mip_levels = if generate_mipmaps { texture.get_mip_level_count() } else { 1 };
+        let extent = Extent2D { width: texture.width, height: texture.height };
+        let image = VkImage::new_mipmapped(allocator, device, extent, TEXTURE_FORMAT,
+                                           ImageUsageFlags::TRANSFER_SRC | ImageUsageFlags::TRANSFER_DST | ImageUsageFlags::SAMPLED,
+                                           ImageAspectFlags::COLOR, mip_levels);
+
+        let staging_buffer = VkBuffer::new(allocator, device, texture.pixels.len() as DeviceSize, BufferUsageFlags::TRANSFER_SRC,
+                                           MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT, AllocationStrategy::FreeList);
+        staging_buffer.upload(&texture.pixels);
+
+        submit_once(device, command_pool, queue, |command_buffer| unsafe {
+            let all_levels = ImageSubresourceRange { aspect_mask: ImageAspectFlags::COLOR, base_mip_level: 0, level_count: mip_levels, base_array_layer: 0, layer_count: 1 };
+            let barrier = get_barrier(image.image, all_levels, ImageLayout::UNDEFINED, ImageLayout::TRANSFER_DST_OPTIMAL,
+                                      AccessFlags::empty(), AccessFlags::TRANSFER_WRITE);
+            device.cmd_pipeline_barrier(command_buffer, PipelineStageFlags::TOP_OF_PIPE, PipelineStageFlags::TRANSFER, DependencyFlags::empty(), &[], &[], &[barrier]);
+
+            let region = BufferImageCopy {
+                buffer_offset: 0,
+                buffer_row_length: 0,
+                buffer_image_height: 0,
+                image_subresource: get_layers(0),
+                image_offset: Offset3D::default(),
+                image_extent: Extent3D { width: texture.width, height: texture.height, depth: 1 },
+            };
+            device.cmd_copy_buffer_to_image(command_buffer, staging_buffer.buffer, image.image, ImageLayout::TRANSFER_DST_OPTIMAL, &[region]);
+
+            // Every level is a blit source for the next one and then moves on to be sampled.
+            for level in 1..mip_levels {
+                let source = get_level(level - 1);
+                let barrier = get_barrier(image.image, source, ImageLayout::TRANSFER_DST_OPTIMAL, ImageLayout::TRANSFER_SRC_OPTIMAL,
+                                          AccessFlags::TRANSFER_WRITE, AccessFlags::TRANSFER_READ);
+                device.cmd_pipeline_barrier(command_buffer, PipelineStageFlags::TRANSFER, PipelineStageFlags::TRANSFER, DependencyFlags::empty(), &[], &[], &[barrier]);
+
+                let blit = ImageBlit {
+                    src_subresource: get_layers(level - 1),
+                    src_offsets: [Offset3D::default(), get_level_size(texture, level - 1)],
+                    dst_subresource: get_layers(level),
+                    dst_offsets: [Offset3D::default(), get_level_size(texture, level)],
+                };
+                device.cmd_blit_image(command_buffer, image.image, ImageLayout::TRANSFER_SRC_OPTIMAL, image.image, ImageLayout::TRANSFER_DST_OPTIMAL, &[blit], Filter::LINEAR);
+
+                let barrier = get_barrier(image.image, source, ImageLayout::TRANSFER_SRC_OPTIMAL, ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                                          AccessFlags::TRANSFER_READ, AccessFlags::SHADER_READ);
+                device.cmd_pipeline_barrier(command_buffer, PipelineStageFlags::TRANSFER, PipelineStageFlags::FRAGMENT_SHADER, DependencyFlags::empty(), &[], &[], &[barrier]);
+            }
+
+            let barrier = get_barrier(image.image, get_level(mip_levels - 1), ImageLayout::TRANSFER_DST_OPTIMAL, ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                                      AccessFlags::TRANSFER_WRITE, AccessFlags::SHADER_READ);
+            device.cmd_pipeline_barrier(command_buffer, PipelineStageFlags::TRANSFER, PipelineStageFlags::FRAGMENT_SHADER, DependencyFlags::empty(), &[], &[], &[barrier]);
+        });
+
+        staging_buffer.destroy(device, allocator);
+        VkTexture {
+            image,
+            mip_levels,
+        }
+    }
+
+    /// Mip levels are blitted with a linear filter, which not every device supports for the texture format.
+    pub fn supports_mipmaps(instance: &Instance, physical_device: PhysicalDevice) -> bool {
+        let properties = unsafe { instance.get_physical_device_format_properties(physical_device, TEXTURE_FORMAT) };
+        properties.optimal_tiling_features.contains(FormatFeatureFlags::BLIT_SRC | FormatFeatureFlags::BLIT_DST | FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR)
+    }
+
+    pub fn destroy(&self, device: &Device, allocator: &mut VkAllocator) {
+        self.image.destroy(device, allocator);
+    }
+}
+
+/// Records the commands into a temporary command buffer, submits it and waits for the queue to finish it.
+fn submit_once<F: FnOnce(CommandBuffer)>(device: &Device, command_pool: &CommandPool, queue: Queue, record: F) {
+    let allocate_info = CommandBufferAllocateInfo::builder()
+        .command_buffer_count(1)
+        .command_pool(*command_pool)
+        .level(CommandBufferLevel::PRIMARY);
+
+    unsafe {
+        let command_buffer = device.allocate_command_buffers(&allocate_info).expect("Command buffer allocation failed!")[0];
+        let begin_info = CommandBufferBeginInfo::builder().flags(CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+        device.begin_command_buffer(command_buffer, &begin_info).expect("Begin cmd buffer failed!");
+        record(command_buffer);
+        device.end_command_buffer(command_buffer).expect("End cmd buffer failed!");
+
+        let fence: Fence = device.create_fence(&FenceCreateInfo::default(), None).expect("Create fence failed!");
+        let command_buffers = [command_buffer];
+        let submit_info = SubmitInfo::builder().command_buffers(&command_buffers);
+        device.queue_submit(queue, &[submit_info.build()], fence).expect("Queue submit failed!");
+        device.wait_for_fences(&[fence], true, u64::MAX).expect("Wait for fence failed!");
+
+        device.destroy_fence(fence, None);
+        device.free_command_buffers(*command_pool, &command_buffers);
+    }
+}
+
+fn get_barrier(image: Image, range: ImageSubresourceRange, old_layout: ImageLayout, new_layout: ImageLayout,
+               src_access: AccessFlags, dst_access: AccessFlags) -> ImageMemoryBarrier {
+    ImageMemoryBarrier::builder()
+        .image(image)
+        .subresource_range(range)
+        .old_layout(old_layout)
+        .new_layout(new_layout)
+        .src_access_mask(src_access)
+        .dst_access_mask(dst_access)
+        .src_queue_family_index(QUEUE_FAMILY_IGNORED)
+        .dst_queue_family_index(QUEUE_FAMILY_IGNORED)
+        .build()
+}
+
+fn get_level(level: u32) -> ImageSubresourceRange {
+    ImageSubresourceRange { aspect_mask: ImageAspectFlags::COLOR, base_mip_level: level, level_count: 1, base_array_layer: 0, layer_count: 1 }
+}
+
+fn get_layers(level: u32) -> ImageSubresourceLayers {
+    ImageSubresourceLayers { aspect_mask: ImageAspectFlags::COLOR, mip_level: level, base_array_layer: 0, layer_count: 1 }
+}
+
+/// Far corner of a mip level. Levels halve in size, but never get smaller than one pixel.
+fn get_level_size(texture: &TextureImage, level: u32) -> Offset3D {
+    Offset3D {
+        x: (texture.width >> level).max(1) as i32,
+        y: (texture.height >> level).max(1) as i32,
+        z: 1,
+    }
+}
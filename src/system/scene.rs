@@ -0,0 +1,316 @@
+use std::fmt;
+use std::sync::Arc;
+use crate::system::math::matrix::Mat4;
+use crate::system::math::transform::Transform;
+use crate::system::rendering::material::MaterialInstance;
+use crate::system::rendering::mesh::bounds::Aabb;
+use crate::system::rendering::mesh::mesh::Mesh;
+use crate::system::rendering::render_list::RenderItem;
+
+/// Everything that can go wrong while changing the hierarchy of a scene.
+#[derive(Debug, PartialEq)]
+pub enum SceneError {
+    /// The node was removed or belongs to another scene.
+    InvalidNode { id: NodeId },
+    /// The new parent is the node itself or one of its descendants.
+    Cycle { id: NodeId, parent: NodeId },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::InvalidNode { id } => write!(f, "node {} does not exist", id.index),
+            SceneError::Cycle { id, parent } => write!(f, "node {} can not become a child of its descendant {}", id.index, parent.index),
+        }
+    }
+}
+
+/// Handle of a node. A handle of a removed node stays invalid, even if its slot is reused.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct NodeId {
+    index: u32,
+    generation: u32,
+}
+
+/// Component of an entity drawing a mesh at the world transform of its node. The material replaces the mesh's own material, if there is one.
+#[derive(Clone)]
+pub struct Renderable {
+    mesh: Arc<Mesh>,
+    pub material: Option<Arc<MaterialInstance>>,
+    /// Computed once, the mesh can not change behind the Arc.
+    bounds: Option<Aabb>,
+}
+
+impl Renderable {
+    pub fn new(mesh: Arc<Mesh>) -> Self {
+        let bounds = Aabb::from_vertices(&mesh.vertices);
+        Renderable { mesh, material: None, bounds }
+    }
+
+    pub fn get_mesh(&self) -> &Arc<Mesh> {
+        &self.mesh
+    }
+
+    /// Bounds of the mesh in its own space, None if it has no vertices.
+    pub fn get_bounds(&self) -> Option<Aabb> {
+        self.bounds
+    }
+
+    /// Item drawing the mesh at the world matrix, with the material of the renderable or else the mesh's own.
+    pub fn to_render_item(&self, transform: Mat4) -> RenderItem {
+        let material = self.material.clone().or_else(|| self.mesh.material.clone());
+        RenderItem { mesh: self.mesh.clone(), material, transform, bounds: self.bounds }
+    }
+}
+
+struct Node {
+    name: String,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    local_transform: Transform,
+    world_matrix: Mat4,
+    /// The local transform changed since the world matrix was computed. The world matrices of all descendants are outdated as well.
+    dirty: bool,
+}
+
+struct Slot {
+    generation: u32,
+    node: Option<Node>,
+}
+
+/// Hierarchy of nodes with transforms relative to their parent. World matrices are only recomputed by
+/// `update_transforms` for nodes whose transform or whose ancestors' transforms changed.
+#[derive(Default)]
+pub struct Scene {
+    slots: Vec<Slot>,
+    free_slots: Vec<u32>,
+    roots: Vec<NodeId>,
+    node_count: usize,
+}
+
+impl Scene {
+    pub fn new() -> Self {
+        Scene::default()
+    }
+
+    /// Adds a node below the parent, or a root node without parent.
+    pub fn add_node(&mut self, name: &str, parent: Option<NodeId>, transform: Transform) -> Result<NodeId, SceneError> {
+        if let Some(parent) = parent {
+            self.get_node(parent)?;
+        }
+
+        let node = Node {
+            name: name.to_string(),
+            parent,
+            children: Vec::new(),
+            local_transform: transform,
+            world_matrix: Mat4::IDENTITY,
+            dirty: true,
+        };
+        let id = match self.free_slots.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index as usize];
+                slot.node = Some(node);
+                NodeId { index, generation: slot.generation }
+            }
+            None => {
+                self.slots.push(Slot { generation: 0, node: Some(node) });
+                NodeId { index: (self.slots.len() - 1) as u32, generation: 0 }
+            }
+        };
+
+        match parent {
+            Some(parent) => self.get_node_mut(parent)?.children.push(id),
+            None => self.roots.push(id),
+        }
+        self.node_count += 1;
+        Ok(id)
+    }
+
+    /// Removes the node together with all of its descendants.
+    pub fn remove_node(&mut self, id: NodeId) -> Result<(), SceneError> {
+        let parent = self.get_node(id)?.parent;
+        self.detach(id, parent);
+
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            let slot = &mut self.slots[id.index as usize];
+            if let Some(node) = slot.node.take() {
+                stack.extend(node.children);
+                slot.generation = slot.generation.wrapping_add(1);
+                self.free_slots.push(id.index);
+                self.node_count -= 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves the node below another parent, or makes it a root node. Its local transform is kept, so it moves with the new parent.
+    pub fn set_parent(&mut self, id: NodeId, parent: Option<NodeId>) -> Result<(), SceneError> {
+        let old_parent = self.get_node(id)?.parent;
+        if let Some(parent) = parent {
+            // Walking up from the new parent must not reach the node itself.
+            let mut ancestor = Some(parent);
+            while let Some(current) = ancestor {
+                if current == id {
+                    return Err(SceneError::Cycle { id, parent });
+                }
+                ancestor = self.get_node(current)?.parent;
+            }
+        }
+
+        self.detach(id, old_parent);
+        match parent {
+            Some(parent) => self.get_node_mut(parent)?.children.push(id),
+            None => self.roots.push(id),
+        }
+        let node = self.get_node_mut(id)?;
+        node.parent = parent;
+        node.dirty = true;
+        Ok(())
+    }
+
+    pub fn get_parent(&self, id: NodeId) -> Result<Option<NodeId>, SceneError> {
+        Ok(self.get_node(id)?.parent)
+    }
+
+    pub fn get_children(&self, id: NodeId) -> Result<&[NodeId], SceneError> {
+        Ok(&self.get_node(id)?.children)
+    }
+
+    pub fn get_roots(&self) -> &[NodeId] {
+        &self.roots
+    }
+
+    pub fn get_name(&self, id: NodeId) -> Result<&str, SceneError> {
+        Ok(&self.get_node(id)?.name)
+    }
+
+    /// First node with the name, in no particular order.
+    pub fn find_node(&self, name: &str) -> Option<NodeId> {
+        self.slots.iter().enumerate()
+            .find(|(_, slot)| slot.node.as_ref().map(|node| node.name == name).unwrap_or(false))
+            .map(|(index, slot)| NodeId { index: index as u32, generation: slot.generation })
+    }
+
+    pub fn get_node_count(&self) -> usize {
+        self.node_count
+    }
+
+    pub fn contains(&self, id: NodeId) -> bool {
+        self.get_node(id).is_ok()
+    }
+
+    pub fn get_local_transform(&self, id: NodeId) -> Result<Transform, SceneError> {
+        Ok(self.get_node(id)?.local_transform)
+    }
+
+    pub fn set_local_transform(&mut self, id: NodeId, transform: Transform) -> Result<(), SceneError> {
+        let node = self.get_node_mut(id)?;
+        node.local_transform = transform;
+        node.dirty = true;
+        Ok(())
+    }
+
+    /// World matrix as of the last `update_transforms`.
+    pub fn get_world_matrix(&self, id: NodeId) -> Result<Mat4, SceneError> {
+        Ok(self.get_node(id)?.world_matrix)
+    }
+
+    /// Recomputes the world matrices of all dirty nodes and their descendants. Returns the number of updated nodes.
+    pub fn update_transforms(&mut self) -> usize {
+        let mut updated = 0;
+        let mut stack: Vec<(NodeId, Mat4, bool)> = self.roots.iter().map(|&root| (root, Mat4::IDENTITY, false)).collect();
+        while let Some((id, parent_matrix, parent_changed)) = stack.pop() {
+            let node = match self.slots[id.index as usize].node.as_mut() {
+                Some(node) => node,
+                None => continue,
+            };
+            let changed = parent_changed || node.dirty;
+            if changed {
+                node.world_matrix = parent_matrix * node.local_transform.to_matrix();
+                node.dirty = false;
+                updated += 1;
+            }
+            let world_matrix = node.world_matrix;
+            stack.extend(node.children.iter().map(|&child| (child, world_matrix, changed)));
+        }
+        updated
+    }
+
+    fn detach(&mut self, id: NodeId, parent: Option<NodeId>) {
+        match parent.and_then(|parent| self.get_node_mut(parent).ok()) {
+            Some(parent) => parent.children.retain(|&child| child != id),
+            None => self.roots.retain(|&root| root != id),
+        }
+    }
+
+    fn get_node(&self, id: NodeId) -> Result<&Node, SceneError> {
+        self.slots.get(id.index as usize)
+            .filter(|slot| slot.generation == id.generation)
+            .and_then(|slot| slot.node.as_ref())
+            .ok_or(SceneError::InvalidNode { id })
+    }
+
+    fn get_node_mut(&mut self, id: NodeId) -> Result<&mut Node, SceneError> {
+        self.slots.get_mut(id.index as usize)
+            .filter(|slot| slot.generation == id.generation)
+            .and_then(|slot| slot.node.as_mut())
+            .ok_or(SceneError::InvalidNode { id })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::math::vector::Vec3;
+
+    #[test]
+    fn world_transforms_follow_the_parents() {
+        let mut scene = Scene::new();
+        let parent = scene.add_node("parent", None, Transform::from_translation(Vec3::new(1.0, 0.0, 0.0))).unwrap();
+        let child = scene.add_node("child", Some(parent), Transform::from_translation(Vec3::new(0.0, 2.0, 0.0))).unwrap();
+        assert_eq!(scene.update_transforms(), 2);
+        assert_eq!(scene.get_world_matrix(child).unwrap().get_translation(), Vec3::new(1.0, 2.0, 0.0));
+
+        // Nothing changed, nothing is recomputed.
+        assert_eq!(scene.update_transforms(), 0);
+
+        scene.set_local_transform(parent, Transform::from_scale(Vec3::splat(2.0))).unwrap();
+        assert_eq!(scene.update_transforms(), 2);
+        assert_eq!(scene.get_world_matrix(child).unwrap().get_translation(), Vec3::new(0.0, 4.0, 0.0));
+    }
+
+    #[test]
+    fn reparenting_rejects_cycles() {
+        let mut scene = Scene::new();
+        let a = scene.add_node("a", None, Transform::IDENTITY).unwrap();
+        let b = scene.add_node("b", Some(a), Transform::IDENTITY).unwrap();
+        let c = scene.add_node("c", Some(b), Transform::IDENTITY).unwrap();
+        assert_eq!(scene.set_parent(a, Some(c)), Err(SceneError::Cycle { id: a, parent: c }));
+
+        scene.set_parent(c, None).unwrap();
+        assert_eq!(scene.get_roots(), &[a, c]);
+        assert!(scene.get_children(b).unwrap().is_empty());
+        assert_eq!(scene.find_node("c"), Some(c));
+    }
+
+    #[test]
+    fn removed_subtrees_invalidate_their_handles() {
+        let mut scene = Scene::new();
+        let root = scene.add_node("root", None, Transform::IDENTITY).unwrap();
+        let group = scene.add_node("cube", Some(root), Transform::IDENTITY).unwrap();
+        let mesh = scene.add_node("cube.0", Some(group), Transform::IDENTITY).unwrap();
+        assert_eq!(scene.get_node_count(), 3);
+
+        scene.remove_node(group).unwrap();
+        assert_eq!(scene.get_node_count(), 1);
+        assert!(!scene.contains(mesh));
+        assert!(scene.get_children(root).unwrap().is_empty());
+        assert_eq!(scene.get_name(group), Err(SceneError::InvalidNode { id: group }));
+
+        // The slot is reused, the old handle still does not reach the new node.
+        let other = scene.add_node("other", None, Transform::IDENTITY).unwrap();
+        assert!(!scene.contains(group) && scene.contains(other));
+    }
+}
@@ -1,10 +1,17 @@
+use std::sync::Arc;
 use num_format::Locale::se;
-use crate::system::ini::{WindowIniData, WindowMode};
-use winit::{event::{Event, WindowEvent}, event_loop::{ControlFlow, EventLoop}, window::{WindowBuilder, Window}};
+use crate::system::ini::{RenderingIniData, WindowIniData, WindowMode};
+use winit::{event::{DeviceEvent, Event, WindowEvent}, event_loop::{ControlFlow, EventLoop}, window::{WindowBuilder, Window}};
 use winit::dpi::{LogicalSize};
+use crate::system::assets::manager::AssetManager;
+use crate::system::ecs::schedule::Schedule;
+use crate::system::ecs::world::World;
 use crate::system::rendering::IRenderer;
-use crate::system::rendering::mesh::mesh::Mesh;
+use crate::system::rendering::camera_uniform::CameraUniform;
+use crate::system::rendering::render_list::{CullStats, RenderList};
 use crate::system::rendering::mesh::vertex::Vertex;
+use crate::system::log::Log;
+use crate::system::rendering::software::renderer::SoftwareRenderer;
 use crate::system::rendering::vulkan::renderer::VkRenderer;
 
 pub struct ResaWindow {
@@ -16,11 +23,38 @@ pub struct ResaWindow {
     window: Window,
     event_loop: EventLoop<()>,
     os: String,
+    assets: Arc<AssetManager>,
+}
+
+/// Window events that arrived since the last frame, in their order. Pushed by the event loop and drained by the input system.
+#[derive(Default)]
+pub struct WindowEvents(pub Vec<WindowEvent<'static>>);
+
+/// Mouse motion events that arrived since the last frame, also pushed by the event loop and drained by the input system.
+#[derive(Default)]
+pub struct DeviceEvents(pub Vec<DeviceEvent>);
+
+/// Whether the cursor is hidden and kept inside the window, e.g. while the mouse turns the camera. Set by the systems and applied after each frame.
+#[derive(Default)]
+pub struct CursorGrab(pub bool);
+
+/// Size of the drawable area of the window in physical pixels, zero while it is minimized.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct WindowSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// What the renderer draws this frame, filled by the systems of the render extract stage.
+#[derive(Default)]
+pub struct RenderFrame {
+    pub camera: CameraUniform,
+    pub render_list: RenderList,
 }
 
 impl ResaWindow {
 
-    pub fn init(ini_data: &WindowIniData, os_name: &str) -> ResaWindow {
+    pub fn init(ini_data: &WindowIniData, rendering_data: &RenderingIniData, os_name: &str) -> ResaWindow {
         let event_loop = EventLoop::new();
 
         let window_size = LogicalSize::new(ini_data.window_width, ini_data.window_height);
@@ -41,7 +75,13 @@ impl ResaWindow {
         // if self.os == "Darwin" {
         //     println!("Init metal rs here!");
         // } else {
-        let renderer_loop = Box::new(VkRenderer::new(&window));
+        let assets = Arc::new(AssetManager::new());
+        let renderer_loop: Box<dyn IRenderer> = if VkRenderer::is_available() {
+            Box::new(VkRenderer::new(&window, rendering_data, assets.clone()))
+        } else {
+            Log::get().write_warning("No vulkan loader found! Falling back to the software renderer.");
+            Box::new(SoftwareRenderer::new(&window, rendering_data, assets.clone()))
+        };
         // }
 
 
@@ -54,6 +94,7 @@ impl ResaWindow {
             window,
             event_loop,
             os: os_name.to_string(),
+            assets,
         };
 
         instance
@@ -64,10 +105,22 @@ impl ResaWindow {
         name
     }
 
-    pub fn run_window_loop(self) {
+    pub fn get_assets(&self) -> Arc<AssetManager> {
+        self.assets.clone()
+    }
+
+    pub fn get_size(&self) -> WindowSize {
+        let size = self.window.inner_size();
+        WindowSize { width: size.width, height: size.height }
+    }
+
+    /// Runs the event loop until the window is closed. Every frame the schedule runs against the world,
+    /// with the window events of the frame in `WindowEvents` and the mouse motion in `DeviceEvents`, and the renderer draws the `RenderFrame` the systems extracted.
+    /// Its culling results are stored in the `CullStats` resource for the next frame, and the cursor is grabbed or released as `CursorGrab` asks.
+    pub fn run_window_loop(self, mut world: World, mut schedule: Schedule) {
         let win = self.window;
         let mut renderer = self.renderer_loop;
-
+        let mut cursor_grabbed = false;
 
         self.event_loop.run(move |event, _, control_flow| {
             *control_flow = ControlFlow::Poll;
@@ -77,17 +130,52 @@ impl ResaWindow {
                     event: WindowEvent::CloseRequested, ..
                 } => {
                     *control_flow = ControlFlow::Exit;
-                    renderer.dispose();
+                }
+                Event::WindowEvent {
+                    event: WindowEvent::Resized(size), ..
+                } => {
+                    *world.resource_mut::<WindowSize>() = WindowSize { width: size.width, height: size.height };
+                    renderer.recreate_pipelines(size.width, size.height);
+                }
+                Event::WindowEvent {
+                    event: WindowEvent::ScaleFactorChanged { new_inner_size, .. }, ..
+                } => {
+                    *world.resource_mut::<WindowSize>() = WindowSize { width: new_inner_size.width, height: new_inner_size.height };
+                    renderer.recreate_pipelines(new_inner_size.width, new_inner_size.height);
+                }
+                Event::WindowEvent { event, .. } => {
+                    if let Some(event) = event.to_static() {
+                        world.resource_mut::<WindowEvents>().0.push(event);
+                    }
+                }
+                Event::DeviceEvent { event: event @ DeviceEvent::MouseMotion { .. }, .. } => {
+                    world.resource_mut::<DeviceEvents>().0.push(event);
                 }
                 Event::MainEventsCleared => {
                     win.request_redraw();
                 }
-                Event::RedrawRequested(_) => {}
+                Event::RedrawRequested(_) => {
+                    schedule.run(&mut world);
+                    let frame = world.resource::<RenderFrame>();
+                    renderer.render(&frame.camera, &frame.render_list);
+                    *world.resource_mut::<CullStats>() = renderer.get_cull_stats();
+
+                    let grab = world.resource::<CursorGrab>().0;
+                    if grab != cursor_grabbed {
+                        cursor_grabbed = grab;
+                        // Not every platform can confine the cursor. It is still hidden then and mouse motion keeps turning the camera.
+                        if let Err(e) = win.set_cursor_grab(grab) {
+                            Log::get().write_error(format!("Grabbing the cursor failed: {}", e).as_str());
+                        }
+                        win.set_cursor_visible(!grab);
+                    }
+                }
+                // The loop still draws after the close request, so the renderer lives until the loop is gone.
+                Event::LoopDestroyed => {
+                    renderer.dispose();
+                }
                 _ => ()
             }
-
-            let meshes = [Mesh { vertices: Box::new([]), indices: Box::new([]), faces: Box::new([]) }];
-            renderer.render(&meshes);
         });
     }
-}
\ No newline at end of file
+}